@@ -1,10 +1,14 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult
+    to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError,
+    StdResult,
 };
 use cw_ownable::{assert_owner, get_ownership, initialize_owner};
-use cw_tba::{ExecuteMsg, QueryMsg};
+use cw_tba::{
+    query_staking, try_handling_ics721_ack, try_syncing_known_tokens,
+    try_updating_known_cw20_on_receive, ExecuteMsg, QueryMsg, SignedCredentials,
+};
 use saa_wasm::account_number;
 
 #[cfg(target_arch = "wasm32")]
@@ -12,11 +16,22 @@ use crate::utils::query_if_registry;
 use crate::{
     error::ContractError,
     execute::{
-        try_changing_data, try_executing, try_executing_actions, try_freezing, try_purging, try_updating_known_on_receive, try_updating_known_tokens, try_updating_ownership, MINT_REPLY_ID
+        try_changing_data, try_executing, try_executing_actions, try_executing_as_executor,
+        try_executing_signed, try_freezing, try_handling_payments, try_purging,
+        try_receiving_ics721, try_updating_known_on_receive, try_updating_known_tokens,
+        try_updating_ownership, MINT_REPLY_ID,
     },
     msg::{InstantiateMsg, MigrateMsg, Status},
-    query::{assets, can_execute, full_info, known_tokens, valid_signature},
-    state::{MINT_CACHE, PUBKEY, REGISTRY_ADDRESS, STATUS, TOKEN_INFO},
+    query::{
+        account_tree, assets, can_execute, can_execute_native, can_execute_signed, executors,
+        fee_grants, full_info, known_collections, known_tokens, plugins, policy, policy_simulation,
+        pull_authorizations, queued_actions, scheduled_payments, token_approvals, token_attributes,
+        valid_signature, valid_signatures, watched_collections,
+    },
+    state::{
+        rebuild_known_collections, IBC_CONFIG, MINT_CACHE, PUBKEY, RECEIVE_CONFIG, REGISTRY_ADDRESS,
+        REMOTE_HOLDER, STATUS, TIMELOCK, TOKEN_INFO,
+    },
     utils::extract_pubkey,
 };
 
@@ -96,20 +111,43 @@ pub fn execute(
 
         ExecuteMsg::ReceiveNft(
             msg
-        ) => try_updating_known_on_receive(deps, info.sender.to_string(), msg.token_id),
+        ) => try_updating_known_on_receive(deps, &env, info.sender.to_string(), msg.token_id, msg.msg),
+
+        ExecuteMsg::Receive(
+            msg
+        ) => Ok(try_updating_known_cw20_on_receive(deps.storage, info.sender.to_string(), msg.amount)?),
+
+        ExecuteMsg::Ics721AckCallback(
+            msg
+        ) => Ok(try_handling_ics721_ack(deps, &env, info, msg)?),
+
+        ExecuteMsg::Ics721ReceiveCallback(
+            msg
+        ) => try_receiving_ics721(deps, &env, info, msg),
+
+        ExecuteMsg::Recovery(_) | ExecuteMsg::Approvals(_) => Err(ContractError::NotSupported {}),
+
+        ExecuteMsg::Payments(msg) => try_handling_payments(deps.storage, &env, &info, msg),
+
+        ExecuteMsg::Executor(msg) => try_executing_as_executor(deps, &env, info, msg),
 
         ExecuteMsg::Purge {} => try_purging(deps, info.sender),
 
         ExecuteMsg::Freeze {} => try_freezing(&deps.querier, deps.storage, info.sender),
 
-        ExecuteMsg::Execute { msgs, .. } => try_executing(deps.as_ref(), info.sender, msgs),
+        ExecuteMsg::SyncKnownTokens { limit } => Ok(try_syncing_known_tokens(deps, env, limit)?),
+
+        ExecuteMsg::Execute { msgs, .. } => try_executing(deps, &env, &info, msgs),
 
         ExecuteMsg::ExecuteNative { msgs } => {
             assert_owner(deps.storage, &info.sender)?;
             try_executing_actions(deps, &env, &info, msgs)
         },
-        
-        _ => Err(ContractError::NotSupported {}),
+
+        ExecuteMsg::ExecuteSigned { msgs, signed, .. } => match signed {
+            SignedCredentials::Single(cred) => try_executing_signed(deps, env, info, cred, msgs),
+            SignedCredentials::Multiple(_) => Err(ContractError::NotSupported {}),
+        },
     }?;
 
     Ok(res)
@@ -125,31 +163,56 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::Status {} => to_json_binary(&STATUS.load(deps.storage)?),
         QueryMsg::AccountNumber {} => to_json_binary(&account_number(deps.storage)),
         QueryMsg::Registry {} => to_json_binary(&REGISTRY_ADDRESS.load(deps.storage)?),
+        QueryMsg::RemoteHolder {} => to_json_binary(&REMOTE_HOLDER.may_load(deps.storage)?),
         QueryMsg::Ownership {} => to_json_binary(&get_ownership(deps.storage)?),
-        QueryMsg::CanExecute { sender, msg } => to_json_binary(&can_execute(deps, sender, &msg)?),
-        QueryMsg::KnownTokens { skip, limit } => to_json_binary(&known_tokens(deps, skip, limit)?),
-        QueryMsg::Assets { skip, limit } => to_json_binary(&assets(deps, env, skip, limit)?),
-        QueryMsg::FullInfo { skip, limit } => to_json_binary(&full_info(deps, env, skip, limit)?),
+        QueryMsg::CanExecute { sender, msg } => to_json_binary(&can_execute(deps, env, sender, &msg)?),
+        QueryMsg::KnownTokens { collection, start_after, limit, with_metadata } => to_json_binary(&known_tokens(deps, collection, start_after, limit, with_metadata)?),
+        QueryMsg::KnownCollections { start_after, limit } => to_json_binary(&known_collections(deps, start_after, limit)?),
+        QueryMsg::Assets { skip, limit, with_metadata } => to_json_binary(&assets(deps, env, skip, limit, with_metadata)?),
+        QueryMsg::Staking {} => to_json_binary(&query_staking(&deps.querier, env.contract.address.as_str())?),
+        QueryMsg::FeeGrants { start_after, limit } => to_json_binary(&fee_grants(deps, start_after, limit)?),
+        QueryMsg::Timelock {} => to_json_binary(&TIMELOCK.may_load(deps.storage)?),
+        QueryMsg::QueuedActions { start_after, limit } => to_json_binary(&queued_actions(deps, start_after, limit)?),
+        QueryMsg::ScheduledPayments { start_after, limit } => to_json_binary(&scheduled_payments(deps, start_after, limit)?),
+        QueryMsg::PullAuthorizations { start_after, limit } => to_json_binary(&pull_authorizations(deps, start_after, limit)?),
+        QueryMsg::Executors { start_after, limit } => to_json_binary(&executors(deps, start_after, limit)?),
+        QueryMsg::Plugins {} => to_json_binary(&plugins(deps)?),
+        QueryMsg::Policy {} => to_json_binary(&policy(deps)?),
+        QueryMsg::SimulatePolicy { actions } => to_json_binary(&policy_simulation(deps, env, actions)?),
+        QueryMsg::WatchedCollections {} => to_json_binary(&watched_collections(deps)?),
+        QueryMsg::TokenApprovals {} => to_json_binary(&token_approvals(deps)?),
+        QueryMsg::ReceiveConfig {} => to_json_binary(&RECEIVE_CONFIG.may_load(deps.storage)?.unwrap_or_default()),
+        QueryMsg::TokenAttributes { collection, token_id } => to_json_binary(&token_attributes(deps, collection, token_id)?),
+        QueryMsg::AccountTree { depth } => to_json_binary(&account_tree(deps, env, depth)?),
+        QueryMsg::Recovery {} | QueryMsg::TwoFactor {} | QueryMsg::PendingApprovals { .. } => Err(StdError::generic_err(ContractError::NotSupported {}.to_string())),
+        QueryMsg::IbcConfig {} => to_json_binary(&IBC_CONFIG.may_load(deps.storage)?.unwrap_or_default()),
+        QueryMsg::FullInfo { skip, limit, with_metadata } => to_json_binary(&full_info(deps, env, skip, limit, with_metadata)?),
         QueryMsg::ValidSignature {
             signature,
             data,
             payload,
         } => to_json_binary(&valid_signature(deps, data, signature, &payload)?),
-        QueryMsg::ValidSignatures { .. } => Err(StdError::generic_err(
-            "ValidSignatures query is not supported for now",
-        )), 
-        QueryMsg::CanExecuteSigned { .. } => Err(StdError::generic_err(
-            "CanExecuteSigned query is not supported for now",
-        )),
-        QueryMsg::CanExecuteNative { .. } => Err(StdError::generic_err(
-            "CanExecuteNative query is not supported for now",
-        ))
+        QueryMsg::ValidSignatures {
+            signatures,
+            data,
+            payload,
+        } => to_json_binary(&valid_signatures(deps, data, signatures, &payload)?),
+        QueryMsg::CanExecuteSigned { 
+            msgs, 
+            signed, 
+            .. 
+        } => to_json_binary(&can_execute_signed(deps, env, signed, msgs)?),
+        QueryMsg::CanExecuteNative { 
+            sender, 
+            msg 
+        } => to_json_binary(&can_execute_native(deps, env, sender, msg)?),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(deps: DepsMut, _: Env, _: MigrateMsg) -> StdResult<Response> {
     STATUS.save(deps.storage, &Status { frozen: false })?;
+    rebuild_known_collections(deps.storage)?;
     Ok(Response::default())
 }
 
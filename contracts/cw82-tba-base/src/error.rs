@@ -1,5 +1,6 @@
 use cosmwasm_std::StdError;
 use cw_ownable::OwnershipError;
+use cw_tba::AccountError;
 use cw_utils::ParseReplyError;
 use saa_wasm::saa_types::AuthError;
 use thiserror::Error;
@@ -12,6 +13,9 @@ pub enum ContractError {
     #[error("{0}")]
     Ownership(#[from] OwnershipError),
 
+    #[error("{0}")]
+    Account(#[from] AccountError),
+
     #[error("{0}")]
     Parse(#[from] ParseReplyError),
 
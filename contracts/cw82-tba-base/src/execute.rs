@@ -1,32 +1,98 @@
 use crate::{
     error::ContractError,
     msg::Status,
-    state::{KNOWN_TOKENS, MINT_CACHE, PUBKEY, REGISTRY_ADDRESS, STATUS, TOKEN_INFO},
-    utils::{assert_ok_cosmos_msg, assert_registry, assert_status, extract_pubkey},
+    state::{
+        remove_known_token, save_known_token, EXECUTORS, FEE_GRANTS, IBC_CONFIG, KNOWN_COLLECTIONS,
+        KNOWN_CW20S, KNOWN_TOKENS, MINT_CACHE, PAYMENTS, PAYMENT_COUNT, PLUGINS, POLICY, PUBKEY,
+        PULLS, QUEUE, QUEUE_COUNT, RECEIVE_CONFIG, REGISTRY_ADDRESS, REMOTE_HOLDER, STATUS,
+        TIMELOCK, TOKEN_APPROVALS, TOKEN_ATTRIBUTES, TOKEN_INFO, WATCHED_COLLECTIONS,
+    },
+    utils::{
+        assert_not_frozen, assert_registry, assert_status, builtin_policy, extract_pubkey,
+        verify_signed_actions,
+    },
 };
 use cosmwasm_std::{
-    ensure, Addr, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
-    QuerierWrapper, ReplyOn, Response, StdResult, SubMsg, WasmMsg,
+    ensure, Addr, Binary, Coin, CosmosMsg, DepsMut, Env,
+    MessageInfo, QuerierWrapper, ReplyOn, Response, StdResult, SubMsg, WasmMsg,
 };
 use cw_ownable::{assert_owner, get_ownership, is_owner, OwnershipError};
-use cw_tba::{encode_feegrant_msg, query_tokens, transfer_nft_msg, verify_nft_ownership, BasicAllowance, ActiontMsg};
-use saa_wasm::UpdateOperation;
-use smart_account_auth::VerifiedData;
+use cw_tba::{
+    assert_no_cycle, assert_plugins_validation, assert_policy, collection_backend,
+    plugin_hook_msgs, remote_holder_actions, revoke_token_approvals, take_ready_action,
+    try_approving, try_authorizing_pull, try_burning_token, try_cancelling_payment,
+    try_cancelling_queued, try_claiming_rewards, try_cranking_payments, try_delegating,
+    try_executing_on_child, try_fee_granting, try_granting_executor, try_handling_ics721_receive,
+    try_ibc_transfering, try_installing_plugin, try_pulling, try_queueing_action, try_redelegating,
+    try_revoking, try_revoking_executor, try_revoking_fee_grant, try_revoking_pull,
+    try_scheduling_payment, try_sending_cw20, try_sending_token, try_setting_withdraw_address,
+    try_transfering_cw20, try_transfering_token, try_transfering_tokens, try_undelegating,
+    try_uninstalling_plugin, try_updating_ibc_config, try_updating_policy,
+    try_updating_receive_config, try_updating_timelock, try_updating_watched_collections,
+    try_voting, try_voting_weighted, try_withdrawing_all, use_executor_grant, verify_nft_ownership,
+    ActiontMsg, ExecutorMsg, Ics721ReceiveCallbackMsg, PaymentMsg, ReceivePayload, TokenInfo,
+};
+use saa_wasm::{stores::ACCOUNT_NUMBER, UpdateOperation};
+use smart_account_auth::{Credential, VerifiedData};
 
 pub const MINT_REPLY_ID: u64 = 1;
 
 
 pub fn try_executing(
-    deps: Deps,
-    sender: Addr,
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
     msgs: Vec<CosmosMsg>,
 ) -> Result<Response, ContractError> {
-    assert_owner(deps.storage, &sender)?;
+    assert_owner(deps.storage, &info.sender)?;
+    // goes through the same checks as the proxy action, including the timelock
+    try_executing_actions(deps, env, info, vec![ActiontMsg::Execute { msgs }])
+}
+
+pub fn try_executing_signed(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cred: Credential,
+    actions: Vec<ActiontMsg>,
+) -> Result<Response, ContractError> {
     assert_status(deps.storage)?;
+    let num = verify_signed_actions(deps.as_ref(), &env, &cred, &actions)?;
+    ACCOUNT_NUMBER.save(deps.storage, &num)?;
+
+    // the actions are executed on behalf of the owner who signed them and not the relayer
+    let owner = get_ownership(deps.storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized {})?;
+    let info = MessageInfo {
+        sender: owner,
+        funds: info.funds,
+    };
+    try_executing_actions(deps, &env, &info, actions)
+}
 
-    msgs.iter().try_for_each(|msg| assert_ok_cosmos_msg(&msg))?;
+pub fn try_executing_as_executor(
+    deps: DepsMut,
+    env: &Env,
+    info: MessageInfo,
+    msg: ExecutorMsg,
+) -> Result<Response, ContractError> {
+    let fee = use_executor_grant(&deps.querier, deps.storage, env, info.sender.as_str(), &msg)?;
+
+    // the actions are executed on behalf of the owner and not the executor
+    let owner = get_ownership(deps.storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized {})?;
+    let executor = info.sender.to_string();
+    let info = MessageInfo {
+        sender: owner,
+        funds: vec![],
+    };
+    let res = try_executing_actions(deps, env, &info, vec![msg.into_action()])?;
 
-    Ok(Response::new().add_messages(msgs))
+    Ok(res
+        .add_messages(fee)
+        .add_attribute("executor", executor))
 }
 
 pub fn try_executing_actions(
@@ -35,9 +101,59 @@ pub fn try_executing_actions(
     info: &MessageInfo,
     actions: Vec<ActiontMsg>,
 ) -> Result<Response, ContractError> {
+    assert_status(deps.storage)?;
+    execute_actions(deps, env, info, actions)
+}
+
+/// Keep track of a received packet and run the actions the holder of the voucher sent along while the token is away
+pub fn try_receiving_ics721(
+    mut deps: DepsMut,
+    env: &Env,
+    info: MessageInfo,
+    msg: Ics721ReceiveCallbackMsg,
+) -> Result<Response, ContractError> {
+    let actions = remote_holder_actions(&deps.querier, deps.storage, info.sender.as_str(), &msg)?;
+    let holder = msg.original_packet.sender.clone();
+    let res = try_handling_ics721_receive(deps.branch(), env, info, msg)?;
+    let actions = match actions {
+        Some(actions) => actions,
+        None => return Ok(res),
+    };
+
+    // the actions are executed on behalf of the owner and not the bridge
+    let owner = get_ownership(deps.storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized {})?;
+    let info = MessageInfo {
+        sender: owner,
+        funds: vec![],
+    };
+    let remote_res = execute_actions(deps, env, &info, actions)?;
+
+    Ok(res
+        .add_submessages(remote_res.messages)
+        .add_events(remote_res.events)
+        .add_attributes(remote_res.attributes)
+        .add_attribute("remote_holder", holder))
+}
+
+fn execute_actions(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    actions: Vec<ActiontMsg>,
+) -> Result<Response, ContractError> {
+    assert_policy(deps.storage, env, &builtin_policy(), &actions, &info.funds)?;
+    assert_plugins_validation(deps.as_ref(), env, info.sender.as_str(), &actions)?;
+    let hooks = plugin_hook_msgs(deps.storage, env, info.sender.as_str(), &actions)?;
+    let delayed = match TIMELOCK.may_load(deps.storage)? {
+        Some(timelock) => timelock.delayed_actions(&actions),
+        None => vec![false; actions.len()],
+    };
+
     let mut res = Response::new();
-    for act in actions {
-        let action_res = execute_action(&deps.querier, deps.storage, &env, &info, act)?;
+    for (act, delayed) in actions.into_iter().zip(delayed) {
+        let action_res = execute_action(&deps.querier, deps.storage, env, info, act, delayed)?;
         res = res
             .add_submessages(action_res.messages)
             .add_events(action_res.events)
@@ -46,7 +162,7 @@ pub fn try_executing_actions(
             res = res.set_data(data);
         }
     }
-    Ok(res)
+    Ok(res.add_messages(hooks))
 }
 
 pub fn execute_action(
@@ -55,16 +171,28 @@ pub fn execute_action(
     env: &Env,
     info: &MessageInfo,
     msg: ActiontMsg,
+    delayed: bool,
+) -> Result<Response, ContractError> {
+    assert_not_frozen(storage)?;
+
+    if delayed {
+        let timelock = TIMELOCK.load(storage)?;
+        return Ok(try_queueing_action(storage, env, &timelock, msg)?);
+    }
+    run_action(querier, storage, env, info, msg)
+}
+
+fn run_action(
+    querier: &QuerierWrapper,
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &Env,
+    info: &MessageInfo,
+    msg: ActiontMsg,
 ) -> Result<Response, ContractError> {
-    assert_status(storage)?;
     use ActiontMsg::*;
 
     match msg {
-        Execute { msgs } => {
-            msgs.iter().try_for_each(|msg| assert_ok_cosmos_msg(&msg))?;
-
-            Ok(Response::new().add_messages(msgs))
-        }
+        Execute { msgs } => Ok(Response::new().add_messages(msgs)),
 
         MintToken { minter, msg } => try_minting_token(
             storage,
@@ -78,22 +206,78 @@ pub fn execute_action(
             collection,
             token_id,
             recipient,
-        } => try_transfering_token(env, storage, collection, token_id, recipient, info.funds.clone()),
+        } => Ok(try_transfering_token(querier, env, storage, collection, token_id, recipient)?),
+
+        TransferTokens {
+            collection,
+            token_ids,
+            recipient,
+        } => Ok(try_transfering_tokens(querier, env, storage, collection, token_ids, recipient)?),
+
+        WithdrawAll {
+            recipient,
+            include_native,
+            collections,
+            limit,
+        } => Ok(try_withdrawing_all(querier, env, storage, recipient, include_native, collections, limit)?),
 
-        #[cfg(not(feature = "omniflix"))]
         SendToken {
             collection,
             token_id,
             contract,
             msg,
-        } => try_sending_token(
+        } => Ok(try_sending_token(
+            querier,
+            env,
             storage,
             collection,
             token_id,
             contract,
             msg,
             info.funds.clone(),
-        ),
+        )?),
+
+        Approve {
+            collection,
+            token_id,
+            spender,
+            expires,
+        } => Ok(try_approving(querier, env, storage, collection, Some(token_id), spender, expires)?),
+
+        Revoke {
+            collection,
+            token_id,
+            spender,
+        } => Ok(try_revoking(querier, storage, collection, Some(token_id), spender)?),
+
+        ApproveAll {
+            collection,
+            operator,
+            expires,
+        } => Ok(try_approving(querier, env, storage, collection, None, operator, expires)?),
+
+        RevokeAll {
+            collection,
+            operator,
+        } => Ok(try_revoking(querier, storage, collection, None, operator)?),
+
+        Burn {
+            collection,
+            token_id,
+        } => Ok(try_burning_token(querier, env, storage, collection, token_id)?),
+
+        TransferCw20 {
+            contract,
+            amount,
+            recipient,
+        } => Ok(try_transfering_cw20(contract, amount, recipient)?),
+
+        SendCw20 {
+            contract,
+            amount,
+            recipient,
+            msg,
+        } => Ok(try_sending_cw20(contract, amount, recipient, msg)?),
 
         UpdateKnownTokens {
             collection,
@@ -118,13 +302,120 @@ pub fn execute_action(
 
         Unfreeze {} => try_unfreezing(querier, storage),
 
-        FeeGrant { grantee, allowance } => try_fee_granting(
-            storage,
-            env.contract.address.clone(),
-            info.sender.clone(),
+        FeeGrant {
             grantee,
             allowance,
-        ),
+            periodic,
+            allowed_msgs,
+        } => {
+            assert_owner(storage, &info.sender)?;
+            Ok(try_fee_granting(querier, storage, env, grantee, allowance, periodic, allowed_msgs)?)
+        }
+
+        RevokeFeeGrant { grantee } => {
+            assert_owner(storage, &info.sender)?;
+            Ok(try_revoking_fee_grant(querier, storage, env, grantee)?)
+        }
+
+        Delegate { validator, amount } => Ok(try_delegating(validator, amount)?),
+
+        Undelegate { validator, amount } => Ok(try_undelegating(validator, amount)?),
+
+        Redelegate {
+            src_validator,
+            dst_validator,
+            amount,
+        } => Ok(try_redelegating(src_validator, dst_validator, amount)?),
+
+        ClaimRewards { validators } => Ok(try_claiming_rewards(querier, env, validators)?),
+
+        SetWithdrawAddress { address } => Ok(try_setting_withdraw_address(address)?),
+
+        Vote {
+            proposal_id,
+            option,
+        } => Ok(try_voting(proposal_id, option)?),
+
+        VoteWeighted {
+            proposal_id,
+            options,
+        } => Ok(try_voting_weighted(proposal_id, options)?),
+
+        IbcTransfer {
+            channel,
+            to,
+            amount,
+            memo,
+            timeout,
+        } => Ok(try_ibc_transfering(storage, env, channel, to, amount, memo, timeout)?),
+
+        UpdateIbcConfig {
+            channels,
+            default_timeout,
+        } => {
+            assert_owner(storage, &info.sender)?;
+            Ok(try_updating_ibc_config(storage, channels, default_timeout)?)
+        }
+
+        UpdateTimelock {
+            delay,
+            thresholds,
+            tokens,
+        } => Ok(try_updating_timelock(storage, delay, thresholds, tokens)?),
+
+        ExecuteQueued { id } => try_executing_queued(querier, storage, env, info, id),
+
+        CancelQueued { id } => Ok(try_cancelling_queued(storage, id)?),
+
+        SchedulePayment {
+            recipient,
+            amount,
+            interval,
+            start,
+            end,
+        } => Ok(try_scheduling_payment(storage, env, recipient, amount, interval, start, end)?),
+
+        CancelPayment { id } => Ok(try_cancelling_payment(storage, id)?),
+
+        AuthorizePull {
+            merchant,
+            cap,
+            period,
+            end,
+        } => Ok(try_authorizing_pull(storage, env, merchant, cap, period, end)?),
+
+        RevokePull { merchant } => Ok(try_revoking_pull(storage, merchant)?),
+
+        GrantExecutor {
+            executor,
+            permissions,
+            expiration,
+            fee,
+            interval,
+        } => Ok(try_granting_executor(storage, executor, permissions, expiration, fee, interval)?),
+
+        RevokeExecutor { executor } => Ok(try_revoking_executor(storage, executor)?),
+
+        InstallPlugin { address, kind } => Ok(try_installing_plugin(querier, storage, env, address, kind)?),
+
+        UninstallPlugin { address } => Ok(try_uninstalling_plugin(storage, address)?),
+
+        UpdatePolicy { rules } => Ok(try_updating_policy(storage, rules)?),
+
+        UpdateReceiveConfig { reject_unknown } => Ok(try_updating_receive_config(storage, reject_unknown)?),
+
+        UpdateWatchedCollections { collections } => Ok(try_updating_watched_collections(storage, collections)?),
+
+        ExecuteOnChild {
+            token_info,
+            actions,
+        } => Ok(try_executing_on_child(querier, storage, env, token_info, actions)?),
+
+        UpdateGuardians { .. }
+        | VetoRecovery {}
+        | UpdateTwoFactor { .. }
+        | RemoveCredentials { .. }
+        | ApproveMigration { .. } => Err(ContractError::NotSupported {}),
     }
 }
 
@@ -136,7 +427,7 @@ pub fn try_minting_token(
     funds: Vec<Coin>,
 ) -> Result<Response, ContractError> {
     assert_owner(storage, &sender)?;
-    assert_status(storage)?;
+    assert_not_frozen(storage)?;
     MINT_CACHE.save(storage, &collection)?;
     Ok(Response::new()
     .add_submessage(SubMsg {
@@ -158,10 +449,13 @@ pub fn try_freezing(
     sender: Addr,
 ) -> Result<Response, ContractError> {
     let token = TOKEN_INFO.load(storage)?;
-    let owner = cw_ownable::get_ownership(storage)?.owner.unwrap();
+    let owner = cw_ownable::get_ownership(storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized {})?;
     if owner != sender {
         // check if current owner still holds the token
-        let verification = verify_nft_ownership(querier, owner.as_str(), token);
+        let backend = collection_backend(querier, storage, &token.collection)?;
+        let verification = verify_nft_ownership(querier, &backend, owner.as_str(), token.clone());
 
         if verification.is_ok() {
             // the token is not in escrow it isn't freezable by other entities
@@ -177,9 +471,12 @@ pub fn try_unfreezing(
     querier: &QuerierWrapper,
     storage: &mut dyn cosmwasm_std::Storage,
 ) -> Result<Response, ContractError> {
-    let owner = cw_ownable::get_ownership(storage)?.owner.unwrap();
+    let owner = cw_ownable::get_ownership(storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized {})?;
     let token = TOKEN_INFO.load(storage)?;
-    verify_nft_ownership(querier, owner.as_str(), token)?;
+    let backend = collection_backend(querier, storage, &token.collection)?;
+    verify_nft_ownership(querier, &backend, owner.as_str(), token)?;
     Ok(Response::default().add_attribute("action", "unfreeze"))
 }
 
@@ -194,6 +491,19 @@ pub fn try_updating_ownership(
     let ownership = get_ownership(deps.storage)?;
     let addr = deps.api.addr_validate(&new_owner)?;
 
+    // actions queued and payments set up by the previous holder must not run for the new one
+    TIMELOCK.remove(deps.storage);
+    QUEUE.clear(deps.storage);
+    PAYMENTS.clear(deps.storage);
+    PULLS.clear(deps.storage);
+    EXECUTORS.clear(deps.storage);
+    PLUGINS.clear(deps.storage);
+    POLICY.remove(deps.storage);
+    RECEIVE_CONFIG.remove(deps.storage);
+    REMOTE_HOLDER.remove(deps.storage);
+    // so that nobody approved by the previous holder can take the tokens
+    let revoke_msgs = revoke_token_approvals(&deps.querier, deps.storage, &env)?;
+
     if let Some(data) = new_data {
         let new_pubkey = extract_pubkey(data, &addr)?;
         PUBKEY.save(deps.storage, &new_pubkey)?;
@@ -204,7 +514,7 @@ pub fn try_updating_ownership(
         cw_ownable::update_ownership(
             deps,
             &env.block,
-            &ownership.owner.unwrap(),
+            &ownership.owner.ok_or(ContractError::Unauthorized {})?,
             cw_ownable::Action::TransferOwnership {
                 new_owner: new_owner.to_string(),
                 expiry: None,
@@ -213,6 +523,7 @@ pub fn try_updating_ownership(
     }
 
     Ok(Response::default()
+        .add_messages(revoke_msgs)
         .add_attribute("action", "update_ownership")
         .add_attribute("new_owner", new_owner.as_str()))
 }
@@ -226,7 +537,7 @@ pub fn try_changing_data(
     match op {
         UpdateOperation::Add(data) => {
             let ownershop = get_ownership(deps.storage)?;
-            let owner = ownershop.owner.unwrap();
+            let owner = ownershop.owner.ok_or(ContractError::Unauthorized {})?;
             let new_owner = if let Some(pending) = ownershop.pending_owner {
                 ensure!(pending == info.sender, ContractError::Unauthorized {});
                 STATUS.save(deps.storage, &Status { frozen: false })?;
@@ -275,7 +586,7 @@ pub fn try_forgeting_tokens(
     };
 
     for id in ids {
-        KNOWN_TOKENS.remove(storage, (collection.as_str(), id.as_str()));
+        remove_known_token(storage, collection.as_str(), id.as_str())?;
     }
 
     Ok(Response::new().add_attribute("action", "forget_tokens"))
@@ -297,7 +608,7 @@ pub fn try_updating_known_tokens(
         ));
     }
 
-    let res = query_tokens(
+    let res = collection_backend(querier, storage, &collection)?.query_tokens(
         querier,
         &collection,
         env.contract.address.to_string(),
@@ -306,7 +617,7 @@ pub fn try_updating_known_tokens(
     )?;
 
     for id in res.tokens {
-        KNOWN_TOKENS.save(storage, (collection.as_str(), id.as_str()), &true)?;
+        save_known_token(storage, collection.as_str(), id.as_str())?;
     }
 
     Ok(Response::new().add_attribute("action", "update_known_tokens"))
@@ -314,90 +625,267 @@ pub fn try_updating_known_tokens(
 
 pub fn try_updating_known_on_receive(
     deps: DepsMut,
+    env: &Env,
     collection: String,
     token_id: String,
+    msg: Binary,
 ) -> Result<Response, ContractError> {
-    KNOWN_TOKENS.save(
-        deps.storage,
-        (collection.as_str(), token_id.as_str()),
-        &true,
-    )?;
-    Ok(Response::default().add_attributes(vec![
+    // erroring out bounces the token back to the sender
+    let tokens = [TokenInfo {
+        collection: collection.clone(),
+        id: token_id.clone(),
+    }];
+    assert_no_cycle(&deps.querier, deps.storage, env, env.contract.address.as_str(), &tokens)?;
+
+    let payload = RECEIVE_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .parse(&msg)?;
+
+    let res = Response::default().add_attributes(vec![
         ("action", "update_known_on_receive"),
         ("collection", collection.as_str()),
         ("token_id", token_id.as_str()),
-    ]))
-}
+    ]);
+    let key = (collection.as_str(), token_id.as_str());
+
+    let payload = match payload {
+        Some(payload) => payload,
+        None => {
+            save_known_token(deps.storage, &collection, &token_id)?;
+            return Ok(res);
+        }
+    };
+    payload.validate(&env.block.time)?;
+
+    match payload {
+        ReceivePayload::Forward { recipient } => {
+            assert_status(deps.storage)?;
+            assert_no_cycle(&deps.querier, deps.storage, env, &recipient, &tokens)?;
+            let msg = collection_backend(&deps.querier, deps.storage, &collection)?.transfer_msg(
+                &collection,
+                &token_id,
+                env.contract.address.as_str(),
+                &recipient,
+            )?;
+            Ok(res.add_message(msg).add_attribute("payload", "forward"))
+        }
 
-pub fn try_transfering_token(
-    env: &Env,
-    storage: &mut dyn cosmwasm_std::Storage,
-    collection: String,
-    token_id: String,
-    recipient: String,
-    _funds: Vec<Coin>,
-) -> Result<Response, ContractError> {
-    assert_status(storage)?;
-    KNOWN_TOKENS.remove(storage, (collection.as_str(), token_id.as_str()));
-    let msg = transfer_nft_msg(
-        &collection,
-        &token_id,
-        env.contract.address.as_str(),
-        &recipient,
-    );
-    Ok(Response::default()
-        .add_message(msg)
-        .add_attribute("action", "transfer_token"))
-}
+        ReceivePayload::Stake { contract, msg } => {
+            assert_status(deps.storage)?;
+            assert_no_cycle(&deps.querier, deps.storage, env, &contract, &tokens)?;
+            let msg = collection_backend(&deps.querier, deps.storage, &collection)?
+                .send_msg(&collection, &token_id, &contract, msg, vec![])?;
+            Ok(res.add_message(msg).add_attribute("payload", "stake"))
+        }
 
+        ReceivePayload::Lock { until } => {
+            let mut attrs = TOKEN_ATTRIBUTES.may_load(deps.storage, key)?.unwrap_or_default();
+            // a lock can only be extended
+            attrs.locked_until = Some(attrs.locked_until.map_or(until, |t| t.max(until)));
+            TOKEN_ATTRIBUTES.save(deps.storage, key, &attrs)?;
+            save_known_token(deps.storage, &collection, &token_id)?;
+            Ok(res.add_attribute("payload", "lock"))
+        }
 
-#[cfg(not(feature = "omniflix"))]
-pub fn try_sending_token(
-    storage: &mut dyn cosmwasm_std::Storage,
-    collection: String,
-    token_id: String,
-    contract: String,
-    msg: Binary,
-    funds: Vec<Coin>,
-) -> Result<Response, ContractError> {
-    assert_status(storage)?;
-    KNOWN_TOKENS.remove(storage, (collection.as_str(), token_id.as_str()));
-    let msg: cosmwasm_std::CosmosMsg = WasmMsg::Execute {
-        contract_addr: collection,
-        msg: cosmwasm_std::to_json_binary(&cw_tba::Cw721Msg::SendNft {
-            contract,
-            token_id,
-            msg: msg.to_vec().into(),
-        })?,
-        funds,
+        ReceivePayload::Tag { label } => {
+            let mut attrs = TOKEN_ATTRIBUTES.may_load(deps.storage, key)?.unwrap_or_default();
+            attrs.label = Some(label);
+            TOKEN_ATTRIBUTES.save(deps.storage, key, &attrs)?;
+            save_known_token(deps.storage, &collection, &token_id)?;
+            Ok(res.add_attribute("payload", "tag"))
+        }
     }
-    .into();
-    Ok(Response::default()
-        .add_message(msg)
-        .add_attribute("action", "send_token"))
 }
 
+
 pub fn try_purging(deps: DepsMut, sender: Addr) -> Result<Response, ContractError> {
     assert_registry(deps.storage, &sender)?;
     KNOWN_TOKENS.clear(deps.storage);
+    KNOWN_COLLECTIONS.clear(deps.storage);
+    KNOWN_CW20S.clear(deps.storage);
     REGISTRY_ADDRESS.remove(deps.storage);
     TOKEN_INFO.remove(deps.storage);
     PUBKEY.remove(deps.storage);
     STATUS.remove(deps.storage);
+    IBC_CONFIG.remove(deps.storage);
+    FEE_GRANTS.clear(deps.storage);
+    TIMELOCK.remove(deps.storage);
+    QUEUE.clear(deps.storage);
+    QUEUE_COUNT.remove(deps.storage);
+    PAYMENTS.clear(deps.storage);
+    PAYMENT_COUNT.remove(deps.storage);
+    PULLS.clear(deps.storage);
+    EXECUTORS.clear(deps.storage);
+    PLUGINS.clear(deps.storage);
+    POLICY.remove(deps.storage);
+    RECEIVE_CONFIG.remove(deps.storage);
+    TOKEN_ATTRIBUTES.clear(deps.storage);
+    WATCHED_COLLECTIONS.clear(deps.storage);
+    TOKEN_APPROVALS.clear(deps.storage);
+    REMOTE_HOLDER.remove(deps.storage);
     Ok(Response::default().add_attribute("action", "purge"))
 }
 
-pub fn try_fee_granting(
+pub fn try_executing_queued(
+    querier: &QuerierWrapper,
     storage: &mut dyn cosmwasm_std::Storage,
-    contract: Addr,
-    sender: Addr,
-    grantee: String,
-    allowance: Option<BasicAllowance>,
+    env: &Env,
+    info: &MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let action = take_ready_action(storage, env, id)?;
+    let res = run_action(querier, storage, env, info, action)?;
+    Ok(res
+        .add_attribute("action", "execute_queued")
+        .add_attribute("id", id.to_string()))
+}
+
+pub fn try_handling_payments(
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &Env,
+    info: &MessageInfo,
+    msg: PaymentMsg,
 ) -> Result<Response, ContractError> {
-    assert_owner(storage, &sender)?;
     assert_status(storage)?;
+    match msg {
+        PaymentMsg::Crank { ids } => Ok(try_cranking_payments(storage, env, ids)?),
+        PaymentMsg::Pull { amount, recipient } => {
+            Ok(try_pulling(storage, env, info.sender.to_string(), amount, recipient)?)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{
+        from_json,
+        testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage},
+        to_json_binary, BankMsg, ContractResult, OwnedDeps, SystemError, SystemResult, WasmQuery,
+    };
+    use cw_tba::{NftBackend, NonFungibleTokenPacketData, OwnerOfResponse, RemoteHolder};
 
-    let msg = encode_feegrant_msg(contract.as_str(), &grantee, allowance)?;
+    #[cosmwasm_schema::cw_serde]
+    enum RegistryQueryMsg {
+        Ics721Bridges {},
+        CollectionBackend { collection: String },
+    }
+
+    /// Account whose token is escrowed by the bridge while `remote` holds the voucher
+    fn bridged_account() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        REGISTRY_ADDRESS.save(&mut deps.storage, &"registry".to_string()).unwrap();
+        TOKEN_INFO
+            .save(&mut deps.storage, &TokenInfo {
+                collection: "collection".into(),
+                id: "1".into(),
+            })
+            .unwrap();
+        STATUS.save(&mut deps.storage, &Status { frozen: false }).unwrap();
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some("owner")).unwrap();
+        REMOTE_HOLDER
+            .save(&mut deps.storage, &RemoteHolder {
+                bridge: "bridge".into(),
+                class_id: "collection".into(),
+                holder: "remote".into(),
+            })
+            .unwrap();
+
+        deps.querier.update_wasm(|query| {
+            let WasmQuery::Smart { contract_addr, msg } = query else {
+                unimplemented!()
+            };
+            let res = match contract_addr.as_str() {
+                "registry" => match from_json(msg).unwrap() {
+                    RegistryQueryMsg::Ics721Bridges {} => to_json_binary(&vec!["bridge"]),
+                    RegistryQueryMsg::CollectionBackend { .. } => {
+                        to_json_binary(&NftBackend::Cw721)
+                    }
+                },
+                "collection" => to_json_binary(&OwnerOfResponse {
+                    owner: "bridge".into(),
+                    approvals: vec![],
+                }),
+                // the bridge didn't mint the collection
+                addr => {
+                    return SystemResult::Err(SystemError::NoSuchContract {
+                        addr: addr.to_string(),
+                    })
+                }
+            };
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        });
+        deps
+    }
+
+    fn send(to: &str) -> Vec<ActiontMsg> {
+        vec![ActiontMsg::Execute {
+            msgs: vec![BankMsg::Send {
+                to_address: to.into(),
+                amount: vec![],
+            }
+            .into()],
+        }]
+    }
+
+    fn receive(sender: &str, actions: &[ActiontMsg]) -> Ics721ReceiveCallbackMsg {
+        Ics721ReceiveCallbackMsg {
+            nft_contract: "other".into(),
+            original_packet: NonFungibleTokenPacketData {
+                class_id: "other".into(),
+                class_uri: None,
+                class_data: None,
+                token_ids: vec!["7".into()],
+                token_uris: None,
+                token_data: None,
+                sender: sender.into(),
+                receiver: "anyone".into(),
+                memo: None,
+            },
+            msg: to_json_binary(&actions).unwrap(),
+        }
+    }
 
-    Ok(Response::new().add_message(msg))
+    #[test]
+    fn voucher_holder_controls_the_bridged_account() {
+        let mut deps = bridged_account();
+        let env = mock_env();
+
+        let bridge = mock_info("bridge", &[]);
+
+        let msg = receive("remote", &send("friend"));
+        let res = try_receiving_ics721(deps.as_mut(), &env, bridge.clone(), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        // the packets of anyone else only update the known tokens
+        let msg = receive("stranger", &send("thief"));
+        let res = try_receiving_ics721(deps.as_mut(), &env, bridge, msg).unwrap();
+        assert!(res.messages.is_empty());
+    }
+
+    #[test]
+    fn local_owner_is_refused_while_bridged() {
+        let mut deps = bridged_account();
+        let env = mock_env();
+
+        let owner = mock_info("owner", &[]);
+        let err = try_executing_actions(deps.as_mut(), &env, &owner, send("friend"));
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn bridged_account_can_be_frozen() {
+        let mut deps = bridged_account();
+        let querier = QuerierWrapper::new(&deps.querier);
+
+        try_freezing(&querier, &mut deps.storage, Addr::unchecked("anyone")).unwrap();
+        assert!(STATUS.load(&deps.storage).unwrap().frozen);
+
+        // the freeze applies to the holder of the voucher as well
+        let msg = receive("remote", &send("friend"));
+        let err = try_receiving_ics721(deps.as_mut(), &mock_env(), mock_info("bridge", &[]), msg);
+        assert!(err.is_err());
+    }
 }
@@ -1,22 +1,18 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Addr, Binary, Coin};
 pub use cw82::{account_query, CanExecuteResponse};
-pub use cw_tba::{InstantiateAccountMsg as InstantiateMsg, QueryMsg, TokenInfo};
+pub use cw_tba::{Cw20Coin, InstantiateAccountMsg as InstantiateMsg, KnownToken, QueryMsg, Status, TokenInfo};
 
 pub type MigrateMsg = Binary;
 
-#[cw_serde]
-pub struct Status {
-    /// Whether the account is frozen
-    pub frozen: bool,
-}
-
 #[cw_serde]
 pub struct AssetsResponse {
     /// Native fungible tokens held by an account
     pub balances: Vec<Coin>,
+    /// CW20 tokens held by an account
+    pub cw20_balances: Vec<Cw20Coin>,
     /// NFT tokens the account is aware of
-    pub tokens: Vec<TokenInfo>,
+    pub tokens: Vec<KnownToken>,
 }
 
 #[cw_serde]
@@ -31,11 +27,13 @@ pub struct FullInfoResponse {
     pub registry: String,
     /// Native fungible tokens held by an account
     pub balances: Vec<Coin>,
+    /// CW20 tokens held by an account
+    pub cw20_balances: Vec<Cw20Coin>,
     /// NFT tokens the account is aware of
-    pub tokens: Vec<TokenInfo>,
+    pub tokens: Vec<KnownToken>,
     /// Whether the account is frozen
     pub status: Status,
 }
 
-pub type KnownTokensResponse = Vec<TokenInfo>;
+pub type KnownTokensResponse = Vec<KnownToken>;
 
@@ -1,19 +1,31 @@
-use cosmwasm_std::{Addr, Binary, Deps, Env, Order, StdError, StdResult};
+use cosmwasm_std::{ensure, Addr, Binary, CosmosMsg, Deps, Env, StdError, StdResult};
 use cw82::{CanExecuteResponse, ValidSignatureResponse};
+use cw84::ValidSignaturesResponse;
 use cw_ownable::is_owner;
-use cw_tba::TokenInfo;
+use cw_tba::{
+    assert_policy, native_actions, simulate_policy, with_token_metadata, ActiontMsg,
+    SimulatePolicyResponse,
+};
 use smart_account_auth::{CosmosArbitrary, Credential, Verifiable};
 
+pub use cw_tba::state::{
+    account_tree, cw20_balances, executors, fee_grants, known_collections, known_tokens, plugins,
+    policy, pull_authorizations, queued_actions, scheduled_payments, skipped_known_tokens,
+    token_approvals, token_attributes, watched_collections,
+};
+
 use crate::{
     msg::{AssetsResponse, FullInfoResponse},
-    state::{KNOWN_TOKENS, PUBKEY, REGISTRY_ADDRESS, STATUS, TOKEN_INFO},
-    utils::{assert_ok_cosmos_msg, assert_status, status_ok},
+    state::{
+        PUBKEY, REGISTRY_ADDRESS,
+        STATUS, TOKEN_INFO,
+    },
+    utils::{assert_status, builtin_policy, status_ok, verify_signed_actions},
 };
 
-const DEFAULT_BATCH_SIZE: u32 = 100;
-
 pub fn can_execute(
     deps: Deps,
+    env: Env,
     sender: String,
     msg: &cosmwasm_std::CosmosMsg,
 ) -> StdResult<CanExecuteResponse> {
@@ -34,7 +46,56 @@ pub fn can_execute(
     };
 
     Ok(CanExecuteResponse {
-        can_execute: assert_ok_cosmos_msg(msg).is_ok(),
+        can_execute: assert_policy(deps.storage, &env, &builtin_policy(), &[ActiontMsg::Execute {
+            msgs: vec![msg.clone()],
+        }], &[])
+        .is_ok(),
+    })
+}
+
+pub fn can_execute_native(
+    deps: Deps,
+    env: Env,
+    sender: String,
+    msg: CosmosMsg,
+) -> StdResult<CanExecuteResponse> {
+    let cant = CanExecuteResponse { can_execute: false };
+
+    if !status_ok(deps.storage) {
+        return Ok(cant);
+    };
+
+    let addr = match deps.api.addr_validate(&sender) {
+        Ok(addr) => addr,
+        Err(_) => return Ok(cant),
+    };
+
+    if !is_owner(deps.storage, &addr).unwrap_or_default() {
+        return Ok(cant);
+    };
+
+    Ok(CanExecuteResponse {
+        can_execute: assert_policy(
+            deps.storage,
+            &env,
+            &builtin_policy(),
+            &native_actions(env.contract.address.as_str(), msg),
+            &[],
+        )
+        .is_ok(),
+    })
+}
+
+pub fn can_execute_signed(
+    deps: Deps,
+    env: Env,
+    cred: Credential,
+    msgs: Vec<ActiontMsg>,
+) -> StdResult<CanExecuteResponse> {
+    Ok(CanExecuteResponse {
+        can_execute: status_ok(deps.storage)
+            && verify_signed_actions(deps, &env, &cred, &msgs).is_ok()
+            && assert_policy(deps.storage, &env, &builtin_policy(), &msgs, &[]).is_ok(),
     })
 }
 
@@ -55,6 +116,37 @@ pub fn valid_signature(
     })
 }
 
+pub fn valid_signatures(
+    deps: Deps,
+    data: Vec<Binary>,
+    signatures: Vec<Binary>,
+    _payload: &Option<Credential>,
+) -> StdResult<ValidSignaturesResponse> {
+    ensure!(
+        data.len() == signatures.len(),
+        StdError::generic_err("Data and signatures must be of equal length")
+    );
+    if !status_ok(deps.storage) {
+        return Ok(ValidSignaturesResponse {
+            are_valid: vec![false; data.len()],
+        });
+    };
+
+    let pk: Binary = PUBKEY.load(deps.storage)?;
+    let owner = cw_ownable::get_ownership(deps.storage)?;
+    let address = owner.owner.unwrap_or(Addr::unchecked(""));
+
+    Ok(ValidSignaturesResponse {
+        are_valid: data
+            .into_iter()
+            .zip(signatures)
+            .map(|(data, signature)| {
+                verify_arbitrary(deps, address.as_str(), data, signature, &pk).is_ok()
+            })
+            .collect(),
+    })
+}
+
 pub fn verify_arbitrary(
     deps: Deps,
     account_addr: &str,
@@ -80,39 +172,26 @@ pub fn assets(
     env: Env,
     skip: Option<u32>,
     limit: Option<u32>,
+    with_metadata: Option<bool>,
 ) -> StdResult<AssetsResponse> {
-    let nfts = known_tokens(deps, skip, limit)?;
+    let registry = REGISTRY_ADDRESS.load(deps.storage)?;
+    let nfts = with_token_metadata(&deps.querier, &registry, skipped_known_tokens(deps, skip, limit)?, with_metadata)?;
+    let cw20_balances = cw20_balances(deps, &env, skip, limit)?;
     let balance = deps.querier.query_all_balances(env.contract.address)?;
 
     Ok(AssetsResponse {
         balances: balance,
+        cw20_balances,
         tokens: nfts,
     })
 }
 
-pub fn known_tokens(
+pub fn policy_simulation(
     deps: Deps,
-    skip: Option<u32>,
-    limit: Option<u32>,
-) -> StdResult<Vec<TokenInfo>> {
-    let skip = skip.unwrap_or(0) as usize;
-    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
-
-    let tokens: StdResult<Vec<TokenInfo>> = KNOWN_TOKENS
-        .keys(deps.storage, None, None, Order::Ascending)
-        .enumerate()
-        .filter(|(i, _)| *i >= skip)
-        .take(limit)
-        .map(|(_, kt)| {
-            let kp = kt?;
-            Ok(TokenInfo {
-                collection: kp.0,
-                id: kp.1,
-            })
-        })
-        .collect();
-
-    tokens
+    env: Env,
+    actions: Vec<ActiontMsg>,
+) -> StdResult<SimulatePolicyResponse> {
+    simulate_policy(deps.storage, &env, &builtin_policy(), &actions, &[])
 }
 
 pub fn full_info(
@@ -120,13 +199,17 @@ pub fn full_info(
     env: Env,
     skip: Option<u32>,
     limit: Option<u32>,
+    with_metadata: Option<bool>,
 ) -> StdResult<FullInfoResponse> {
-    let tokens = known_tokens(deps, skip, limit)?;
+    let registry = REGISTRY_ADDRESS.load(deps.storage)?;
+    let tokens = with_token_metadata(&deps.querier, &registry, skipped_known_tokens(deps, skip, limit)?, with_metadata)?;
+    let cw20_balances = cw20_balances(deps, &env, skip, limit)?;
     let balances = deps.querier.query_all_balances(env.contract.address)?;
     let ownership = cw_ownable::get_ownership(deps.storage)?;
 
     Ok(FullInfoResponse {
         balances,
+        cw20_balances,
         tokens,
         ownership,
         registry: REGISTRY_ADDRESS.load(deps.storage)?,
@@ -1,12 +1,13 @@
 use cosmwasm_std::Binary;
-use cw_storage_plus::{Item, Map};
-use cw_tba::TokenInfo;
+use cw_storage_plus::Item;
 
-use crate::msg::Status;
+pub use cw_tba::state::{
+    forget_token, rebuild_known_collections, remove_known_token, save_known_token, EXECUTORS,
+    FEE_GRANTS, IBC_CONFIG, KNOWN_COLLECTIONS, KNOWN_CW20S, KNOWN_TOKENS, MINT_CACHE, PAYMENTS,
+    PAYMENT_COUNT, PLUGINS, POLICY, PULLS, QUEUE, QUEUE_COUNT, RECEIVE_CONFIG, REGISTRY_ADDRESS,
+    REMOTE_HOLDER, STATUS, TIMELOCK, TOKEN_APPROVALS, TOKEN_ATTRIBUTES, TOKEN_INFO,
+    WATCHED_COLLECTIONS,
+};
 
-pub static REGISTRY_ADDRESS: Item<String> = Item::new("r");
-pub static TOKEN_INFO: Item<TokenInfo> = Item::new("t");
-pub static STATUS: Item<Status> = Item::new("s");
 pub static PUBKEY: Item<Binary> = Item::new("p");
-pub static MINT_CACHE: Item<String> = Item::new("m");
-pub static KNOWN_TOKENS: Map<(&str, &str), bool> = Map::new("k");
+
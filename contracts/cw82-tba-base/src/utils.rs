@@ -1,13 +1,33 @@
 use crate::{
     error::ContractError,
-    state::{REGISTRY_ADDRESS, STATUS},
+    state::{PUBKEY, REGISTRY_ADDRESS, REMOTE_HOLDER, STATUS},
 };
 use cosmwasm_std::{
-    ensure, Addr, Binary, CosmosMsg, QuerierWrapper, StdError, StdResult, Storage, WasmMsg,
+    ensure, to_json_string, Addr, Binary, Deps, Env, QuerierWrapper, StdError,
+    StdResult, Storage,
+};
+use cw_tba::{
+    ActiontMsg, MsgKind, Policy, PolicyRule,
+};
+use saa_wasm::{
+    account_number,
+    saa_types::{
+        AuthError, CheckOption, Credential, CredentialAddress, ReplayParams, ReplayProtection,
+        Verifiable, VerifiedData,
+    },
 };
-use saa_wasm::saa_types::{CredentialAddress, VerifiedData};
 
 pub fn assert_status(store: &dyn Storage) -> StdResult<()> {
+    assert_not_frozen(store)?;
+    // the owner no longer holds the token while it is escrowed by a bridge
+    if REMOTE_HOLDER.exists(store) {
+        return Err(StdError::generic_err("Account is locked while the token is on another chain"));
+    }
+    Ok(())
+}
+
+/// The only check of the status left for the holder of the voucher acting through the bridge
+pub fn assert_not_frozen(store: &dyn Storage) -> StdResult<()> {
     let status = STATUS.load(store)?;
     if status.frozen {
         return Err(StdError::generic_err("Account is frozen"));
@@ -19,28 +39,15 @@ pub fn status_ok(store: &dyn Storage) -> bool {
     assert_status(store).is_ok()
 }
 
-pub fn assert_ok_wasm_msg(msg: &WasmMsg) -> StdResult<()> {
-    let bad_wasm_error = StdError::generic_err("Not Supported");
-    match msg {
-        // todo: add whitelististed messages
-        WasmMsg::Execute { .. } => Err(bad_wasm_error),
-        _ => Err(bad_wasm_error),
+/// Rules the raw messages of the owner are always checked against
+pub fn builtin_policy() -> Policy {
+    Policy {
+        rules: vec![PolicyRule::BlockedMsgKinds {
+            kinds: vec![MsgKind::Wasm, MsgKind::Stargate],
+        }],
     }
 }
 
-pub fn assert_ok_cosmos_msg(msg: &CosmosMsg) -> StdResult<()> {
-    let bad_msg_error = StdError::generic_err("Not Supported");
-    match msg {
-        CosmosMsg::Wasm(msg) => assert_ok_wasm_msg(msg),
-        CosmosMsg::Stargate { .. } => Err(bad_msg_error),
-        _ => Ok(()),
-    }
-}
-
-pub fn is_ok_cosmos_msg(msg: &CosmosMsg) -> bool {
-    assert_ok_cosmos_msg(msg).is_ok()
-}
-
 pub fn query_if_registry(querier: &QuerierWrapper, addr: Addr) -> StdResult<bool> {
     Ok(cw22::query_supported_interface_version(querier, addr.as_str(), "crates:cw83")?.is_some())
 }
@@ -54,7 +61,7 @@ pub fn assert_registry(store: &dyn Storage, addr: &Addr) -> Result<(), ContractE
 }
 
 pub fn is_registry(store: &dyn Storage, addr: &Addr) -> StdResult<bool> {
-    REGISTRY_ADDRESS.load(store).map(|a| a == addr.to_string())
+    REGISTRY_ADDRESS.load(store).map(|a| a == *addr)
 }
 
 pub fn extract_pubkey(data: VerifiedData, owner: &Addr) -> Result<Binary, ContractError> {
@@ -65,8 +72,56 @@ pub fn extract_pubkey(data: VerifiedData, owner: &Addr) -> Result<Binary, Contra
 
     if let Some(CredentialAddress::Bech32(a)) = info.address.as_ref() {
         ensure!(a == owner, ContractError::Unauthorized {});
-        return Ok(Binary::from_base64(&id)?);
+        Ok(Binary::from_base64(id)?)
     } else {
-        return Err(ContractError::NotSupported {});
+        Err(ContractError::NotSupported {})
+    }
+}
+
+/// Verify that the actions have been signed by the stored public key of the current owner
+/// and return the account number that must be used for the next signed execution
+pub fn verify_signed_actions(
+    deps: Deps,
+    env: &Env,
+    cred: &Credential,
+    actions: &Vec<ActiontMsg>,
+) -> Result<u64, ContractError> {
+    let arb = match cred {
+        Credential::CosmosArbitrary(arb) => arb,
+        _ => return Err(ContractError::PubkeyOnly {}),
+    };
+    let owner = cw_ownable::get_ownership(deps.storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized {})?;
+
+    ensure!(
+        arb.pubkey == PUBKEY.load(deps.storage)? && arb.address == owner.as_str(),
+        ContractError::Unauthorized {}
+    );
+    cred.verify(deps)?;
+
+    let nonce = account_number(deps.storage);
+    let params = ReplayParams::new(nonce, CheckOption::Messages(vec![to_json_string(actions)?]));
+    cred.protect_reply(env, params).map_err(AuthError::from)?;
+
+    Ok(nonce + 1)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{testing::mock_env, WasmMsg};
+
+    #[test]
+    fn raw_wasm_messages_are_blocked() {
+        let now = mock_env().block.time;
+        let raw = ActiontMsg::Execute {
+            msgs: vec![WasmMsg::ClearAdmin {
+                contract_addr: "contract".into(),
+            }
+            .into()],
+        };
+        assert!(builtin_policy().check(&[raw], &[], &now).is_err());
     }
 }
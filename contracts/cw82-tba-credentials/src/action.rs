@@ -1,15 +1,27 @@
 use crate::{
     error::ContractError,
     msg::ContractResult,
-    state::{KNOWN_TOKENS, MINT_CACHE, STATUS, TOKEN_INFO},
-    utils::{assert_ok_cosmos_msg, assert_status},
+    state::{
+        remove_known_token, save_known_token, KNOWN_TOKENS, MINT_CACHE,
+        STATUS, TIMELOCK, TOKEN_INFO,
+    },
+    utils::assert_not_frozen,
 };
 use cosmwasm_std::{
-    to_json_binary, Binary, CosmosMsg, DepsMut, Env, MessageInfo, QuerierWrapper, ReplyOn, Response, StdResult, Storage, SubMsg, WasmMsg
+    Binary, DepsMut, Env, MessageInfo, QuerierWrapper, ReplyOn, Response, StdResult, Storage,
+    SubMsg, WasmMsg,
 };
 use cw_tba::{
-    encode_feegrant_msg, query_tokens, verify_nft_ownership, BasicAllowance, Cw721Msg,
-    ActiontMsg, Status,
+    collection_backend, take_ready_action, try_approving, try_authorizing_pull, try_burning_token,
+    try_cancelling_payment, try_cancelling_queued, try_claiming_rewards, try_delegating,
+    try_executing_on_child, try_fee_granting, try_granting_executor, try_ibc_transfering,
+    try_installing_plugin, try_queueing_action, try_redelegating, try_revoking,
+    try_revoking_executor, try_revoking_fee_grant, try_revoking_pull, try_scheduling_payment,
+    try_sending_cw20, try_sending_token, try_setting_withdraw_address, try_transfering_cw20,
+    try_transfering_token, try_transfering_tokens, try_undelegating, try_uninstalling_plugin,
+    try_updating_ibc_config, try_updating_policy, try_updating_receive_config,
+    try_updating_timelock, try_updating_watched_collections, try_voting, try_voting_weighted,
+    try_withdrawing_all, verify_nft_ownership, ActiontMsg, Status,
 };
 
 pub const MINT_REPLY_ID: u64 = 1;
@@ -19,8 +31,18 @@ pub fn execute_action(
     env: &Env,
     info: &MessageInfo,
     msg: ActiontMsg,
+    delayed: bool,
 ) -> ContractResult {
-    assert_status(deps.storage)?;
+    assert_not_frozen(deps.storage)?;
+
+    if delayed {
+        let timelock = TIMELOCK.load(deps.storage)?;
+        return Ok(try_queueing_action(deps.storage, env, &timelock, msg)?);
+    }
+    run_action(deps, env, info, msg)
+}
+
+fn run_action(deps: &mut DepsMut, env: &Env, info: &MessageInfo, msg: ActiontMsg) -> ContractResult {
     use ActiontMsg::*;
 
     match msg {
@@ -32,15 +54,77 @@ pub fn execute_action(
             collection,
             token_id,
             recipient,
-        } => try_transfering_token(deps.storage, collection, token_id, recipient),
+        } => Ok(try_transfering_token(&deps.querier, env, deps.storage, collection, token_id, recipient)?),
+
+        TransferTokens {
+            collection,
+            token_ids,
+            recipient,
+        } => Ok(try_transfering_tokens(&deps.querier, env, deps.storage, collection, token_ids, recipient)?),
+
+        WithdrawAll {
+            recipient,
+            include_native,
+            collections,
+            limit,
+        } => Ok(try_withdrawing_all(
+            &deps.querier,
+            env,
+            deps.storage,
+            recipient,
+            include_native,
+            collections,
+            limit,
+        )?),
 
-        #[cfg(not(feature = "omniflix"))]
         SendToken {
             collection,
             token_id,
             contract,
             msg,
-        } => try_sending_token(deps.storage, collection, token_id, contract, msg),
+        } => Ok(try_sending_token(&deps.querier, env, deps.storage, collection, token_id, contract, msg, vec![])?),
+
+        Approve {
+            collection,
+            token_id,
+            spender,
+            expires,
+        } => Ok(try_approving(&deps.querier, env, deps.storage, collection, Some(token_id), spender, expires)?),
+
+        Revoke {
+            collection,
+            token_id,
+            spender,
+        } => Ok(try_revoking(&deps.querier, deps.storage, collection, Some(token_id), spender)?),
+
+        ApproveAll {
+            collection,
+            operator,
+            expires,
+        } => Ok(try_approving(&deps.querier, env, deps.storage, collection, None, operator, expires)?),
+
+        RevokeAll {
+            collection,
+            operator,
+        } => Ok(try_revoking(&deps.querier, deps.storage, collection, None, operator)?),
+
+        Burn {
+            collection,
+            token_id,
+        } => Ok(try_burning_token(&deps.querier, env, deps.storage, collection, token_id)?),
+
+        TransferCw20 {
+            contract,
+            amount,
+            recipient,
+        } => Ok(try_transfering_cw20(contract, amount, recipient)?),
+
+        SendCw20 {
+            contract,
+            amount,
+            recipient,
+            msg,
+        } => Ok(try_sending_cw20(contract, amount, recipient, msg)?),
 
         UpdateKnownTokens {
             collection,
@@ -64,14 +148,145 @@ pub fn execute_action(
 
         Unfreeze {} => try_unfreezing(&deps.querier, deps.storage),
 
-        FeeGrant { grantee, allowance } => {
-            try_fee_granting(env.contract.address.as_str(), grantee.as_str(), allowance)
+        FeeGrant {
+            grantee,
+            allowance,
+            periodic,
+            allowed_msgs,
+        } => Ok(try_fee_granting(&deps.querier, deps.storage, env, grantee, allowance, periodic, allowed_msgs)?),
+
+        RevokeFeeGrant { grantee } => Ok(try_revoking_fee_grant(&deps.querier, deps.storage, env, grantee)?),
+
+        Delegate { validator, amount } => Ok(try_delegating(validator, amount)?),
+
+        Undelegate { validator, amount } => Ok(try_undelegating(validator, amount)?),
+
+        Redelegate {
+            src_validator,
+            dst_validator,
+            amount,
+        } => Ok(try_redelegating(src_validator, dst_validator, amount)?),
+
+        ClaimRewards { validators } => Ok(try_claiming_rewards(&deps.querier, env, validators)?),
+
+        SetWithdrawAddress { address } => Ok(try_setting_withdraw_address(address)?),
+
+        Vote {
+            proposal_id,
+            option,
+        } => Ok(try_voting(proposal_id, option)?),
+
+        VoteWeighted {
+            proposal_id,
+            options,
+        } => Ok(try_voting_weighted(proposal_id, options)?),
+
+        IbcTransfer {
+            channel,
+            to,
+            amount,
+            memo,
+            timeout,
+        } => Ok(try_ibc_transfering(deps.storage, env, channel, to, amount, memo, timeout)?),
+
+        UpdateIbcConfig {
+            channels,
+            default_timeout,
+        } => Ok(try_updating_ibc_config(deps.storage, channels, default_timeout)?),
+
+        UpdateTimelock {
+            delay,
+            thresholds,
+            tokens,
+        } => Ok(try_updating_timelock(deps.storage, delay, thresholds, tokens)?),
+
+        ExecuteQueued { id } => try_executing_queued(deps, env, info, id),
+
+        CancelQueued { id } => Ok(try_cancelling_queued(deps.storage, id)?),
+
+        SchedulePayment {
+            recipient,
+            amount,
+            interval,
+            start,
+            end,
+        } => Ok(try_scheduling_payment(deps.storage, env, recipient, amount, interval, start, end)?),
+
+        CancelPayment { id } => Ok(try_cancelling_payment(deps.storage, id)?),
+
+        AuthorizePull {
+            merchant,
+            cap,
+            period,
+            end,
+        } => Ok(try_authorizing_pull(deps.storage, env, merchant, cap, period, end)?),
+
+        RevokePull { merchant } => Ok(try_revoking_pull(deps.storage, merchant)?),
+
+        GrantExecutor {
+            executor,
+            permissions,
+            expiration,
+            fee,
+            interval,
+        } => Ok(try_granting_executor(deps.storage, executor, permissions, expiration, fee, interval)?),
+
+        RevokeExecutor { executor } => Ok(try_revoking_executor(deps.storage, executor)?),
+
+        InstallPlugin { address, kind } => {
+            Ok(try_installing_plugin(&deps.querier, deps.storage, env, address, kind)?)
+        }
+
+        UninstallPlugin { address } => Ok(try_uninstalling_plugin(deps.storage, address)?),
+
+        UpdatePolicy { rules } => Ok(try_updating_policy(deps.storage, rules)?),
+
+        UpdateReceiveConfig { reject_unknown } => {
+            Ok(try_updating_receive_config(deps.storage, reject_unknown)?)
         }
+
+        UpdateWatchedCollections { collections } => {
+            Ok(try_updating_watched_collections(deps.storage, collections)?)
+        }
+
+        ExecuteOnChild {
+            token_info,
+            actions,
+        } => Ok(try_executing_on_child(&deps.querier, deps.storage, env, token_info, actions)?),
+
+        UpdateTwoFactor {
+            actions,
+            thresholds,
+        } => crate::approvals::try_updating_two_factor(deps.storage, actions, thresholds),
+
+        RemoveCredentials { ids } => {
+            let owner = cw_ownable::get_ownership(deps.storage)?
+                .owner
+                .ok_or(ContractError::Unauthorized("The account has no owner".into()))?;
+            crate::approvals::try_removing_credentials(deps.storage, owner.as_str(), ids)
+        }
+
+        ApproveMigration { code_id } => {
+            crate::approvals::try_approving_migration(deps.storage, code_id)
+        }
+
+        UpdateGuardians {
+            guardians,
+            threshold,
+            delay,
+        } => crate::recovery::try_updating_guardians(
+            deps.api,
+            deps.storage,
+            guardians,
+            threshold,
+            delay,
+        ),
+
+        VetoRecovery {} => crate::recovery::try_vetoing_recovery(deps.storage),
     }
 }
 
 pub fn try_executing(msgs: Vec<cosmwasm_std::CosmosMsg>) -> ContractResult {
-    msgs.iter().try_for_each(|msg| assert_ok_cosmos_msg(msg))?;
     Ok(Response::new().add_messages(msgs))
 }
 
@@ -102,9 +317,12 @@ pub fn try_freezing(storage: &mut dyn Storage) -> ContractResult {
 }
 
 pub fn try_unfreezing(querier: &QuerierWrapper, storage: &mut dyn Storage) -> ContractResult {
-    let owner = cw_ownable::get_ownership(storage)?.owner.unwrap();
+    let owner = cw_ownable::get_ownership(storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized("The account has no owner".into()))?;
     let token = TOKEN_INFO.load(storage)?;
-    verify_nft_ownership(&querier, owner.as_str(), token)?;
+    let backend = collection_backend(querier, storage, &token.collection)?;
+    verify_nft_ownership(querier, &backend, owner.as_str(), token)?;
     STATUS.save(storage, &Status { frozen: false })?;
     Ok(Response::default().add_attribute("action", "unfreeze"))
 }
@@ -121,7 +339,7 @@ pub fn try_forgeting_tokens(
         .collect::<StdResult<Vec<String>>>()?
     } else { token_ids } 
 {
-        KNOWN_TOKENS.remove(storage, (collection.as_str(), id.as_str()));
+        remove_known_token(storage, collection.as_str(), id.as_str())?;
     }
     Ok(Response::new().add_attribute("action", "forget_tokens"))
 }
@@ -134,14 +352,15 @@ pub fn try_updating_known_tokens(
     start_after: Option<String>,
     limit: Option<u32>,
 ) -> ContractResult {
-    for id in  query_tokens(
-        &querier,
+    let backend = collection_backend(querier, storage, &collection)?;
+    for id in  backend.query_tokens(
+        querier,
         &collection,
         env.contract.address.to_string(),
         start_after,
         limit,
     )?.tokens {
-        KNOWN_TOKENS.save(storage, (collection.as_str(), id.as_str()), &true)?;
+        save_known_token(storage, collection.as_str(), id.as_str())?;
     }
     Ok(Response::new().add_attributes(vec![
         ("action", "update_known_tokens"),
@@ -149,57 +368,16 @@ pub fn try_updating_known_tokens(
     ]))
 }
 
-pub fn try_transfering_token(
-    storage: &mut dyn Storage,
-    contract_addr: String,
-    token_id: String,
-    recipient: String,
-) -> ContractResult {
-    KNOWN_TOKENS.remove(storage, (contract_addr.as_str(), token_id.as_str()));
-    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        msg: to_json_binary(&Cw721Msg::TransferNft {
-            recipient,
-            token_id,
-        })?,
-        contract_addr,
-        funds: vec![],
-    });
-    Ok(Response::default()
-        .add_message(msg)
-        .add_attribute("action", "transfer_token"))
-}
 
-#[cfg(not(feature = "omniflix"))]
-pub fn try_sending_token(
-    storage: &mut dyn Storage,
-    contract_addr: String,
-    token_id: String,
-    contract: String,
-    msg: Binary,
+pub fn try_executing_queued(
+    deps: &mut DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    id: u64,
 ) -> ContractResult {
-    KNOWN_TOKENS.remove(storage, (contract_addr.as_str(), token_id.as_str()));
-    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        msg: to_json_binary(&Cw721Msg::SendNft {
-            contract,
-            token_id,
-            msg
-        })?,
-        contract_addr,
-        funds: vec![],
-    });
-    Ok(Response::default()
-        .add_message(msg)
-        .add_attribute("action", "send_token"))
-}
-
-pub fn try_fee_granting(
-    contract: &str,
-    grantee: &str,
-    allowance: Option<BasicAllowance>,
-) -> Result<Response, ContractError> {
-    let msg = encode_feegrant_msg(contract, grantee, allowance)?;
-
-    Ok(Response::new()
-        .add_message(msg)
-        .add_attribute("action", "fee_grant"))
+    let action = take_ready_action(deps.storage, env, id)?;
+    let res = run_action(deps, env, info, action)?;
+    Ok(res
+        .add_attribute("action", "execute_queued")
+        .add_attribute("id", id.to_string()))
 }
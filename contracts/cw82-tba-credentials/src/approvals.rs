@@ -0,0 +1,339 @@
+use cosmwasm_std::{
+    ensure, to_json_string, Coin, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult,
+    Storage,
+};
+use cw_storage_plus::Bound;
+use cw_tba::{
+    ActiontMsg, ApprovalMsg, PendingApproval, PendingApprovalsResponse, SensitiveAction,
+    TwoFactorConfig, APPROVAL_TTL,
+};
+use saa_wasm::{
+    account_number, get_stored_credentials, has_credential, remove_credentials,
+    saa_types::{
+        AuthError, CheckOption, Credential, CredentialId, CredentialName, Identifiable,
+        ReplayParams, ReplayProtection, Verifiable,
+    },
+    stores::ACCOUNT_NUMBER,
+    verify_native,
+};
+
+use crate::{
+    error::ContractError,
+    execute::try_executing_actions,
+    msg::ContractResult,
+    state::{APPROVALS, APPROVAL_COUNT, MIGRATION_APPROVAL, TWO_FACTOR},
+    utils::{assert_owner_derivable, assert_status},
+};
+
+const DEFAULT_BATCH_SIZE: u32 = 100;
+
+
+/// Verify that every credential is stored on the account and has signed the actions
+/// with the current account number. Returns the ids of the distinct signers.
+/// A native credential is meant to count as a signer when it is the sender, since the
+/// transaction signature proves control over it the same way a message signature does
+pub fn verify_signers(
+    deps: &mut DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    creds: Vec<Credential>,
+    actions: &[ActiontMsg],
+) -> Result<Vec<CredentialId>, ContractError> {
+    ensure!(!creds.is_empty(), ContractError::Unauthorized("No credentials".into()));
+
+    let nonce = account_number(deps.storage);
+    let msgs = vec![to_json_string(&actions)?];
+    let mut signers: Vec<CredentialId> = vec![];
+
+    for cred in creds {
+        let id = cred.id();
+        ensure!(
+            has_credential(deps.storage, id.clone(), Some(cred.name())),
+            ContractError::Unauthorized(format!("{id} is not a stored credential"))
+        );
+        ensure!(
+            !signers.contains(&id),
+            ContractError::Generic(format!("{id} has signed more than once"))
+        );
+        if cred.name() == CredentialName::Native {
+            // native credentials can't sign anything and must be the sender instead
+            ensure!(
+                info.sender.as_str().to_lowercase() == id,
+                ContractError::Unauthorized(format!("{id} must be the sender"))
+            );
+        } else {
+            cred.verify(deps.as_ref())?;
+            let params = ReplayParams::new(nonce, CheckOption::Messages(msgs.clone()));
+            cred.protect_reply(env, params).map_err(AuthError::from)?;
+        }
+        signers.push(id);
+    }
+    ACCOUNT_NUMBER.save(deps.storage, &(nonce + 1))?;
+    Ok(signers)
+}
+
+/// Make sure the sensitive actions are authorised by enough credentials
+pub fn assert_two_factor(
+    storage: &dyn Storage,
+    actions: &[ActiontMsg],
+    signers: usize,
+) -> Result<(), ContractError> {
+    if signers >= 2 {
+        return Ok(());
+    }
+    if let Some(config) = TWO_FACTOR.may_load(storage)? {
+        ensure!(
+            !config.requires_two(actions),
+            ContractError::Unauthorized("The actions must be approved by two credentials".into())
+        );
+    }
+    Ok(())
+}
+
+pub fn assert_removal_allowed(storage: &dyn Storage) -> Result<(), ContractError> {
+    let covered = TWO_FACTOR
+        .may_load(storage)?
+        .map(|c| c.actions.contains(&SensitiveAction::RemoveCredentials))
+        .unwrap_or_default();
+    ensure!(
+        !covered,
+        ContractError::Unauthorized("Credentials must be removed with two credentials".into())
+    );
+    Ok(())
+}
+
+pub fn try_handling_approvals(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ApprovalMsg,
+) -> ContractResult {
+    assert_status(deps.storage)?;
+
+    match msg {
+        ApprovalMsg::Propose { msgs, signed } => {
+            let signer = verify_signers(&mut deps, &env, &info, vec![signed], &msgs)?.remove(0);
+            let id = APPROVAL_COUNT.may_load(deps.storage)?.unwrap_or_default();
+            APPROVALS.save(
+                deps.storage,
+                id,
+                &PendingApproval {
+                    id,
+                    actions: msgs,
+                    proposer: signer.clone(),
+                    expires_at: env.block.time.plus_seconds(APPROVAL_TTL),
+                },
+            )?;
+            APPROVAL_COUNT.save(deps.storage, &(id + 1))?;
+
+            Ok(Response::new()
+                .add_attribute("action", "propose_approval")
+                .add_attribute("id", id.to_string())
+                .add_attribute("proposer", signer))
+        }
+
+        ApprovalMsg::Approve { id, signed } => {
+            let pending = APPROVALS
+                .may_load(deps.storage, id)?
+                .ok_or(ContractError::Generic(format!("No pending approval with id {id}")))?;
+            APPROVALS.remove(deps.storage, id);
+
+            ensure!(
+                env.block.time < pending.expires_at,
+                ContractError::Generic(format!("Approval {id} has expired"))
+            );
+            ensure!(
+                has_credential(deps.storage, pending.proposer.clone(), None),
+                ContractError::Generic("The proposer is no longer a stored credential".into())
+            );
+            let signer =
+                verify_signers(&mut deps, &env, &info, vec![signed], &pending.actions)?.remove(0);
+            ensure!(
+                signer != pending.proposer,
+                ContractError::Generic("Must be approved by a different credential".into())
+            );
+
+            let res = try_executing_actions(deps, &env, info, pending.actions)?;
+            Ok(res
+                .add_attribute("action", "approve_approval")
+                .add_attribute("id", id.to_string())
+                .add_attribute("approver", signer))
+        }
+
+        ApprovalMsg::Cancel { id } => {
+            verify_native(deps.storage, info.sender.to_string())?;
+            ensure!(
+                APPROVALS.has(deps.storage, id),
+                ContractError::Generic(format!("No pending approval with id {id}"))
+            );
+            APPROVALS.remove(deps.storage, id);
+            Ok(Response::new()
+                .add_attribute("action", "cancel_approval")
+                .add_attribute("id", id.to_string()))
+        }
+    }
+}
+
+pub fn try_updating_two_factor(
+    storage: &mut dyn Storage,
+    actions: Vec<SensitiveAction>,
+    thresholds: Vec<Coin>,
+) -> ContractResult {
+    if actions.is_empty() {
+        TWO_FACTOR.remove(storage);
+        return Ok(Response::new().add_attribute("action", "remove_two_factor"));
+    }
+    // otherwise the sensitive actions could never be executed again
+    ensure!(
+        get_stored_credentials(storage)?.records.len() >= 2,
+        ContractError::Generic("At least two credentials must be stored".into())
+    );
+    TWO_FACTOR.save(storage, &TwoFactorConfig { actions, thresholds })?;
+    Ok(Response::new().add_attribute("action", "update_two_factor"))
+}
+
+pub fn try_removing_credentials(
+    storage: &mut dyn Storage,
+    owner: &str,
+    ids: Vec<CredentialId>,
+) -> ContractResult {
+    let rest = remove_credentials(storage, &ids)?;
+    assert_owner_derivable(&rest, owner)?;
+    if TWO_FACTOR.exists(storage) {
+        ensure!(
+            rest.len() >= 2,
+            ContractError::Generic("Two factor approval needs at least two credentials".into())
+        );
+    }
+    Ok(Response::new().add_attribute("action", "remove_credentials"))
+}
+
+pub fn try_approving_migration(storage: &mut dyn Storage, code_id: u64) -> ContractResult {
+    MIGRATION_APPROVAL.save(storage, &code_id)?;
+    Ok(Response::new()
+        .add_attribute("action", "approve_migration")
+        .add_attribute("code_id", code_id.to_string()))
+}
+
+/// Migrations must be approved beforehand if the owner has asked for it
+pub fn assert_migration_approved(deps: &mut DepsMut, env: &Env) -> Result<(), ContractError> {
+    let covered = TWO_FACTOR
+        .may_load(deps.storage)?
+        .map(|c| c.actions.contains(&SensitiveAction::MigrationApproval))
+        .unwrap_or_default();
+    let approved = MIGRATION_APPROVAL.may_load(deps.storage)?;
+    MIGRATION_APPROVAL.remove(deps.storage);
+
+    if covered {
+        let code_id = deps
+            .querier
+            .query_wasm_contract_info(env.contract.address.as_str())?
+            .code_id;
+        ensure!(
+            approved == Some(code_id),
+            ContractError::Unauthorized(format!("Migration to {code_id} hasn't been approved"))
+        );
+    }
+    Ok(())
+}
+
+pub fn pending_approvals(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PendingApprovalsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    APPROVALS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, approval)| approval))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use saa_wasm::saa_types::{Caller, CredentialInfo, VerifiedData};
+
+    fn native_credentials(ids: &[&str]) -> VerifiedData {
+        VerifiedData {
+            credentials: ids
+                .iter()
+                .map(|id| {
+                    (id.to_string(), CredentialInfo {
+                        name: CredentialName::Native,
+                        hrp: None,
+                        extension: None,
+                        address: None,
+                    })
+                })
+                .collect(),
+            addresses: vec![],
+            primary_id: ids[0].to_string(),
+            override_primary: true,
+            has_natives: true,
+            has_extensions: false,
+            nonce: 0,
+        }
+    }
+
+    fn native(id: &str) -> Credential {
+        Credential::Native(Caller::from(id))
+    }
+
+    #[test]
+    fn native_sender_counts_as_a_signer() {
+        let mut deps = mock_dependencies();
+        saa_wasm::save_credentials(&mut deps.storage, &native_credentials(&["owner"])).unwrap();
+        let info = mock_info("owner", &[]);
+
+        let signers =
+            verify_signers(&mut deps.as_mut(), &mock_env(), &info, vec![native("owner")], &[]).unwrap();
+        assert_eq!(signers, vec!["owner".to_string()]);
+    }
+
+    #[test]
+    fn native_credential_must_be_the_sender() {
+        let mut deps = mock_dependencies();
+        saa_wasm::save_credentials(&mut deps.storage, &native_credentials(&["owner", "other"])).unwrap();
+        let info = mock_info("owner", &[]);
+
+        let err = verify_signers(&mut deps.as_mut(), &mock_env(), &info, vec![native("other")], &[]);
+        assert!(matches!(err, Err(ContractError::Unauthorized(_))));
+
+        // the sender alone is never two distinct signers
+        let err = verify_signers(
+            &mut deps.as_mut(),
+            &mock_env(),
+            &info,
+            vec![native("owner"), native("owner")],
+            &[],
+        );
+        assert!(matches!(err, Err(ContractError::Generic(_))));
+    }
+
+    #[test]
+    fn sensitive_actions_need_two_signers() {
+        let mut deps = mock_dependencies();
+        let guardians = vec![ActiontMsg::UpdateGuardians {
+            guardians: vec!["guardian".into()],
+            threshold: 1,
+            delay: crate::recovery::MIN_RECOVERY_DELAY,
+        }];
+        assert!(assert_two_factor(&deps.storage, &guardians, 1).is_ok());
+
+        TWO_FACTOR
+            .save(&mut deps.storage, &TwoFactorConfig {
+                actions: vec![SensitiveAction::FeeGrants],
+                thresholds: vec![],
+            })
+            .unwrap();
+        assert!(assert_two_factor(&deps.storage, &guardians, 1).is_err());
+        assert!(assert_two_factor(&deps.storage, &guardians, 2).is_ok());
+        assert!(assert_two_factor(&deps.storage, &[ActiontMsg::VetoRecovery {}], 1).is_ok());
+    }
+}
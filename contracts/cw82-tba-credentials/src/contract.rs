@@ -5,23 +5,34 @@ use saa_wasm::{account_number, verify_native};
 use {crate::grants::register_granter_msg, cosmwasm_std::SubMsg};
 
 use cosmwasm_std::{
-    Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult,
-    to_json_binary as to_bin
+    to_json_binary as to_bin, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError,
+    StdResult,
 };
 
 use cw_ownable::get_ownership;
-use cw_tba::{ExecuteMsg, Status};
+use cw_tba::{
+    query_staking, try_handling_ics721_ack, try_syncing_known_tokens,
+    try_updating_known_cw20_on_receive, ActiontMsg, ExecuteMsg, Status,
+};
 use crate::execute;
 
 use crate::{
     action::{self, MINT_REPLY_ID},
+    approvals::{
+        assert_migration_approved, assert_two_factor, pending_approvals, try_handling_approvals,
+    },
     error::ContractError,
     msg::{ContractResult, InstantiateMsg, MigrateMsg, QueryMsg},
     query::{
-        assets, can_execute, can_execute_signed, full_info, known_tokens, valid_signature, valid_signatures,
+        account_tree, assets, token_approvals, token_attributes, watched_collections, can_execute, can_execute_native, can_execute_signed, fee_grants, full_info,
+        executors, known_collections, known_tokens, plugins, policy, policy_simulation, pull_authorizations, queued_actions, scheduled_payments,
+        valid_signature, valid_signatures,
        /*  valid_signatures,  */
     },
-    state::{save_token_credentials, MINT_CACHE, REGISTRY_ADDRESS, STATUS, TOKEN_INFO},
+    state::{
+        rebuild_known_collections, save_token_credentials, IBC_CONFIG, MINT_CACHE, RECEIVE_CONFIG,
+        REGISTRY_ADDRESS, REMOTE_HOLDER, STATUS, TIMELOCK, TOKEN_INFO, TWO_FACTOR,
+    },
 };
 
 pub const CONTRACT_NAME: &str = "crates:cw82-tba-creds";
@@ -71,8 +82,28 @@ pub fn instantiate(
 }
 
 
+#[cfg(not(feature = "archway"))]
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> ContractResult {
+    execute_account(deps, env, info, msg)
+}
+
+#[cfg(feature = "archway")]
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: crate::msg::ExtendedExecuteMsg,
+) -> ContractResult {
+    use crate::msg::ExtendedExecuteMsg;
+    match msg {
+        ExtendedExecuteMsg::Account(msg) => execute_account(deps, env, info, msg),
+        ExtendedExecuteMsg::Archway(msg) => crate::grants::try_handling_archway_msg(deps, info, msg),
+    }
+}
+
+fn execute_account(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> ContractResult {
     if REGISTRY_ADDRESS.load(deps.storage).is_err() {
         return Err(ContractError::Deleted {});
     }
@@ -89,19 +120,49 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> C
         }
 
         ExecuteMsg::ReceiveNft(msg) => {
-            execute::try_updating_known_on_receive(deps, info.sender.to_string(), msg.token_id)
+            execute::try_updating_known_on_receive(
+                deps,
+                &env,
+                info.sender.to_string(),
+                msg.token_id,
+                msg.msg,
+            )
+        }
+
+        ExecuteMsg::Ics721AckCallback(msg) => Ok(try_handling_ics721_ack(deps, &env, info, msg)?),
+
+        ExecuteMsg::Ics721ReceiveCallback(msg) => execute::try_receiving_ics721(deps, &env, info, msg),
+
+        ExecuteMsg::Recovery(msg) => crate::recovery::try_recovering(deps, env, info, msg),
+
+        ExecuteMsg::Approvals(msg) => try_handling_approvals(deps, env, info, msg),
+
+        ExecuteMsg::Executor(msg) => execute::try_executing_as_executor(deps, &env, info, msg),
+
+        ExecuteMsg::Payments(msg) => {
+            crate::payments::try_handling_payments(deps.storage, &env, &info, msg)
+        }
+
+        ExecuteMsg::Receive(msg) => {
+            Ok(try_updating_known_cw20_on_receive(deps.storage, info.sender.to_string(), msg.amount)?)
         }
 
         ExecuteMsg::Purge {} => execute::try_purging(deps.api, deps.storage, info.sender.as_str()),
 
         ExecuteMsg::Freeze {} => execute::try_freezing(deps),
 
+        ExecuteMsg::SyncKnownTokens { limit } => Ok(try_syncing_known_tokens(deps, env, limit)?),
+
         ExecuteMsg::Execute { msgs, .. } => {
             verify_native(deps.storage, info.sender.to_string())?;
-            action::try_executing(msgs)
+            // goes through the same checks as the proxy action, including the timelock
+            let actions = vec![ActiontMsg::Execute { msgs }];
+            assert_two_factor(deps.storage, &actions, 1)?;
+            execute::try_executing_actions(deps, &env, info, actions)
         }
         ExecuteMsg::ExecuteNative { msgs } => {
             verify_native(deps.storage, info.sender.to_string())?;
+            assert_two_factor(deps.storage, &msgs, 1)?;
             execute::try_executing_actions(deps, &env, info, msgs)
         }
 
@@ -117,40 +178,126 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> C
     )
 }
 
+#[cfg(not(feature = "archway"))]
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    query_account(deps, env, msg)
+}
+
+#[cfg(feature = "archway")]
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: crate::msg::ExtendedQueryMsg) -> StdResult<Binary> {
+    use crate::msg::ExtendedQueryMsg;
+    match msg {
+        ExtendedQueryMsg::Account(msg) => query_account(deps, env, *msg),
+        ExtendedQueryMsg::Archway(cw_tba::ArchwayQueryMsg::CwFees {}) => {
+            to_bin(&crate::grants::cw_fees(deps, env)?)
+        }
+    }
+}
+
+fn query_account(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     let registry = match REGISTRY_ADDRESS.load(deps.storage) {
         Err(_) => return Err(StdError::generic_err(ContractError::Deleted {}.to_string())),
         Ok(r) => r
     };
     match msg {
         QueryMsg::Registry {} => to_bin(&registry),
+        QueryMsg::RemoteHolder {} => to_bin(&REMOTE_HOLDER.may_load(deps.storage)?),
         QueryMsg::Token {} => to_bin(&TOKEN_INFO.load(deps.storage)?),
         QueryMsg::Status {} => to_bin(&STATUS.load(deps.storage)?),
         QueryMsg::Ownership {} => to_bin(&get_ownership(deps.storage)?),
         QueryMsg::AccountNumber {} => to_bin(&account_number(deps.storage)),
         
-        QueryMsg::KnownTokens { 
-            skip, 
-            limit 
-        } => to_bin(&known_tokens(deps, skip, limit)?),
+        QueryMsg::KnownTokens {
+            collection,
+            start_after,
+            limit,
+            with_metadata
+        } => to_bin(&known_tokens(deps, collection, start_after, limit, with_metadata)?),
+
+        QueryMsg::KnownCollections {
+            start_after,
+            limit
+        } => to_bin(&known_collections(deps, start_after, limit)?),
         
         QueryMsg::Assets { 
             skip, 
-            limit 
-        } => to_bin(&assets(deps, env, skip, limit)?),
+            limit,
+            with_metadata
+        } => to_bin(&assets(deps, env, skip, limit, with_metadata)?),
         
+        QueryMsg::Staking {} => to_bin(&query_staking(&deps.querier, env.contract.address.as_str())?),
+
+        QueryMsg::FeeGrants { 
+            start_after,
+            limit
+        } => to_bin(&fee_grants(deps, start_after, limit)?),
+
+        QueryMsg::Timelock {} => to_bin(&TIMELOCK.may_load(deps.storage)?),
+
+        QueryMsg::QueuedActions { 
+            start_after,
+            limit
+        } => to_bin(&queued_actions(deps, start_after, limit)?),
+
+        QueryMsg::ScheduledPayments { 
+            start_after,
+            limit
+        } => to_bin(&scheduled_payments(deps, start_after, limit)?),
+
+        QueryMsg::PullAuthorizations { 
+            start_after,
+            limit
+        } => to_bin(&pull_authorizations(deps, start_after, limit)?),
+
+        QueryMsg::Executors {
+            start_after,
+            limit
+        } => to_bin(&executors(deps, start_after, limit)?),
+
+        QueryMsg::Plugins {} => to_bin(&plugins(deps)?),
+
+        QueryMsg::Policy {} => to_bin(&policy(deps)?),
+
+        QueryMsg::SimulatePolicy { actions } => to_bin(&policy_simulation(deps, env, actions)?),
+
+        QueryMsg::WatchedCollections {} => to_bin(&watched_collections(deps)?),
+
+        QueryMsg::TokenApprovals {} => to_bin(&token_approvals(deps)?),
+
+        QueryMsg::ReceiveConfig {} => to_bin(&RECEIVE_CONFIG.may_load(deps.storage)?.unwrap_or_default()),
+
+        QueryMsg::TokenAttributes {
+            collection,
+            token_id
+        } => to_bin(&token_attributes(deps, collection, token_id)?),
+
+        QueryMsg::AccountTree { depth } => to_bin(&account_tree(deps, env, depth)?),
+
+        QueryMsg::TwoFactor {} => to_bin(&TWO_FACTOR.may_load(deps.storage)?),
+
+        QueryMsg::PendingApprovals {
+            start_after,
+            limit
+        } => to_bin(&pending_approvals(deps, start_after, limit)?),
+
+        QueryMsg::Recovery {} => to_bin(&crate::recovery::recovery(deps)?),
+
+        QueryMsg::IbcConfig {} => to_bin(&IBC_CONFIG.may_load(deps.storage)?.unwrap_or_default()),
+
         QueryMsg::FullInfo { 
             skip, 
-            limit 
-        } => to_bin(&full_info(deps, env, skip, limit)?),
+            limit,
+            with_metadata
+        } => to_bin(&full_info(deps, env, skip, limit, with_metadata)?),
         
         // QueryMsg::SessionQueries(q) => handle_session_query(deps.api, deps.storage, &env, q),
         
         QueryMsg::CanExecute { 
             sender, 
             msg 
-        } => to_bin(&can_execute(deps, sender, msg)?),
+        } => to_bin(&can_execute(deps, env, sender, msg)?),
         
         QueryMsg::CanExecuteSigned { 
             msgs, 
@@ -170,16 +317,19 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             payload,
         } => to_bin(&valid_signatures(deps, env, data, signatures, payload)?),
 
-        QueryMsg::CanExecuteNative { .. } => Err(StdError::generic_err(
-            "CanExecuteNative query is not supported for now",
-        )),
+        QueryMsg::CanExecuteNative { 
+            sender, 
+            msg 
+        } => to_bin(&can_execute_native(deps, env, sender, msg)?),
     }
 }
 
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _: Env, _: MigrateMsg) -> ContractResult {
+pub fn migrate(mut deps: DepsMut, env: Env, _: MigrateMsg) -> ContractResult {
+    assert_migration_approved(&mut deps, &env)?;
     STATUS.save(deps.storage, &Status { frozen: false })?;
+    rebuild_known_collections(deps.storage)?;
     Ok(Response::default())
 }
 
@@ -207,7 +357,7 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> ContractResult {
 #[cfg(feature = "archway")]
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn sudo(deps: DepsMut, env: Env, msg: crate::msg::SudoMsg) -> ContractResult {
-    return match msg {
+    match msg {
         crate::msg::SudoMsg::CwGrant(grant) => crate::grants::cwfee_grant(deps, env, grant),
-    };
+    }
 }
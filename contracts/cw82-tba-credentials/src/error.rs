@@ -1,5 +1,6 @@
 use cosmwasm_std::StdError;
 use cw_ownable::OwnershipError;
+use cw_tba::AccountError;
 use saa_wasm::saa_types::{errors::StorageError, AuthError};
 use thiserror::Error;
 
@@ -11,6 +12,9 @@ pub enum ContractError {
     #[error("{0}")]
     Ownership(#[from] OwnershipError),
 
+    #[error("{0}")]
+    Account(#[from] AccountError),
+
     #[error("{0}")]
     Auth(#[from] AuthError),
 
@@ -32,6 +36,9 @@ pub enum ContractError {
     #[error("Account is frozen until ownership or credentials updates")]
     Frozen {},
 
+    #[error("Account is locked while the token is on another chain")]
+    Bridged {},
+
     #[error("Deleted")]
     Deleted {},
 
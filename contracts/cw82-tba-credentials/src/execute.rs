@@ -1,42 +1,130 @@
 use crate::{
     action::execute_action,
+    approvals::{assert_removal_allowed, assert_two_factor, verify_signers},
     error::ContractError,
     msg::ContractResult,
-    state::{save_token_credentials, KNOWN_TOKENS, REGISTRY_ADDRESS, STATUS, TOKEN_INFO},
-    utils::{assert_owner_derivable, assert_registry, assert_status},
+    state::{
+        save_known_token, save_token_credentials, APPROVALS, APPROVAL_COUNT, EXECUTORS, FEE_GRANTS,
+        GUARDIANS, IBC_CONFIG, KNOWN_COLLECTIONS, KNOWN_CW20S, KNOWN_TOKENS, MIGRATION_APPROVAL,
+        PAYMENTS, PAYMENT_COUNT, PLUGINS, POLICY, PULLS, QUEUE, QUEUE_COUNT, RECEIVE_CONFIG,
+        RECOVERY, REGISTRY_ADDRESS, REMOTE_HOLDER, STATUS, TIMELOCK, TOKEN_APPROVALS,
+        TOKEN_ATTRIBUTES, TOKEN_INFO, TWO_FACTOR, WATCHED_COLLECTIONS,
+    },
+    utils::{assert_owner_derivable, assert_registry, assert_status, builtin_policy},
+};
+use cosmwasm_std::{
+    ensure, Api, Binary, DepsMut, Env, MessageInfo, Response, Storage,
 };
-use cosmwasm_std::{ensure, to_json_string, Api, DepsMut, Env, MessageInfo, Response, Storage};
 use cw2::CONTRACT;
 use cw22::SUPPORTED_INTERFACES;
 use cw_ownable::{get_ownership, Action};
-use cw_tba::{verify_nft_ownership, ActiontMsg, Status, UpdateAccountOp};
-use saa_wasm::{
-    add_credentials, stores::ACCOUNT_NUMBER, remove_credentials, saa_types::{Credential, VerifiedData}, verify_cred_actions
+use cw_tba::{
+    assert_no_cycle, assert_plugins_validation, assert_policy, collection_backend, plugin_hook_msgs,
+    remote_holder_actions, revoke_token_approvals, try_handling_ics721_receive, use_executor_grant,
+    verify_nft_ownership, ActiontMsg, ExecutorMsg, Ics721ReceiveCallbackMsg, ReceivePayload,
+    SignedCredentials, Status, TokenInfo, UpdateAccountOp,
 };
+use saa_wasm::{add_credentials, remove_credentials, saa_types::VerifiedData};
 
 pub fn try_executing_signed(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    cred: Credential,
+    signed: SignedCredentials,
     msgs: Vec<ActiontMsg>,
 ) -> ContractResult {
     assert_status(deps.storage)?;
-    let num = verify_cred_actions(deps.storage, &env, cred, Some(vec![to_json_string(&msgs)?]))?;
-    ACCOUNT_NUMBER.save(deps.storage, &num)?;
+    let signers = verify_signers(&mut deps, &env, &info, signed.into_vec(), &msgs)?;
+    assert_two_factor(deps.storage, &msgs, signers.len())?;
     try_executing_actions(deps, &env, info, msgs)
 }
 
 
+pub fn try_executing_as_executor(
+    deps: DepsMut,
+    env: &Env,
+    info: MessageInfo,
+    msg: ExecutorMsg,
+) -> ContractResult {
+    let fee = use_executor_grant(&deps.querier, deps.storage, env, info.sender.as_str(), &msg)?;
+
+    // the actions are executed on behalf of the owner and not the executor
+    let owner = get_ownership(deps.storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized("The account has no owner".into()))?;
+    let executor = info.sender.to_string();
+    let info = MessageInfo {
+        sender: owner,
+        funds: vec![],
+    };
+    let res = try_executing_actions(deps, env, info, vec![msg.into_action()])?;
+
+    Ok(res
+        .add_messages(fee)
+        .add_attribute("executor", executor))
+}
+
 pub fn try_executing_actions(
+    deps: DepsMut,
+    env: &Env,
+    info: MessageInfo,
+    actions: Vec<ActiontMsg>,
+) -> ContractResult {
+    assert_status(deps.storage)?;
+    execute_actions(deps, env, info, actions)
+}
+
+/// Keep track of a received packet and run the actions the holder of the voucher sent along while the token is away
+pub fn try_receiving_ics721(
+    mut deps: DepsMut,
+    env: &Env,
+    info: MessageInfo,
+    msg: Ics721ReceiveCallbackMsg,
+) -> ContractResult {
+    let actions = remote_holder_actions(&deps.querier, deps.storage, info.sender.as_str(), &msg)?;
+    let holder = msg.original_packet.sender.clone();
+    let res = try_handling_ics721_receive(deps.branch(), env, info, msg)?;
+    let actions = match actions {
+        Some(actions) => actions,
+        None => return Ok(res),
+    };
+    // the packet stands for a single signer
+    assert_two_factor(deps.storage, &actions, 1)?;
+
+    // the actions are executed on behalf of the owner and not the bridge
+    let owner = get_ownership(deps.storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized("The account has no owner".into()))?;
+    let info = MessageInfo {
+        sender: owner,
+        funds: vec![],
+    };
+    let remote_res = execute_actions(deps, env, info, actions)?;
+
+    Ok(res
+        .add_submessages(remote_res.messages)
+        .add_events(remote_res.events)
+        .add_attributes(remote_res.attributes)
+        .add_attribute("remote_holder", holder))
+}
+
+fn execute_actions(
     mut deps: DepsMut,
     env: &Env,
     info: MessageInfo,
     actions: Vec<ActiontMsg>,
 ) -> ContractResult {
+    assert_policy(deps.storage, env, &builtin_policy(), &actions, &info.funds)?;
+    assert_plugins_validation(deps.as_ref(), env, info.sender.as_str(), &actions)?;
+    let hooks = plugin_hook_msgs(deps.storage, env, info.sender.as_str(), &actions)?;
+    let delayed = match TIMELOCK.may_load(deps.storage)? {
+        Some(timelock) => timelock.delayed_actions(&actions),
+        None => vec![false; actions.len()],
+    };
+
     let mut res = Response::new();
-    for act in actions {
-        let action_res = execute_action(&mut deps, &env, &info, act)?;
+    for (act, delayed) in actions.into_iter().zip(delayed) {
+        let action_res = execute_action(&mut deps, &env, &info, act, delayed)?;
         res = res
             .add_submessages(action_res.messages)
             .add_events(action_res.events)
@@ -45,7 +133,7 @@ pub fn try_executing_actions(
             res = res.set_data(data);
         }
     }
-    Ok(res)
+    Ok(res.add_messages(hooks))
 }
 
 pub fn try_updating_account_data(
@@ -57,7 +145,9 @@ pub fn try_updating_account_data(
     assert_registry(deps.storage, info.sender.as_str())?;
 
     let ownership = cw_ownable::get_ownership(deps.storage)?;
-    let owner = ownership.owner.unwrap();
+    let owner = ownership
+        .owner
+        .ok_or(ContractError::Unauthorized("The account has no owner".into()))?;
 
     match op {
         UpdateAccountOp::Add(data) => {
@@ -69,6 +159,7 @@ pub fn try_updating_account_data(
             }
         }
         UpdateAccountOp::Remove(idx) => {
+            assert_removal_allowed(deps.storage)?;
             let rest = remove_credentials(deps.storage, &idx)?;
             assert_owner_derivable(&rest, owner.as_str())?;
         }
@@ -84,11 +175,30 @@ pub fn try_updating_ownership(
     new_data: Option<VerifiedData>,
 ) -> ContractResult {
     assert_registry(deps.storage, info.sender.as_str())?;
-    let owner = get_ownership(deps.storage)?.owner.unwrap();
+    let owner = get_ownership(deps.storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized("The account has no owner".into()))?;
     let owner_str = owner.as_str();
 
     ensure!(new_owner != owner_str, ContractError::SameOwner {});
     saa_wasm::reset_credentials(deps.storage, false)?;
+    // guardians and queued actions belong to the previous holder
+    GUARDIANS.remove(deps.storage);
+    RECOVERY.remove(deps.storage);
+    TIMELOCK.remove(deps.storage);
+    QUEUE.clear(deps.storage);
+    PAYMENTS.clear(deps.storage);
+    PULLS.clear(deps.storage);
+    EXECUTORS.clear(deps.storage);
+    PLUGINS.clear(deps.storage);
+    POLICY.remove(deps.storage);
+    TWO_FACTOR.remove(deps.storage);
+    APPROVALS.clear(deps.storage);
+    MIGRATION_APPROVAL.remove(deps.storage);
+    RECEIVE_CONFIG.remove(deps.storage);
+    REMOTE_HOLDER.remove(deps.storage);
+    // so that nobody approved by the previous holder can take the tokens
+    let revoke_msgs = revoke_token_approvals(&deps.querier, deps.storage, &env)?;
 
     if let Some(data) = new_data {
         STATUS.save(deps.storage, &Status { frozen: false })?;
@@ -108,6 +218,7 @@ pub fn try_updating_ownership(
     }
 
     Ok(Response::default()
+        .add_messages(revoke_msgs)
         .add_attribute("action", "update_ownership")
         .add_attribute("new_owner", new_owner.as_str()))
 }
@@ -115,28 +226,89 @@ pub fn try_updating_ownership(
 
 pub fn try_updating_known_on_receive(
     deps: DepsMut,
+    env: &Env,
     collection: String,
     token_id: String,
+    msg: Binary,
 ) -> ContractResult {
-    KNOWN_TOKENS.save(
-        deps.storage,
-        (collection.as_str(), token_id.as_str()),
-        &true,
-    )?;
-    Ok(Response::default().add_attributes(vec![
+    // erroring out bounces the token back to the sender
+    let tokens = [TokenInfo {
+        collection: collection.clone(),
+        id: token_id.clone(),
+    }];
+    assert_no_cycle(&deps.querier, deps.storage, env, env.contract.address.as_str(), &tokens)?;
+
+    let payload = RECEIVE_CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .parse(&msg)?;
+
+    let res = Response::default().add_attributes(vec![
         ("action", "update_known_on_receive"),
         ("collection", collection.as_str()),
         ("token_id", token_id.as_str()),
-    ]))
+    ]);
+    let key = (collection.as_str(), token_id.as_str());
+
+    let payload = match payload {
+        Some(payload) => payload,
+        None => {
+            save_known_token(deps.storage, &collection, &token_id)?;
+            return Ok(res);
+        }
+    };
+    payload.validate(&env.block.time)?;
+
+    match payload {
+        ReceivePayload::Forward { recipient } => {
+            assert_status(deps.storage)?;
+            assert_no_cycle(&deps.querier, deps.storage, env, &recipient, &tokens)?;
+            let msg = collection_backend(&deps.querier, deps.storage, &collection)?.transfer_msg(
+                &collection,
+                &token_id,
+                env.contract.address.as_str(),
+                &recipient,
+            )?;
+            Ok(res.add_message(msg).add_attribute("payload", "forward"))
+        }
+
+        ReceivePayload::Stake { contract, msg } => {
+            assert_status(deps.storage)?;
+            assert_no_cycle(&deps.querier, deps.storage, env, &contract, &tokens)?;
+            let msg = collection_backend(&deps.querier, deps.storage, &collection)?
+                .send_msg(&collection, &token_id, &contract, msg, vec![])?;
+            Ok(res.add_message(msg).add_attribute("payload", "stake"))
+        }
+
+        ReceivePayload::Lock { until } => {
+            let mut attrs = TOKEN_ATTRIBUTES.may_load(deps.storage, key)?.unwrap_or_default();
+            // a lock can only be extended
+            attrs.locked_until = Some(attrs.locked_until.map_or(until, |t| t.max(until)));
+            TOKEN_ATTRIBUTES.save(deps.storage, key, &attrs)?;
+            save_known_token(deps.storage, &collection, &token_id)?;
+            Ok(res.add_attribute("payload", "lock"))
+        }
+
+        ReceivePayload::Tag { label } => {
+            let mut attrs = TOKEN_ATTRIBUTES.may_load(deps.storage, key)?.unwrap_or_default();
+            attrs.label = Some(label);
+            TOKEN_ATTRIBUTES.save(deps.storage, key, &attrs)?;
+            save_known_token(deps.storage, &collection, &token_id)?;
+            Ok(res.add_attribute("payload", "tag"))
+        }
+    }
 }
 
 pub fn try_freezing(deps: DepsMut) -> ContractResult {
     let token = TOKEN_INFO.load(deps.storage)?;
-    let owner = cw_ownable::get_ownership(deps.storage)?.owner.unwrap();
+    let owner = cw_ownable::get_ownership(deps.storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized("The account has no owner".into()))?;
 
+    let backend = collection_backend(&deps.querier, deps.storage, &token.collection)?;
     // only allow freezing if the token owner is differnt from the stored owner
     ensure!(
-        verify_nft_ownership(&deps.querier, owner.as_str(), token).is_err(),
+        verify_nft_ownership(&deps.querier, &backend, owner.as_str(), token.clone()).is_err(),
         ContractError::Unauthorized(
             "Can only freeze if the owner has changed or called by the owner".into()
         )
@@ -155,5 +327,167 @@ pub fn try_purging(api: &dyn Api, store: &mut dyn Storage, sender: &str) -> Cont
     TOKEN_INFO.remove(store);
     STATUS.remove(store);
     KNOWN_TOKENS.clear(store);
+    KNOWN_COLLECTIONS.clear(store);
+    KNOWN_CW20S.clear(store);
+    IBC_CONFIG.remove(store);
+    FEE_GRANTS.clear(store);
+    GUARDIANS.remove(store);
+    RECOVERY.remove(store);
+    TIMELOCK.remove(store);
+    QUEUE.clear(store);
+    QUEUE_COUNT.remove(store);
+    PAYMENTS.clear(store);
+    PAYMENT_COUNT.remove(store);
+    PULLS.clear(store);
+    EXECUTORS.clear(store);
+    PLUGINS.clear(store);
+    POLICY.remove(store);
+    TWO_FACTOR.remove(store);
+    APPROVALS.clear(store);
+    APPROVAL_COUNT.remove(store);
+    MIGRATION_APPROVAL.remove(store);
+    RECEIVE_CONFIG.remove(store);
+    TOKEN_ATTRIBUTES.clear(store);
+    WATCHED_COLLECTIONS.clear(store);
+    TOKEN_APPROVALS.clear(store);
+    REMOTE_HOLDER.remove(store);
+    #[cfg(feature = "archway")]
+    {
+        crate::state::CW_FEES_POLICY.remove(store);
+        crate::state::CW_FEES_USAGE.remove(store);
+    }
     Ok(Response::default().add_attribute("action", "purge"))
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{
+        from_json,
+        testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage},
+        to_json_binary, BankMsg, ContractResult, OwnedDeps, SystemError, SystemResult, WasmQuery,
+    };
+    use cw_tba::{NftBackend, NonFungibleTokenPacketData, OwnerOfResponse, RemoteHolder};
+
+    #[cosmwasm_schema::cw_serde]
+    enum RegistryQueryMsg {
+        Ics721Bridges {},
+        CollectionBackend { collection: String },
+    }
+
+    /// Account whose token is escrowed by the bridge while `remote` holds the voucher
+    fn bridged_account() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        REGISTRY_ADDRESS.save(&mut deps.storage, &"registry".to_string()).unwrap();
+        TOKEN_INFO
+            .save(&mut deps.storage, &TokenInfo {
+                collection: "collection".into(),
+                id: "1".into(),
+            })
+            .unwrap();
+        STATUS.save(&mut deps.storage, &Status { frozen: false }).unwrap();
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some("owner")).unwrap();
+        REMOTE_HOLDER
+            .save(&mut deps.storage, &RemoteHolder {
+                bridge: "bridge".into(),
+                class_id: "collection".into(),
+                holder: "remote".into(),
+            })
+            .unwrap();
+
+        deps.querier.update_wasm(|query| {
+            let WasmQuery::Smart { contract_addr, msg } = query else {
+                unimplemented!()
+            };
+            let res = match contract_addr.as_str() {
+                "registry" => match from_json(msg).unwrap() {
+                    RegistryQueryMsg::Ics721Bridges {} => to_json_binary(&vec!["bridge"]),
+                    RegistryQueryMsg::CollectionBackend { .. } => {
+                        to_json_binary(&NftBackend::Cw721)
+                    }
+                },
+                "collection" => to_json_binary(&OwnerOfResponse {
+                    owner: "bridge".into(),
+                    approvals: vec![],
+                }),
+                // the bridge didn't mint the collection
+                addr => {
+                    return SystemResult::Err(SystemError::NoSuchContract {
+                        addr: addr.to_string(),
+                    })
+                }
+            };
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        });
+        deps
+    }
+
+    fn send(to: &str) -> Vec<ActiontMsg> {
+        vec![ActiontMsg::Execute {
+            msgs: vec![BankMsg::Send {
+                to_address: to.into(),
+                amount: vec![],
+            }
+            .into()],
+        }]
+    }
+
+    fn receive(sender: &str, actions: &[ActiontMsg]) -> Ics721ReceiveCallbackMsg {
+        Ics721ReceiveCallbackMsg {
+            nft_contract: "other".into(),
+            original_packet: NonFungibleTokenPacketData {
+                class_id: "other".into(),
+                class_uri: None,
+                class_data: None,
+                token_ids: vec!["7".into()],
+                token_uris: None,
+                token_data: None,
+                sender: sender.into(),
+                receiver: "anyone".into(),
+                memo: None,
+            },
+            msg: to_json_binary(&actions).unwrap(),
+        }
+    }
+
+    #[test]
+    fn voucher_holder_controls_the_bridged_account() {
+        let mut deps = bridged_account();
+        let env = mock_env();
+
+        let bridge = mock_info("bridge", &[]);
+
+        let msg = receive("remote", &send("friend"));
+        let res = try_receiving_ics721(deps.as_mut(), &env, bridge.clone(), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        // the packets of anyone else only update the known tokens
+        let msg = receive("stranger", &send("thief"));
+        let res = try_receiving_ics721(deps.as_mut(), &env, bridge, msg).unwrap();
+        assert!(res.messages.is_empty());
+    }
+
+    #[test]
+    fn local_owner_is_refused_while_bridged() {
+        let mut deps = bridged_account();
+        let env = mock_env();
+
+        let owner = mock_info("owner", &[]);
+        let err = try_executing_actions(deps.as_mut(), &env, owner, send("friend"));
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn bridged_account_can_be_frozen() {
+        let mut deps = bridged_account();
+
+        try_freezing(deps.as_mut()).unwrap();
+        assert!(STATUS.load(&deps.storage).unwrap().frozen);
+
+        // the freeze applies to the holder of the voucher as well
+        let msg = receive("remote", &send("friend"));
+        let err = try_receiving_ics721(deps.as_mut(), &mock_env(), mock_info("bridge", &[]), msg);
+        assert!(err.is_err());
+    }
+}
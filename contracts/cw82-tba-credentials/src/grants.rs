@@ -1,6 +1,16 @@
-use crate::error::ContractError;
+use crate::{
+    error::ContractError,
+    msg::ContractResult,
+    state::{CW_FEES_POLICY, CW_FEES_USAGE, REGISTRY_ADDRESS, TWO_FACTOR},
+    utils::assert_status,
+};
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Binary, Coin, CosmosMsg, DepsMut, Env, Response};
+use cosmwasm_std::{
+    ensure, Api, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdError,
+    StdResult, Storage, Timestamp,
+};
+use cw_tba::{ArchwayExecuteMsg, CwFeesPolicy, CwFeesResponse, CwFeesUsage, SensitiveAction};
+use saa_wasm::verify_native;
 
 #[cw_serde]
 pub struct CwGrant {
@@ -15,43 +25,189 @@ pub struct CwGrantMessage {
     pub msg: Binary,
 }
 
-pub fn cwfee_grant(deps: DepsMut, env: Env, msg: CwGrant) -> Result<Response, ContractError> {
-    let owner = cw_ownable::get_ownership(deps.storage)?
-        .owner
-        .ok_or(ContractError::NoOwnerCred {})?;
-
-    let owner = owner.as_str();
+pub fn cwfee_grant(deps: DepsMut, env: Env, msg: CwGrant) -> ContractResult {
+    assert_status(deps.storage)?;
+    let policy = CW_FEES_POLICY.may_load(deps.storage)?.unwrap_or_default();
 
     for m in &msg.msgs {
-        if owner == m.sender.as_str() {
-            continue;
-        }
-        if m.type_url == "cosmwasm.wasm.v1.MsgExecuteContract" {
-            let contract = anybuf::Bufany::deserialize(&m.msg)
-                .map_err(|e| ContractError::Generic(e.to_string()))?
-                .string(2)
-                .unwrap_or_default();
-            if env.contract.address.to_string() == contract {
-                continue;
+        ensure!(
+            is_eligible_sender(deps.storage, &policy, &m.sender)?,
+            ContractError::Unauthorized("Sender is not eligible for a cwfee grant".to_string())
+        );
+        ensure!(
+            policy.allowed_msgs.is_empty()
+                || policy
+                    .allowed_msgs
+                    .iter()
+                    .any(|t| t.trim_start_matches('/') == m.type_url.trim_start_matches('/')),
+            ContractError::Unauthorized(format!("Message {} is not eligible for a cwfee grant", m.type_url))
+        );
+    }
+
+    let mut usage = current_usage(deps.storage, &policy, &env.block.time)?;
+
+    for fee in msg.fee_requested {
+        let spent = match usage.spent.iter_mut().find(|c| c.denom == fee.denom) {
+            Some(coin) => {
+                coin.amount = coin.amount.checked_add(fee.amount).map_err(StdError::from)?;
+                coin.clone()
+            }
+            None => {
+                usage.spent.push(fee.clone());
+                fee
             }
+        };
+        if let Some(budget) = &policy.budget {
+            let limit = budget
+                .iter()
+                .find(|c| c.denom == spent.denom)
+                .map(|c| c.amount)
+                .unwrap_or_default();
+            ensure!(
+                spent.amount <= limit,
+                ContractError::Unauthorized(format!("Budget for {} is exceeded", spent.denom))
+            );
         }
-        return Err(ContractError::Unauthorized(
-            "Not ellible for a cwfee grant".to_string(),
-        ));
     }
+    CW_FEES_USAGE.save(deps.storage, &usage)?;
 
     Ok(Response::default())
 }
 
+fn is_eligible_sender(
+    storage: &dyn Storage,
+    policy: &CwFeesPolicy,
+    sender: &str,
+) -> StdResult<bool> {
+    if policy.addresses.iter().any(|a| a == sender) {
+        return Ok(true);
+    }
+    if policy.owner {
+        let owner = cw_ownable::get_ownership(storage)?.owner;
+        if owner.is_some_and(|o| o.as_str() == sender) {
+            return Ok(true);
+        }
+    }
+    if policy.credentials {
+        let records = saa_wasm::get_stored_credentials(storage)
+            .map(|c| c.records)
+            .unwrap_or_default();
+        return Ok(records.iter().any(|(_, info)| {
+            info.address
+                .as_ref()
+                .map(|a| a.to_string() == sender)
+                .unwrap_or_default()
+        }));
+    }
+    Ok(false)
+}
+
+/// Usage within the current period. The spendings are reset once the period is over
+fn current_usage(
+    storage: &dyn Storage,
+    policy: &CwFeesPolicy,
+    now: &Timestamp,
+) -> StdResult<CwFeesUsage> {
+    let fresh = CwFeesUsage {
+        period_start: *now,
+        spent: vec![],
+    };
+    Ok(match CW_FEES_USAGE.may_load(storage)? {
+        Some(usage) if usage.period_start.plus_seconds(policy.period) > *now => usage,
+        _ => fresh,
+    })
+}
+
+pub fn try_handling_archway_msg(
+    deps: DepsMut,
+    info: MessageInfo,
+    msg: ArchwayExecuteMsg,
+) -> ContractResult {
+    ensure!(REGISTRY_ADDRESS.load(deps.storage).is_ok(), ContractError::Deleted {});
+    assert_status(deps.storage)?;
+    verify_native(deps.storage, info.sender.to_string())?;
+
+    match msg {
+        ArchwayExecuteMsg::UpdateCwFeesPolicy { policy } => {
+            try_updating_cwfees_policy(deps.api, deps.storage, policy)
+        }
+    }
+}
+
+fn try_updating_cwfees_policy(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    policy: CwFeesPolicy,
+) -> ContractResult {
+    // the message isn't an action, so it can't be approved by a second credential
+    let two_factor = TWO_FACTOR
+        .may_load(storage)?
+        .map(|c| c.actions.contains(&SensitiveAction::FeeGrants))
+        .unwrap_or_default();
+    ensure!(
+        !two_factor,
+        ContractError::Unauthorized("Fee grants require two credentials".to_string())
+    );
+    ensure!(
+        policy.period > 0,
+        ContractError::Generic("Period must be positive".to_string())
+    );
+    policy
+        .addresses
+        .iter()
+        .try_for_each(|a| api.addr_validate(a).map(|_| ()))?;
+    CW_FEES_POLICY.save(storage, &policy)?;
+    Ok(Response::new().add_attribute("action", "update_cwfees_policy"))
+}
+
+pub fn cw_fees(deps: Deps, env: Env) -> StdResult<CwFeesResponse> {
+    let policy = CW_FEES_POLICY.may_load(deps.storage)?.unwrap_or_default();
+    let usage = current_usage(deps.storage, &policy, &env.block.time)?;
+    Ok(CwFeesResponse { policy, usage })
+}
+
 pub fn register_granter_msg(env: &Env) -> Result<CosmosMsg, ContractError> {
     let register_stargate_msg = CosmosMsg::Stargate {
         type_url: "/archway.cwfees.v1.MsgRegisterAsGranter".to_string(),
         value: Binary::from(
             anybuf::Anybuf::new()
-                .append_string(1, env.contract.address.to_string())
+                .append_string(1, &env.contract.address)
                 .into_vec(),
         ),
     };
 
     Ok(register_stargate_msg)
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{
+        coins,
+        testing::{mock_dependencies, mock_env},
+    };
+
+    fn grant(sender: &str) -> CwGrant {
+        CwGrant {
+            fee_requested: coins(1_000, "aarch"),
+            msgs: vec![CwGrantMessage {
+                sender: sender.into(),
+                type_url: "/cosmos.bank.v1beta1.MsgSend".into(),
+                msg: Binary::default(),
+            }],
+        }
+    }
+
+    #[test]
+    fn owner_fees_are_covered_by_default() {
+        let mut deps = mock_dependencies();
+        crate::state::STATUS
+            .save(&mut deps.storage, &cw_tba::Status { frozen: false })
+            .unwrap();
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some("owner")).unwrap();
+
+        assert!(cwfee_grant(deps.as_mut(), mock_env(), grant("owner")).is_ok());
+        assert!(cwfee_grant(deps.as_mut(), mock_env(), grant("stranger")).is_err());
+    }
+}
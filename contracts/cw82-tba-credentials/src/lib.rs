@@ -1,9 +1,12 @@
 mod action;
+mod approvals;
 pub mod contract;
 pub mod error;
 pub mod msg;
 mod execute;
+mod payments;
 mod query;
+mod recovery;
 mod state;
 mod utils;
 
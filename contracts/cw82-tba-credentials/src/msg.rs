@@ -2,6 +2,24 @@ pub type ContractResult = Result<cosmwasm_std::Response, crate::error::ContractE
 pub use cw_tba::{InstantiateAccountMsg as InstantiateMsg, QueryMsg};
 pub use cosmwasm_std::Binary as MigrateMsg;
 
+/// Common account messages and the Archway extension
+#[cfg(feature = "archway")]
+#[cosmwasm_schema::cw_serde]
+#[serde(untagged)]
+pub enum ExtendedExecuteMsg {
+    Account(cw_tba::ExecuteMsg),
+    Archway(cw_tba::ArchwayExecuteMsg),
+}
+
+/// Common account queries and the Archway extension
+#[cfg(feature = "archway")]
+#[cosmwasm_schema::cw_serde]
+#[serde(untagged)]
+pub enum ExtendedQueryMsg {
+    Account(Box<QueryMsg>),
+    Archway(cw_tba::ArchwayQueryMsg),
+}
+
 #[derive(serde::Deserialize, schemars::JsonSchema)]
 pub enum SudoMsg {
     #[cfg(feature = "archway")]
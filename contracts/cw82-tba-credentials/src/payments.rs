@@ -0,0 +1,19 @@
+use cosmwasm_std::{Env, MessageInfo, Storage};
+use cw_tba::{try_cranking_payments, try_pulling, PaymentMsg};
+
+use crate::{msg::ContractResult, utils::assert_status};
+
+pub fn try_handling_payments(
+    storage: &mut dyn Storage,
+    env: &Env,
+    info: &MessageInfo,
+    msg: PaymentMsg,
+) -> ContractResult {
+    assert_status(storage)?;
+    match msg {
+        PaymentMsg::Crank { ids } => Ok(try_cranking_payments(storage, env, ids)?),
+        PaymentMsg::Pull { amount, recipient } => {
+            Ok(try_pulling(storage, env, info.sender.to_string(), amount, recipient)?)
+        }
+    }
+}
@@ -1,22 +1,60 @@
-use cosmwasm_std::{ensure, to_json_string, Binary, CosmosMsg, Deps, Env, Order, StdError, StdResult};
+use cosmwasm_std::{ensure, to_json_string, Binary, CosmosMsg, Deps, Env, StdError, StdResult};
 use cw84::{CanExecuteResponse, ValidSignatureResponse, ValidSignaturesResponse};
-use cw_tba::{AssetsResponse, ActiontMsg, FullInfoResponse, TokenInfo};
+use cw_tba::{
+    assert_policy, native_actions, simulate_policy, with_token_metadata, ActiontMsg, AssetsResponse,
+    FullInfoResponse, SimulatePolicyResponse,
+};
 use saa_wasm::{
-    saa_types::{Credential}, verify_cred_query, verify_native
+    has_credential,
+    saa_types::{CosmosArbitrary, Credential, Ed25519, EthPersonalSign, Identifiable, Verifiable},
+    verify_cred_query, verify_native,
+};
+
+pub use cw_tba::state::{
+    account_tree, cw20_balances, executors, fee_grants, known_collections, known_tokens, plugins,
+    policy, pull_authorizations, queued_actions, scheduled_payments, skipped_known_tokens,
+    token_approvals, token_attributes, watched_collections,
 };
 
 use crate::{
-    state::{KNOWN_TOKENS, REGISTRY_ADDRESS, STATUS, TOKEN_INFO},
-    utils::{assert_ok_cosmos_msg, assert_status},
+    state::{
+        REGISTRY_ADDRESS, STATUS,
+        TOKEN_INFO,
+    },
+    approvals::assert_two_factor,
+    utils::{assert_status, builtin_policy},
 };
 
-const DEFAULT_BATCH_SIZE: u32 = 100;
+pub fn can_execute(
+    deps: Deps,
+    env: Env,
+    sender: String,
+    msg: CosmosMsg,
+) -> StdResult<CanExecuteResponse> {
+    Ok(CanExecuteResponse {
+        can_execute: assert_status(deps.storage).is_ok()
+            && verify_native(deps.storage, sender).is_ok()
+            && assert_policy(deps.storage, &env, &builtin_policy(), &[ActiontMsg::Execute { msgs: vec![msg] }], &[]).is_ok(),
+    })
+}
 
-pub fn can_execute(deps: Deps, sender: String, msg: CosmosMsg) -> StdResult<CanExecuteResponse> {
+pub fn can_execute_native(
+    deps: Deps,
+    env: Env,
+    sender: String,
+    msg: CosmosMsg,
+) -> StdResult<CanExecuteResponse> {
     Ok(CanExecuteResponse {
         can_execute: assert_status(deps.storage).is_ok()
             && verify_native(deps.storage, sender).is_ok()
-            && assert_ok_cosmos_msg(&msg).is_ok(),
+            && assert_policy(
+                deps.storage,
+                &env,
+                &builtin_policy(),
+                &native_actions(env.contract.address.as_str(), msg),
+                &[],
+            )
+            .is_ok(),
     })
 }
 
@@ -28,11 +66,12 @@ pub fn can_execute_signed(
 ) -> StdResult<CanExecuteResponse> {
     Ok(CanExecuteResponse {
         can_execute: assert_status(deps.storage).is_ok() && 
+        assert_two_factor(deps.storage, &msg, 1).is_ok() &&
+        assert_policy(deps.storage, &env, &builtin_policy(), &msg, &[]).is_ok() &&
         verify_cred_query(deps.storage, &env, cred, Some(vec![to_json_string(&msg)?])).is_ok(),
     })
 }
 
-#[allow(unused)]
 pub fn valid_signature(
     deps: Deps,
     _env: Env,
@@ -40,8 +79,12 @@ pub fn valid_signature(
     signature: Binary,
     payload: Option<Credential>,
 ) -> StdResult<ValidSignatureResponse> {
+    let cred = payload.ok_or_else(|| StdError::generic_err("Credential is required"))?;
+    if assert_status(deps.storage).is_err() {
+        return Ok(ValidSignatureResponse { is_valid: false });
+    }
     Ok(ValidSignatureResponse {
-        is_valid: payload.map(|c|c.verify(deps)).transpose().is_ok()
+        is_valid: verify_signature(deps, &cred, data, signature)?,
     })
 }
 
@@ -52,63 +95,82 @@ pub fn valid_signatures(
     signatures: Vec<Binary>,
     payload: Option<Credential>,
 ) -> StdResult<ValidSignaturesResponse> {
-    if assert_status(deps.storage).is_err() {
-        return Ok(ValidSignaturesResponse {
-            are_valid: vec![false, data.len() > 1],
-        });
-    };
     ensure!(
         data.len() == signatures.len(),
         StdError::generic_err("Data and signatures must be of equal length")
     );
+    let cred = payload.ok_or_else(|| StdError::generic_err("Credential is required"))?;
+    if assert_status(deps.storage).is_err() {
+        return Ok(ValidSignaturesResponse { are_valid: vec![false; data.len()] });
+    }
+
+    Ok(ValidSignaturesResponse {
+        are_valid: data
+            .into_iter()
+            .zip(signatures)
+            .map(|(data, signature)| verify_signature(deps, &cred, data, signature))
+            .collect::<StdResult<Vec<bool>>>()?,
+    })
+}
 
-    let cred = match payload {
-        Some(c) => c,
-        None => return Ok(ValidSignaturesResponse { are_valid: vec![false; data.len()] }),
+/// Verify a signature over the data using the public identity of a credential stored on the account.
+/// Credentials that can't sign arbitrary data are refused
+fn verify_signature(deps: Deps, cred: &Credential, data: Binary, signature: Binary) -> StdResult<bool> {
+    ensure!(
+        !data.is_empty() && !signature.is_empty(),
+        StdError::generic_err("Data and signature are required")
+    );
+    let signed: Credential = match cred {
+        Credential::CosmosArbitrary(c) => CosmosArbitrary {
+            message: data,
+            signature,
+            ..c.clone()
+        }
+        .into(),
+        Credential::EthPersonalSign(c) => EthPersonalSign {
+            message: data,
+            signature,
+            signer: c.signer.clone(),
+        }
+        .into(),
+        Credential::Ed25519(c) => Ed25519 {
+            message: data,
+            signature,
+            pubkey: c.pubkey.clone(),
+        }
+        .into(),
+        _ => return Err(StdError::generic_err(format!(
+            "Verifying signatures of {} credentials isn't supported",
+            cred.name()
+        ))),
     };
-
-    return Ok(ValidSignaturesResponse {
-        are_valid: vec![cred.verify(deps).is_ok(); data.len()],
-    });
-
+    if !has_credential(deps.storage, cred.id(), Some(cred.name())) {
+        return Ok(false);
+    }
+    Ok(signed.verify(deps).is_ok())
 }
 
-
 pub fn assets(
     deps: Deps,
     env: Env,
     skip: Option<u32>,
     limit: Option<u32>,
+    with_metadata: Option<bool>,
 ) -> StdResult<AssetsResponse> {
+    let registry = REGISTRY_ADDRESS.load(deps.storage)?;
     Ok(AssetsResponse {
+        cw20_balances: cw20_balances(deps, &env, skip, limit)?,
         balances: deps.querier.query_all_balances(env.contract.address)?,
-        tokens: known_tokens(deps, skip, limit)?,
+        tokens: with_token_metadata(&deps.querier, &registry, skipped_known_tokens(deps, skip, limit)?, with_metadata)?,
     })
 }
 
-pub fn known_tokens(
+pub fn policy_simulation(
     deps: Deps,
-    skip: Option<u32>,
-    limit: Option<u32>,
-) -> StdResult<Vec<TokenInfo>> {
-    let skip = skip.unwrap_or(0) as usize;
-    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
-
-    let tokens: StdResult<Vec<TokenInfo>> = KNOWN_TOKENS
-        .keys(deps.storage, None, None, Order::Ascending)
-        .enumerate()
-        .filter(|(i, _)| *i >= skip)
-        .take(limit)
-        .map(|(_, kt)| {
-            let kp = kt?;
-            Ok(TokenInfo {
-                collection: kp.0,
-                id: kp.1,
-            })
-        })
-        .collect();
-
-    tokens
+    env: Env,
+    actions: Vec<ActiontMsg>,
+) -> StdResult<SimulatePolicyResponse> {
+    simulate_policy(deps.storage, &env, &builtin_policy(), &actions, &[])
 }
 
 pub fn full_info(
@@ -116,8 +178,11 @@ pub fn full_info(
     env: Env,
     skip: Option<u32>,
     limit: Option<u32>,
+    with_metadata: Option<bool>,
 ) -> StdResult<FullInfoResponse> {
-    let tokens = known_tokens(deps, skip, limit)?;
+    let registry = REGISTRY_ADDRESS.load(deps.storage)?;
+    let tokens = with_token_metadata(&deps.querier, &registry, skipped_known_tokens(deps, skip, limit)?, with_metadata)?;
+    let cw20_balances = cw20_balances(deps, &env, skip, limit)?;
     let balances = deps.querier.query_all_balances(env.contract.address)?;
     let ownership = cw_ownable::get_ownership(deps.storage)?;
     let credentials = saa_wasm::get_stored_credentials(deps.storage)
@@ -125,6 +190,7 @@ pub fn full_info(
 
     Ok(FullInfoResponse {
         balances,
+        cw20_balances,
         tokens,
         ownership,
         credentials,
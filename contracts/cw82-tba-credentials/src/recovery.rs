@@ -0,0 +1,432 @@
+use cosmwasm_std::{ensure, Api, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage};
+use cw_ownable::get_ownership;
+use cw_tba::{GuardiansConfig, RecoveryMsg, RecoveryProposal, RecoveryResponse};
+use saa_wasm::{
+    account_number,
+    saa_types::{CheckOption, CredentialData, CredentialsWrapper, ReplayParams, VerifiedData},
+    stores::ACCOUNT_NUMBER,
+};
+
+use crate::{
+    error::ContractError,
+    msg::ContractResult,
+    state::{APPROVALS, GUARDIANS, RECOVERY, TWO_FACTOR},
+    utils::{assert_owner_derivable, assert_status},
+};
+
+const RECOVERY_MSG: &str = "Recover TBA account";
+
+/// Leaves the owner at least a day to veto a recovery
+pub const MIN_RECOVERY_DELAY: u64 = 86_400;
+
+pub fn try_recovering(deps: DepsMut, env: Env, info: MessageInfo, msg: RecoveryMsg) -> ContractResult {
+    assert_status(deps.storage)?;
+    let config = GUARDIANS
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NotSupported {})?;
+
+    match msg {
+        RecoveryMsg::Propose {
+            account_data,
+            reset_two_factor,
+        } => try_proposing_recovery(
+            deps,
+            env,
+            info,
+            config,
+            account_data,
+            reset_two_factor.unwrap_or_default(),
+        ),
+        RecoveryMsg::Approve {} => try_approving_recovery(deps.storage, env, info, config),
+        RecoveryMsg::Cancel {} => try_cancelling_recovery(deps.storage, info),
+        RecoveryMsg::Finalize {} => try_finalizing_recovery(deps.storage, env),
+    }
+}
+
+fn assert_guardian(config: &GuardiansConfig, sender: &str) -> Result<(), ContractError> {
+    ensure!(
+        config.guardians.iter().any(|g| g == sender),
+        ContractError::Unauthorized("Only guardians can do this".into())
+    );
+    Ok(())
+}
+
+fn with_threshold_check(
+    mut proposal: RecoveryProposal,
+    config: &GuardiansConfig,
+    env: &Env,
+) -> RecoveryProposal {
+    if proposal.ready_at.is_none() && proposal.approvals.len() >= config.threshold as usize {
+        proposal.ready_at = Some(env.block.time.plus_seconds(config.delay));
+    }
+    proposal
+}
+
+fn try_proposing_recovery(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    config: GuardiansConfig,
+    data: CredentialData,
+    reset_two_factor: bool,
+) -> ContractResult {
+    assert_guardian(&config, info.sender.as_str())?;
+    ensure!(
+        !RECOVERY.exists(deps.storage),
+        ContractError::Generic("Another recovery is pending and must be cancelled first".into())
+    );
+
+    let params = ReplayParams::new(
+        account_number(deps.storage),
+        CheckOption::Text(RECOVERY_MSG.to_string()),
+    );
+    let account_data = data.verify(deps.as_ref(), &env, &info, params)?;
+    // the guardian must not be able to sneak in as a native credential
+    ensure!(
+        !account_data.has_natives,
+        ContractError::Unauthorized("Native credentials can't be recovered".into())
+    );
+    assert_recoverable(deps.storage, &account_data, reset_two_factor)?;
+
+    let proposal = with_threshold_check(
+        RecoveryProposal {
+            account_data,
+            proposer: info.sender.to_string(),
+            reset_two_factor,
+            approvals: vec![info.sender.to_string()],
+            ready_at: None,
+        },
+        &config,
+        &env,
+    );
+    RECOVERY.save(deps.storage, &proposal)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_recovery")
+        .add_attribute("guardian", info.sender.as_str()))
+}
+
+fn try_approving_recovery(
+    storage: &mut dyn Storage,
+    env: Env,
+    info: MessageInfo,
+    config: GuardiansConfig,
+) -> ContractResult {
+    let sender = info.sender.to_string();
+    assert_guardian(&config, &sender)?;
+
+    let mut proposal = RECOVERY
+        .may_load(storage)?
+        .ok_or(ContractError::Generic("No pending recovery".into()))?;
+    ensure!(
+        !proposal.approvals.contains(&sender),
+        ContractError::Generic("Already approved".into())
+    );
+    proposal.approvals.push(sender.clone());
+
+    let proposal = with_threshold_check(proposal, &config, &env);
+    RECOVERY.save(storage, &proposal)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "approve_recovery")
+        .add_attribute("guardian", sender))
+}
+
+fn try_cancelling_recovery(storage: &mut dyn Storage, info: MessageInfo) -> ContractResult {
+    let proposal = RECOVERY
+        .may_load(storage)?
+        .ok_or(ContractError::Generic("No pending recovery".into()))?;
+    ensure!(
+        proposal.proposer == info.sender.as_str(),
+        ContractError::Unauthorized("Only the proposer can cancel the recovery".into())
+    );
+    RECOVERY.remove(storage);
+    Ok(Response::new()
+        .add_attribute("action", "cancel_recovery")
+        .add_attribute("guardian", info.sender.as_str()))
+}
+
+/// The new credentials must be usable by the owner and must not leave the account
+/// with a two-credential requirement it can't meet
+fn assert_recoverable(
+    storage: &dyn Storage,
+    data: &VerifiedData,
+    reset_two_factor: bool,
+) -> Result<(), ContractError> {
+    let owner = get_ownership(storage)?
+        .owner
+        .ok_or(ContractError::Unauthorized("The account has no owner".into()))?;
+    assert_owner_derivable(&data.credentials, owner.as_str())?;
+    ensure!(
+        reset_two_factor || data.credentials.len() >= 2 || !TWO_FACTOR.exists(storage),
+        ContractError::Generic(
+            "Two credentials are required unless the two-credential requirement is reset".into()
+        )
+    );
+    Ok(())
+}
+
+fn try_finalizing_recovery(storage: &mut dyn Storage, env: Env) -> ContractResult {
+    let proposal = RECOVERY
+        .may_load(storage)?
+        .ok_or(ContractError::Generic("No pending recovery".into()))?;
+
+    let ready_at = proposal
+        .ready_at
+        .ok_or(ContractError::Generic("Recovery hasn't been approved yet".into()))?;
+    ensure!(
+        env.block.time >= ready_at,
+        ContractError::Generic(format!("Recovery can't be finalized before {ready_at}"))
+    );
+    // the owner or the two factor settings may have changed since the proposal
+    assert_recoverable(storage, &proposal.account_data, proposal.reset_two_factor)?;
+
+    // keep the account number going to invalidate anything signed before
+    let nonce = account_number(storage);
+    saa_wasm::reset_credentials(storage, false)?;
+    saa_wasm::save_credentials(storage, &proposal.account_data)?;
+    ACCOUNT_NUMBER.save(storage, &(nonce + 1))?;
+    RECOVERY.remove(storage);
+    // the new credentials start without anything signed by the old ones
+    APPROVALS.clear(storage);
+    if proposal.reset_two_factor {
+        TWO_FACTOR.remove(storage);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "finalize_recovery")
+        .add_attribute("reset_two_factor", proposal.reset_two_factor.to_string()))
+}
+
+pub fn try_updating_guardians(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    guardians: Vec<String>,
+    threshold: u32,
+    delay: u64,
+) -> ContractResult {
+    RECOVERY.remove(storage);
+
+    if guardians.is_empty() {
+        GUARDIANS.remove(storage);
+        return Ok(Response::new().add_attribute("action", "remove_guardians"));
+    }
+
+    let mut guardians = guardians
+        .iter()
+        .map(|g| api.addr_validate(g).map(|a| a.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+    guardians.sort();
+    guardians.dedup();
+
+    ensure!(
+        threshold > 0 && threshold as usize <= guardians.len(),
+        ContractError::Generic("Threshold must be between 1 and the number of guardians".into())
+    );
+    ensure!(
+        delay >= MIN_RECOVERY_DELAY,
+        ContractError::Generic(format!("The delay must be at least {MIN_RECOVERY_DELAY} seconds"))
+    );
+
+    GUARDIANS.save(
+        storage,
+        &GuardiansConfig {
+            guardians,
+            threshold,
+            delay,
+        },
+    )?;
+    Ok(Response::new().add_attribute("action", "update_guardians"))
+}
+
+pub fn try_vetoing_recovery(storage: &mut dyn Storage) -> ContractResult {
+    ensure!(
+        RECOVERY.exists(storage),
+        ContractError::Generic("No pending recovery".into())
+    );
+    RECOVERY.remove(storage);
+    Ok(Response::new().add_attribute("action", "veto_recovery"))
+}
+
+pub fn recovery(deps: Deps) -> StdResult<RecoveryResponse> {
+    Ok(RecoveryResponse {
+        guardians: GUARDIANS.may_load(deps.storage)?,
+        proposal: RECOVERY.may_load(deps.storage)?,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage},
+        Addr, OwnedDeps,
+    };
+    use cw_tba::{Status, TwoFactorConfig};
+    use saa_wasm::saa_types::{CredentialAddress, CredentialInfo, CredentialName};
+
+    use crate::state::STATUS;
+
+    type Deps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+    fn setup(threshold: u32) -> Deps {
+        let mut deps = mock_dependencies();
+        STATUS.save(&mut deps.storage, &Status { frozen: false }).unwrap();
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some("owner")).unwrap();
+        try_updating_guardians(
+            &deps.api,
+            &mut deps.storage,
+            vec!["alice".into(), "bob".into(), "carol".into()],
+            threshold,
+            MIN_RECOVERY_DELAY,
+        )
+        .unwrap();
+        deps
+    }
+
+    /// The block time once the recovery delay has passed
+    fn ready_env() -> Env {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(MIN_RECOVERY_DELAY);
+        env
+    }
+
+    fn new_credentials(owner: &str) -> VerifiedData {
+        VerifiedData {
+            credentials: vec![("new".to_string(), CredentialInfo {
+                name: CredentialName::CosmosArbitrary,
+                hrp: Some("cosmwasm".into()),
+                extension: None,
+                address: Some(CredentialAddress::Bech32(Addr::unchecked(owner))),
+            })],
+            addresses: vec![],
+            primary_id: "new".to_string(),
+            override_primary: true,
+            has_natives: false,
+            has_extensions: false,
+            nonce: 0,
+        }
+    }
+
+    /// Proposals need signed credentials so they are stored the way the proposer would
+    fn propose(deps: &mut Deps, proposer: &str, owner: &str, reset_two_factor: bool) {
+        let proposal = RecoveryProposal {
+            account_data: new_credentials(owner),
+            proposer: proposer.to_string(),
+            reset_two_factor,
+            approvals: vec![proposer.to_string()],
+            ready_at: None,
+        };
+        let config = GUARDIANS.load(&deps.storage).unwrap();
+        let proposal = with_threshold_check(proposal, &config, &mock_env());
+        RECOVERY.save(&mut deps.storage, &proposal).unwrap();
+    }
+
+    fn recover(deps: &mut Deps, env: &Env, sender: &str, msg: RecoveryMsg) -> ContractResult {
+        try_recovering(deps.as_mut(), env.clone(), mock_info(sender, &[]), msg)
+    }
+
+    #[test]
+    fn pending_proposals_cant_be_overwritten() {
+        let mut deps = setup(2);
+        let env = mock_env();
+        propose(&mut deps, "alice", "owner", false);
+        recover(&mut deps, &env, "bob", RecoveryMsg::Approve {}).unwrap();
+
+        let propose_msg = RecoveryMsg::Propose {
+            account_data: CredentialData {
+                use_native: None,
+                primary_index: None,
+                pre_validate: None,
+                override_primary: None,
+                credentials: vec![],
+            },
+            reset_two_factor: None,
+        };
+        assert!(recover(&mut deps, &env, "carol", propose_msg).is_err());
+        assert_eq!(RECOVERY.load(&deps.storage).unwrap().approvals, vec!["alice", "bob"]);
+
+        // only the proposer can cancel
+        assert!(recover(&mut deps, &env, "bob", RecoveryMsg::Cancel {}).is_err());
+        recover(&mut deps, &env, "alice", RecoveryMsg::Cancel {}).unwrap();
+        assert!(!RECOVERY.exists(&deps.storage));
+    }
+
+    #[test]
+    fn recovery_waits_for_the_threshold_and_delay() {
+        let mut deps = setup(2);
+        let mut env = mock_env();
+        propose(&mut deps, "alice", "owner", false);
+
+        assert!(recover(&mut deps, &env, "mallory", RecoveryMsg::Approve {}).is_err());
+        assert!(recover(&mut deps, &env, "alice", RecoveryMsg::Approve {}).is_err());
+        assert!(recover(&mut deps, &env, "alice", RecoveryMsg::Finalize {}).is_err());
+
+        recover(&mut deps, &env, "bob", RecoveryMsg::Approve {}).unwrap();
+        assert!(recover(&mut deps, &env, "alice", RecoveryMsg::Finalize {}).is_err());
+
+        env.block.time = env.block.time.plus_seconds(MIN_RECOVERY_DELAY);
+        let number = account_number(&deps.storage);
+        recover(&mut deps, &env, "carol", RecoveryMsg::Finalize {}).unwrap();
+        assert!(saa_wasm::has_credential(&deps.storage, "new".into(), None));
+        assert_eq!(account_number(&deps.storage), number + 1);
+        assert!(!RECOVERY.exists(&deps.storage));
+    }
+
+    #[test]
+    fn new_credentials_must_belong_to_the_owner() {
+        let mut deps = setup(1);
+        let env = ready_env();
+        propose(&mut deps, "alice", "owner", false);
+        // the token changed hands since the proposal
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some("buyer")).unwrap();
+
+        let err = recover(&mut deps, &env, "alice", RecoveryMsg::Finalize {});
+        assert!(matches!(err, Err(ContractError::NoOwnerCred {})));
+    }
+
+    #[test]
+    fn two_factor_is_kept_unless_reset() {
+        let mut deps = setup(1);
+        let env = ready_env();
+        let config = TwoFactorConfig {
+            actions: vec![],
+            thresholds: vec![],
+        };
+        TWO_FACTOR.save(&mut deps.storage, &config).unwrap();
+
+        // a single credential can't satisfy the two-credential requirement
+        propose(&mut deps, "alice", "owner", false);
+        recover(&mut deps, &env, "bob", RecoveryMsg::Approve {}).unwrap();
+        assert!(recover(&mut deps, &env, "alice", RecoveryMsg::Finalize {}).is_err());
+
+        recover(&mut deps, &env, "alice", RecoveryMsg::Cancel {}).unwrap();
+        propose(&mut deps, "alice", "owner", true);
+        recover(&mut deps, &env, "bob", RecoveryMsg::Approve {}).unwrap();
+        recover(&mut deps, &env, "alice", RecoveryMsg::Finalize {}).unwrap();
+        assert!(!TWO_FACTOR.exists(&deps.storage));
+    }
+
+    #[test]
+    fn guardian_updates_are_validated() {
+        let mut deps = setup(1);
+        propose(&mut deps, "alice", "owner", false);
+
+        let update = |deps: &mut Deps, threshold, delay| {
+            try_updating_guardians(&deps.api, &mut deps.storage, vec!["alice".into()], threshold, delay)
+        };
+        assert!(update(&mut deps, 2, MIN_RECOVERY_DELAY).is_err());
+        // the owner must always get a chance to veto
+        assert!(update(&mut deps, 1, 0).is_err());
+        assert!(update(&mut deps, 1, MIN_RECOVERY_DELAY - 1).is_err());
+        // updating the guardians drops the pending recovery
+        let guardians = vec!["alice".into(), "alice".into()];
+        try_updating_guardians(&deps.api, &mut deps.storage, guardians, 1, MIN_RECOVERY_DELAY).unwrap();
+        assert_eq!(GUARDIANS.load(&deps.storage).unwrap().guardians, vec!["alice"]);
+        assert!(!RECOVERY.exists(&deps.storage));
+
+        try_updating_guardians(&deps.api, &mut deps.storage, vec![], 0, 0).unwrap();
+        assert!(!GUARDIANS.exists(&deps.storage));
+    }
+}
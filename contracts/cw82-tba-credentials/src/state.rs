@@ -1,13 +1,26 @@
 use crate::error::ContractError;
 use cw_storage_plus::{Item, Map};
-use cw_tba::{Status, TokenInfo};
+use cw_tba::{GuardiansConfig, PendingApproval, RecoveryProposal, TwoFactorConfig};
 use saa_wasm::saa_types::VerifiedData;
 
-pub static REGISTRY_ADDRESS: Item<String> = Item::new("r");
-pub static TOKEN_INFO: Item<TokenInfo> = Item::new("t");
-pub static STATUS: Item<Status> = Item::new("s");
-pub static MINT_CACHE: Item<String> = Item::new("m");
-pub static KNOWN_TOKENS: Map<(&str, &str), bool> = Map::new("k");
+pub use cw_tba::state::{
+    rebuild_known_collections, remove_known_token, save_known_token, EXECUTORS, FEE_GRANTS,
+    IBC_CONFIG, KNOWN_COLLECTIONS, KNOWN_CW20S, KNOWN_TOKENS, MINT_CACHE, PAYMENTS, PAYMENT_COUNT,
+    PLUGINS, POLICY, PULLS, QUEUE, QUEUE_COUNT, RECEIVE_CONFIG, REGISTRY_ADDRESS, REMOTE_HOLDER,
+    STATUS, TIMELOCK, TOKEN_APPROVALS, TOKEN_ATTRIBUTES, TOKEN_INFO, WATCHED_COLLECTIONS,
+};
+
+pub static GUARDIANS: Item<GuardiansConfig> = Item::new("d");
+pub static RECOVERY: Item<RecoveryProposal> = Item::new("v");
+pub static TWO_FACTOR: Item<TwoFactorConfig> = Item::new("a");
+pub static APPROVALS: Map<u64, PendingApproval> = Map::new("b");
+pub static APPROVAL_COUNT: Item<u64> = Item::new("h");
+pub static MIGRATION_APPROVAL: Item<u64> = Item::new("j");
+
+#[cfg(feature = "archway")]
+pub static CW_FEES_POLICY: Item<cw_tba::CwFeesPolicy> = Item::new("g");
+#[cfg(feature = "archway")]
+pub static CW_FEES_USAGE: Item<cw_tba::CwFeesUsage> = Item::new("u");
 
 pub fn save_token_credentials(
     api: &dyn cosmwasm_std::Api,
@@ -20,3 +33,4 @@ pub fn save_token_credentials(
     saa_wasm::save_credentials(storage, &data)?;
     Ok(())
 }
+
@@ -1,12 +1,28 @@
-use cosmwasm_std::{ensure, ensure_eq, CosmosMsg, StdError, StdResult, Storage};
+use cosmwasm_std::{
+    ensure, ensure_eq, StdError, StdResult, Storage,
+};
+use cw_tba::{
+    MsgKind, Policy, PolicyRule,
+};
 use saa_wasm::saa_types::CredentialRecord;
 
 use crate::{
     error::ContractError,
-    state::{REGISTRY_ADDRESS, STATUS},
+    state::{REGISTRY_ADDRESS, REMOTE_HOLDER, STATUS},
 };
 
 pub fn assert_status(store: &dyn Storage) -> StdResult<()> {
+    assert_not_frozen(store)?;
+    // the owner no longer holds the token while it is escrowed by a bridge
+    ensure!(
+        !REMOTE_HOLDER.exists(store),
+        StdError::generic_err(ContractError::Bridged {}.to_string())
+    );
+    Ok(())
+}
+
+/// The only check of the status left for the holder of the voucher acting through the bridge
+pub fn assert_not_frozen(store: &dyn Storage) -> StdResult<()> {
     let status = STATUS.load(store)?;
     ensure!(
         !status.frozen,
@@ -36,16 +52,40 @@ pub fn assert_owner_derivable(
     Ok(())
 }
 
-pub fn assert_ok_cosmos_msg(msg: &CosmosMsg) -> StdResult<()> {
-    match msg {
-        CosmosMsg::Wasm(_) => Err(StdError::generic_err("Not Supported")),
-        CosmosMsg::Stargate { type_url, .. } => {
-            if type_url.to_lowercase().contains("authz") {
-                Err(StdError::generic_err("Not Supported"))
-            } else {
-                Ok(())
-            }
-        }
-        _ => Ok(()),
+/// Rules the raw messages of the owner are always checked against
+pub fn builtin_policy() -> Policy {
+    Policy {
+        rules: vec![
+            PolicyRule::BlockedMsgKinds {
+                kinds: vec![MsgKind::Wasm],
+            },
+            // stargate transfers would get around the channels allowed for IBC
+            PolicyRule::BlockedTypeUrls {
+                type_urls: vec!["authz".into(), "/ibc.applications.transfer.".into()],
+            },
+        ],
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{testing::mock_env, Binary, CosmosMsg};
+    use cw_tba::ActiontMsg;
+
+    #[test]
+    fn stargate_ibc_transfers_are_blocked() {
+        let now = mock_env().block.time;
+        let raw = |type_url: &str| ActiontMsg::Execute {
+            msgs: vec![CosmosMsg::Stargate {
+                type_url: type_url.into(),
+                value: Binary::default(),
+            }],
+        };
+        let policy = builtin_policy();
+        assert!(policy.check(&[raw("/ibc.applications.transfer.v1.MsgTransfer")], &[], &now).is_err());
+        assert!(policy.check(&[raw("/cosmos.authz.v1beta1.MsgExec")], &[], &now).is_err());
+        assert!(policy.check(&[raw("/cosmos.gov.v1.MsgDeposit")], &[], &now).is_ok());
     }
 }
@@ -4,9 +4,12 @@ use cosmwasm_std::{to_json_binary, DepsMut, Env, MessageInfo, Response, StdResul
 
 use crate::{
     error::ContractError,
-    execute::{create_account, execute_admin, migrate_account, update_account_data, update_account_owner},
+    execute::{
+        collection_backend, create_account, execute_admin, migrate_account, update_account_data,
+        update_account_owner,
+    },
     msg::{AccountsQueryMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, SudoMsg},
-    query::{account_info, accounts, collection_accounts, collections},
+    query::{account_info, accounts, collection_accounts, collections, ics721_bridges, voucher_origin},
     state::{ADMIN, COL_TOKEN_COUNTS, LAST_ATTEMPTING, REGISTRY_PARAMS, TOKEN_ADDRESSES},
 };
 
@@ -153,6 +156,12 @@ pub fn query(deps: cosmwasm_std::Deps, _: Env, msg: QueryMsg) -> StdResult<cosmw
 
         QueryMsg::RegistryParams {} => to_json_binary(&REGISTRY_PARAMS.load(deps.storage)?),
 
+        QueryMsg::CollectionBackend { collection } => to_json_binary(&collection_backend(deps.storage, &collection)?),
+
+        QueryMsg::Ics721Bridges {} => to_json_binary(&ics721_bridges(deps)?),
+
+        QueryMsg::VoucherOrigin { collection } => to_json_binary(&voucher_origin(deps, collection)?),
+
         QueryMsg::AccountInfo(token) => to_json_binary(&account_info(deps, token)?),
 
         QueryMsg::Accounts {
@@ -42,6 +42,9 @@ pub enum ContractError {
     #[error("Account for the given token already exists. Use `reset_account` to overwrite it and `migrate_account` to update it to a newer version")]
     AccountExists {},
 
+    #[error("The token is held by an account it would control")]
+    CycleDetected {},
+
     #[error("Generic error: {0}")]
     Generic(String),
 
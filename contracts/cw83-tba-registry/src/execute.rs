@@ -1,14 +1,15 @@
 use cosmwasm_std::{
-    ensure, ensure_eq, to_json_binary, Addr, CosmosMsg, DepsMut, Env, MessageInfo, ReplyOn, Response, StdError, SubMsg, WasmMsg
+    ensure, ensure_eq, to_json_binary, Addr, CosmosMsg, DepsMut, Env, MessageInfo, ReplyOn, Response, StdError, StdResult, Storage, SubMsg, WasmMsg
 };
 
 use crate::{
-    error::ContractError, funds::checked_funds, msg::SudoMsg, state::{LAST_ATTEMPTING, REGISTRY_PARAMS, TOKEN_ADDRESSES}
+    error::ContractError, funds::checked_funds, msg::SudoMsg, state::{COLLECTION_BACKENDS, ICS721_BRIDGES, LAST_ATTEMPTING, REGISTRY_PARAMS, TOKEN_ADDRESSES}
 };
 use cw83::CREATE_ACCOUNT_REPLY_ID;
 use cw84::{Binary, ValidSignatureResponse};
 use cw_tba::{
-    verify_nft_ownership, ActiontMsg, ExecuteMsg, InstantiateAccountMsg, QueryMsg, RegistryParams, TokenInfo
+    creates_cycle, query_account_token, verify_nft_ownership, ActiontMsg, ExecuteMsg, InstantiateAccountMsg,
+    CollectionBackend, NftBackend, QueryMsg, RegistryParams, TokenInfo, DEFAULT_CYCLE_DEPTH
 };
 use saa_wasm::{
     saa_types::{
@@ -21,6 +22,32 @@ const CREATE_MSG: &str = "Create TBA account";
 const UPDATE_MSG: &str = "Update TBA account ownership";
 
 
+/// Backend recorded for the collection or the default one
+pub fn collection_backend(store: &dyn Storage, collection: &str) -> StdResult<NftBackend> {
+    Ok(COLLECTION_BACKENDS.may_load(store, collection)?.unwrap_or_default())
+}
+
+
+/// Refuse tokens held by an account that is bound to them, directly or through other accounts
+fn assert_no_cycle(
+    deps: &DepsMut,
+    env: &Env,
+    owner: &str,
+    token_info: &TokenInfo,
+    depth: Option<u32>,
+) -> Result<(), ContractError> {
+    let registry = env.contract.address.as_str();
+    if let Some(bound) = query_account_token(&deps.querier, registry, owner) {
+        let depth = depth.unwrap_or(DEFAULT_CYCLE_DEPTH);
+        ensure!(
+            !creates_cycle(&deps.querier, registry, bound, std::slice::from_ref(token_info), depth)?,
+            ContractError::CycleDetected {}
+        );
+    }
+    Ok(())
+}
+
+
 fn construct_label(info: &TokenInfo, serial: Option<u64>) -> String {
     let base = format!("{}-{}-account", info.collection, info.id);
     match serial {
@@ -61,7 +88,13 @@ pub fn create_account(
         owner == info.sender.to_string() || is_manager,
         ContractError::Unauthorized {}
     );
-    verify_nft_ownership(&deps.querier, owner.as_str(), token_info.clone())?;
+    verify_nft_ownership(
+        &deps.querier,
+        &collection_backend(deps.storage, &token_info.collection)?,
+        owner.as_str(),
+        token_info.clone(),
+    )?;
+    assert_no_cycle(&deps, &env, owner.as_str(), &token_info, params.max_cycle_depth)?;
 
     LAST_ATTEMPTING.save(deps.storage, &token_info)?;
 
@@ -129,10 +162,8 @@ pub fn update_account_owner(
     new_account_data: Option<CredentialData>,
     update_for: Option<String>,
 ) -> Result<Response, ContractError> {
-    let is_manager = REGISTRY_PARAMS
-        .load(deps.storage)?
-        .managers
-        .contains(&info.sender.to_string());
+    let params = REGISTRY_PARAMS.load(deps.storage)?;
+    let is_manager = params.managers.contains(&info.sender.to_string());
 
     let owner = update_for.unwrap_or(info.sender.to_string());
     // only admin can update ownership but only if the new address is the token owner
@@ -141,7 +172,13 @@ pub fn update_account_owner(
         return Err(ContractError::Unauthorized {});
     }
 
-    verify_nft_ownership(&deps.querier, owner.as_str(), token_info.clone())?;
+    verify_nft_ownership(
+        &deps.querier,
+        &collection_backend(deps.storage, &token_info.collection)?,
+        owner.as_str(),
+        token_info.clone(),
+    )?;
+    assert_no_cycle(&deps, &env, owner.as_str(), &token_info, params.max_cycle_depth)?;
 
     let contract_addr = TOKEN_ADDRESSES.load(deps.storage, token_info.key())?;
 
@@ -207,7 +244,12 @@ pub fn update_account_data(
             ensure!(res.is_valid, ContractError::Unauthorized {});
         }
         None => {
-            verify_nft_ownership(&deps.querier, info.sender.as_str(), token_info.clone())?;
+            verify_nft_ownership(
+                &deps.querier,
+                &collection_backend(deps.storage, &token_info.collection)?,
+                info.sender.as_str(),
+                token_info.clone(),
+            )?;
         }
     }
 
@@ -256,7 +298,12 @@ pub fn migrate_account(
     {
         return Err(ContractError::InvalidCodeId {});
     }
-    verify_nft_ownership(&deps.querier, sender.as_str(), token_info.clone())?;
+    verify_nft_ownership(
+        &deps.querier,
+        &collection_backend(deps.storage, &token_info.collection)?,
+        sender.as_str(),
+        token_info.clone(),
+    )?;
     let contract_addr = TOKEN_ADDRESSES.load(deps.storage, token_info.key())?;
     let msg = CosmosMsg::Wasm(WasmMsg::Migrate {
         contract_addr,
@@ -294,6 +341,22 @@ pub fn execute_admin(
         SudoMsg::UpdateParams(params) => {
             REGISTRY_PARAMS.save(deps.storage, &params)?;
         },
+        SudoMsg::UpdateCollectionBackends { backends } => {
+            for CollectionBackend { collection, backend } in backends {
+                if backend == NftBackend::default() {
+                    COLLECTION_BACKENDS.remove(deps.storage, collection.as_str());
+                } else {
+                    COLLECTION_BACKENDS.save(deps.storage, collection.as_str(), &backend)?;
+                }
+            }
+        },
+        SudoMsg::UpdateIcs721Bridges { bridges } => {
+            ICS721_BRIDGES.clear(deps.storage);
+            for bridge in bridges {
+                let bridge = deps.api.addr_validate(&bridge)?;
+                ICS721_BRIDGES.save(deps.storage, bridge.as_str(), &true)?;
+            }
+        },
     }
     Ok(Response::new().add_attributes(vec![("action", "admin_update")]))
 }
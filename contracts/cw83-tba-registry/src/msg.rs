@@ -2,7 +2,10 @@ use cosmwasm_schema::{cw_serde, QueryResponses};
 use cw83::{registry_execute, registry_query, AccountResponse, AccountsResponse};
 
 use cw84::Binary;
-use cw_tba::{CreateAccountMsg, RegistryParams, TokenAccountPayload, TokenInfo};
+use cw_tba::{
+    CollectionBackend, CreateAccountMsg, NftBackend, RegistryParams, TokenAccountPayload, TokenInfo,
+    VoucherOrigin,
+};
 use saa_wasm::{
     saa_types::{Credential, CredentialData},
     UpdateOperation,
@@ -39,6 +42,18 @@ pub enum QueryMsg {
 
     #[returns(cw_controllers::AdminResponse)]
     Admin {},
+
+    /// Backend used to query and move the tokens of a collection
+    #[returns(NftBackend)]
+    CollectionBackend { collection: String },
+
+    /// ICS-721 contracts recognised by the registry and the accounts
+    #[returns(Vec<String>)]
+    Ics721Bridges {},
+
+    /// Remote class a collection holds the vouchers of, if one of the bridges minted it
+    #[returns(Option<VoucherOrigin>)]
+    VoucherOrigin { collection: String },
 }
 
 
@@ -52,6 +67,10 @@ pub enum SudoMsg {
     UpdateAllowedCodeIds { code_ids: Vec<u64> },
     /// manager contracts that can update an owner for an account if the latter is the new holder of the bound NFT
     UpdateManagers { managers: Vec<String> },
+    /// recording the backends of the collections that don't use the default one
+    UpdateCollectionBackends { backends: Vec<CollectionBackend> },
+    /// replacing the list of ICS-721 contracts whose escrowed tokens keep their accounts unfrozen
+    UpdateIcs721Bridges { bridges: Vec<String> },
 }
 
 #[registry_execute(TokenAccountPayload)]
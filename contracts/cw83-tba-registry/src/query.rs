@@ -1,9 +1,9 @@
 use cosmwasm_std::{Deps, Order, StdResult};
-use cw_tba::TokenInfo;
+use cw_tba::{query_voucher_origin, TokenInfo, VoucherOrigin};
 
 use crate::{
     msg::{Account, AccountOpt, Accounts},
-    state::{COL_TOKEN_COUNTS, TOKEN_ADDRESSES},
+    state::{COL_TOKEN_COUNTS, ICS721_BRIDGES, TOKEN_ADDRESSES},
 };
 
 const DEFAULT_BATCH_SIZE: u32 = 100;
@@ -111,3 +111,15 @@ pub fn collection_accounts(
         accounts, 
     })
 }
+
+pub fn ics721_bridges(deps: Deps) -> StdResult<Vec<String>> {
+    ICS721_BRIDGES
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect()
+}
+
+pub fn voucher_origin(deps: Deps, collection: String) -> StdResult<Option<VoucherOrigin>> {
+    Ok(ics721_bridges(deps)?
+        .iter()
+        .find_map(|bridge| query_voucher_origin(&deps.querier, bridge, &collection)))
+}
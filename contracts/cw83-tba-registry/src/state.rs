@@ -1,6 +1,6 @@
 use cw_controllers::Admin;
 use cw_storage_plus::{Item, Map};
-use cw_tba::{RegistryParams, TokenInfo};
+use cw_tba::{NftBackend, RegistryParams, TokenInfo};
 
 /// A Mapping of the collections addresses to the number of a tokens with an account
 pub static COL_TOKEN_COUNTS: Map<&str, u32> = Map::new("c");
@@ -8,6 +8,10 @@ pub static COL_TOKEN_COUNTS: Map<&str, u32> = Map::new("c");
 pub static TOKEN_ADDRESSES: Map<(&str, &str), String> = Map::new("t");
 /// Cache storage about the token to load from `reply` endpoint when waiting for newly created account address
 pub static LAST_ATTEMPTING: Item<TokenInfo> = Item::new("l");
+/// A Mapping of the collections to their backends. Collections without an entry use the default one
+pub static COLLECTION_BACKENDS: Map<&str, NftBackend> = Map::new("b");
+/// ICS-721 contracts that escrow the bridged tokens and mint the vouchers of the remote ones
+pub static ICS721_BRIDGES: Map<&str, bool> = Map::new("i");
 /// Registry params
 pub static REGISTRY_PARAMS: Item<RegistryParams> = Item::new("p");
 /// Admin for updates without governance
@@ -1,14 +1,24 @@
-use crate::common::TokenInfo;
-use crate::Cw721ReceiveMsg;
+use crate::common::{Cw20Coin, TokenInfo};
+use crate::{Cw20ReceiveMsg, Cw721ReceiveMsg, ExecutorMsg, ExecutorPermission, PaymentMsg, PluginKind, PolicyRule};
+use crate::{ApprovalMsg, KnownToken, SensitiveAction, SignedCredentials, TokenApprovalsResponse};
+use crate::{Ics721AckCallbackMsg, Ics721ReceiveCallbackMsg, RemoteHolder};
 use crate::UpdateAccountOp;
 use anybuf::Anybuf;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Binary, Coin, CosmosMsg, StdResult, Timestamp};
+use crate::{
+    state::{FEE_GRANTS, IBC_CONFIG},
+    AccountError, AccountResult,
+};
+use cosmwasm_std::{
+    ensure, from_json, to_json_vec, Binary, Coin, ContractResult, CosmosMsg, DecCoin, Delegation,
+    DelegatorReward, Empty, Env, Event, IbcMsg, QuerierWrapper, QueryRequest, Response, StdResult, Storage,
+    SystemResult, Timestamp, Uint128, VoteOption, WasmMsg,
+};
 use cw84::{signed_execute, signed_query};
 use cw_ownable::cw_ownable_query;
 use saa_schema::QueryResponses;
 use saa_wasm::saa_types::Credential;
-use saa_wasm::saa_types::VerifiedData;
+use saa_wasm::saa_types::{CredentialData, CredentialId, VerifiedData};
 
 
 #[cw_serde]
@@ -38,6 +48,181 @@ pub struct BasicAllowance {
 }
 
 
+#[cw_serde]
+pub struct PeriodicAllowance {
+    /// Length of a period in seconds
+    pub period: u64,
+    /// Maximum amount of tokens the grantee can spend within a period
+    pub period_spend_limit: Vec<Coin>,
+}
+
+
+/// Message type allowed for fee grants that don't specify their own list
+pub const DEFAULT_FEE_GRANT_MSG: &str = "/cosmwasm.wasm.v1.MsgExecuteContract";
+
+#[cw_serde]
+pub struct FeeGrantInfo {
+    /// Address that can use the account to pay the fees
+    pub grantee: String,
+    /// Total spend limit and expiration of the grant
+    pub allowance: Option<BasicAllowance>,
+    /// Optional limit that resets every period
+    pub periodic: Option<PeriodicAllowance>,
+    /// Message type urls the grantee can pay the fees for
+    pub allowed_msgs: Vec<String>,
+    /// Time of the grant creation
+    pub granted_at: Timestamp,
+}
+
+/// Rules for covering the fees of other transactions through Archway cwfees.
+/// Only the fees of the owner are covered by default
+#[cfg(feature = "archway")]
+#[cw_serde]
+pub struct CwFeesPolicy {
+    /// Cover the fees of the account owner
+    pub owner: bool,
+    /// Cover the fees of the addresses of the stored credentials
+    pub credentials: bool,
+    /// Other addresses to cover the fees for, e.g. session keys
+    pub addresses: Vec<String>,
+    /// Message type urls to cover the fees for. Any if empty
+    pub allowed_msgs: Vec<String>,
+    /// Maximum fees to cover within a period per denom. Unlimited if not set
+    pub budget: Option<Vec<Coin>>,
+    /// Length of a budget period in seconds
+    pub period: u64,
+}
+
+#[cfg(feature = "archway")]
+impl Default for CwFeesPolicy {
+    fn default() -> Self {
+        Self {
+            owner: true,
+            credentials: false,
+            addresses: vec![],
+            allowed_msgs: vec![],
+            budget: None,
+            period: 86400,
+        }
+    }
+}
+
+#[cfg(feature = "archway")]
+#[cw_serde]
+pub struct CwFeesUsage {
+    /// Start of the current budget period
+    pub period_start: Timestamp,
+    /// Fees covered within the current period
+    pub spent: Vec<Coin>,
+}
+
+#[cfg(feature = "archway")]
+#[cw_serde]
+pub struct CwFeesResponse {
+    pub policy: CwFeesPolicy,
+    pub usage: CwFeesUsage,
+}
+
+/// Archway specific messages accepted next to the common [ExecuteMsg]
+#[cfg(feature = "archway")]
+#[cw_serde]
+pub enum ArchwayExecuteMsg {
+    /// Owner only method to replace the policy for covering fees through Archway cwfees
+    UpdateCwFeesPolicy {
+        policy: CwFeesPolicy,
+    },
+}
+
+/// Archway specific queries accepted next to the common [QueryMsg]
+#[cfg(feature = "archway")]
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum ArchwayQueryMsg {
+    /// Policy for covering fees through Archway cwfees and its usage within the current period
+    #[returns(CwFeesResponse)]
+    CwFees {},
+}
+
+
+#[cw_serde]
+pub struct GuardiansConfig {
+    /// Addresses allowed to propose and approve a recovery of the account
+    pub guardians: Vec<String>,
+    /// Number of approvals required to start the recovery
+    pub threshold: u32,
+    /// Seconds between reaching the threshold and the recovery taking effect
+    pub delay: u64,
+}
+
+#[cw_serde]
+pub struct RecoveryProposal {
+    /// Verified credentials that will replace the current ones
+    pub account_data: VerifiedData,
+    /// Guardian that proposed the recovery and the only one who can cancel it
+    pub proposer: String,
+    /// Whether the two-credential requirement is removed together with the old credentials
+    pub reset_two_factor: bool,
+    /// Guardians that have approved the proposal
+    pub approvals: Vec<String>,
+    /// Time after which the recovery can be finalized. Set once the threshold is reached
+    pub ready_at: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct RecoveryResponse {
+    pub guardians: Option<GuardiansConfig>,
+    pub proposal: Option<RecoveryProposal>,
+}
+
+#[cw_serde]
+pub enum RecoveryMsg {
+    /// Guardian only method to propose new credentials for the account.
+    /// Refused while another proposal is pending
+    Propose {
+        /// New credentials signed over the account number of the account.
+        /// One of them must be derivable to the owner
+        account_data: CredentialData,
+        /// Remove the two-credential requirement on finalizing. Required if it's
+        /// set up and fewer than two credentials are proposed
+        reset_two_factor: Option<bool>,
+    },
+
+    /// Guardian only method to approve the pending proposal
+    Approve {},
+
+    /// Remove the pending proposal. Only callable by the guardian who proposed it
+    Cancel {},
+
+    /// Replace the credentials with the proposed ones once the delay has passed.
+    /// Callable by anybody
+    Finalize {},
+}
+
+
+/// Timeout in seconds used for IBC transfers that don't specify their own
+pub const DEFAULT_IBC_TIMEOUT: u64 = 600;
+
+#[cw_serde]
+pub struct IbcConfig {
+    /// Channels the account is allowed to send the tokens through
+    pub channels: Vec<String>,
+    /// Timeout in seconds used for transfers that don't specify their own
+    pub default_timeout: u64,
+}
+
+impl Default for IbcConfig {
+    fn default() -> Self {
+        Self {
+            channels: vec![],
+            default_timeout: DEFAULT_IBC_TIMEOUT,
+        }
+    }
+}
+
+
+pub use cosmwasm_std::WeightedVoteOption;
+
+
 //#[saa_derivable]
 #[cw_serde]
 pub enum ActiontMsg {
@@ -55,7 +240,6 @@ pub enum ActiontMsg {
     },
 
     /// Send NFT to a contract
-    #[cfg(not(feature = "omniflix"))]
     SendToken {
         /// Contract address of the collection
         collection: String,
@@ -77,6 +261,99 @@ pub enum ActiontMsg {
         recipient: String,
     },
 
+    /// Transfer several NFTs of a collection at once
+    TransferTokens {
+        /// Contract address of the collection
+        collection: String,
+        /// Ids of the tokens. Capped at `MAX_BULK_TRANSFER`
+        token_ids: Vec<String>,
+        /// Recipient address
+        recipient: String,
+    },
+
+    /// Empty the account. Transfers up to `limit` known tokens per call, so large
+    /// inventories are withdrawn by repeating the action
+    WithdrawAll {
+        /// Recipient address
+        recipient: String,
+        /// Whether to send the whole bank balance too
+        include_native: bool,
+        /// Only withdraw the tokens of these collections. All known tokens if omitted
+        collections: Option<Vec<String>>,
+        /// Maximum number of tokens to transfer. Capped at `MAX_BULK_TRANSFER`
+        limit: Option<u32>,
+    },
+
+    /// Let an address transfer a token of the account, e.g. to list it on a marketplace
+    Approve {
+        /// Contract address of the collection
+        collection: String,
+        /// Token id
+        token_id: String,
+        /// Address allowed to transfer the token
+        spender: String,
+        /// Expiration of the approval. Never expires if omitted
+        expires: Option<smart_account_auth::Expiration>,
+    },
+
+    /// Revoke an approval given for a token of the account
+    Revoke {
+        /// Contract address of the collection
+        collection: String,
+        /// Token id
+        token_id: String,
+        /// Address whose approval is revoked
+        spender: String,
+    },
+
+    /// Let an address transfer any token of a collection held by the account
+    ApproveAll {
+        /// Contract address of the collection
+        collection: String,
+        /// Address allowed to transfer the tokens
+        operator: String,
+        /// Expiration of the approval. Never expires if omitted
+        expires: Option<smart_account_auth::Expiration>,
+    },
+
+    /// Revoke an operator of a collection
+    RevokeAll {
+        /// Contract address of the collection
+        collection: String,
+        /// Address of the operator
+        operator: String,
+    },
+
+    /// Destroy a token held by the account
+    Burn {
+        /// Contract address of the collection
+        collection: String,
+        /// Token id
+        token_id: String,
+    },
+
+    /// Simple CW20 transfer
+    TransferCw20 {
+        /// Contract address of the cw20 token
+        contract: String,
+        /// Amount of the tokens to transfer
+        amount: Uint128,
+        /// Recipient address
+        recipient: String,
+    },
+
+    /// Send CW20 tokens to a contract
+    SendCw20 {
+        /// Contract address of the cw20 token
+        contract: String,
+        /// Amount of the tokens to send
+        amount: Uint128,
+        /// Recipient contract address
+        recipient: String,
+        /// Send message to pass a recipient contract
+        msg: Binary,
+    },
+
     /// Owner only method to make the account forget about certain tokens
     ForgetTokens {
         /// Contract address of the collection
@@ -95,9 +372,245 @@ pub enum ActiontMsg {
         limit: Option<u32>,
     },
 
+    /// Let another address use the account to pay the fees. Replaces an existing grant to the same grantee
     FeeGrant {
+        /// Address of the grantee
         grantee: String,
+        /// Total spend limit and expiration of the grant
         allowance: Option<BasicAllowance>,
+        /// Optional spend limit that resets every period
+        periodic: Option<PeriodicAllowance>,
+        /// Message type urls the grantee can pay the fees for. Defaults to `MsgExecuteContract`
+        allowed_msgs: Option<Vec<String>>,
+    },
+
+    /// Revoke a fee grant previously given to an address
+    RevokeFeeGrant {
+        /// Address of the grantee
+        grantee: String,
+    },
+
+    /// Delegate native tokens to a validator
+    Delegate {
+        /// Validator address
+        validator: String,
+        /// Amount of the tokens to delegate
+        amount: Coin,
+    },
+
+    /// Undelegate native tokens from a validator
+    Undelegate {
+        /// Validator address
+        validator: String,
+        /// Amount of the tokens to undelegate
+        amount: Coin,
+    },
+
+    /// Move a delegation from one validator to another
+    Redelegate {
+        /// Address of the validator the tokens are delegated to
+        src_validator: String,
+        /// Address of the validator to delegate the tokens to
+        dst_validator: String,
+        /// Amount of the tokens to redelegate
+        amount: Coin,
+    },
+
+    /// Withdraw staking rewards
+    ClaimRewards {
+        /// Validators to claim the rewards from. If empty, rewards from all delegations are claimed
+        validators: Vec<String>,
+    },
+
+    /// Set an address that will be receiving the staking rewards
+    SetWithdrawAddress {
+        /// Address to receive the rewards
+        address: String,
+    },
+
+    /// Vote on a governance proposal
+    Vote {
+        /// Id of the proposal
+        proposal_id: u64,
+        /// The vote option
+        option: VoteOption,
+    },
+
+    /// Split the voting power of the account between multiple options
+    VoteWeighted {
+        /// Id of the proposal
+        proposal_id: u64,
+        /// Options with their weights that must add up to one
+        options: Vec<WeightedVoteOption>,
+    },
+
+    /// Transfer native tokens to another chain through one of the allowed channels
+    IbcTransfer {
+        /// Source channel of the transfer
+        channel: String,
+        /// Recipient address on the counterparty chain
+        to: String,
+        /// Amount of the tokens to transfer
+        amount: Coin,
+        /// Optional memo to attach to the transfer
+        memo: Option<String>,
+        /// Timeout in seconds from now. Uses the configured default if not provided
+        timeout: Option<u64>,
+    },
+
+    /// Owner only method to update the channels allowed for IBC transfers
+    UpdateIbcConfig {
+        /// Full list of the allowed channels replacing the current one
+        channels: Vec<String>,
+        /// New default timeout in seconds. The current one is kept if not provided
+        default_timeout: Option<u64>,
+    },
+
+    /// Owner only method to delay transfers above the thresholds or of the chosen tokens.
+    /// Zero delay removes the timelock. Changes to an active timelock are delayed too
+    UpdateTimelock {
+        /// Seconds a delayed action has to wait in the queue
+        delay: u64,
+        /// Transfers above these amounts are delayed. Cw20 tokens use the contract address as the denom
+        thresholds: Vec<Coin>,
+        /// Tokens whose transfers are delayed
+        tokens: Vec<TokenInfo>,
+    },
+
+    /// Execute a queued action once its delay has passed
+    ExecuteQueued {
+        /// Id of the queued action
+        id: u64,
+    },
+
+    /// Remove an action from the queue without executing it
+    CancelQueued {
+        /// Id of the queued action
+        id: u64,
+    },
+
+    /// Owner only method to register a payment repeating every interval
+    SchedulePayment {
+        /// Address receiving the payments
+        recipient: String,
+        /// Amount paid every interval
+        amount: Vec<Coin>,
+        /// Seconds between the payments
+        interval: u64,
+        /// Time of the first payment. Defaults to now
+        start: Option<Timestamp>,
+        /// Time after which no more payments are made
+        end: Option<Timestamp>,
+    },
+
+    /// Owner only method to stop a scheduled payment
+    CancelPayment {
+        /// Id of the scheduled payment
+        id: u64,
+    },
+
+    /// Owner only method to let a merchant pull funds up to a cap every period
+    AuthorizePull {
+        /// Address allowed to pull the funds
+        merchant: String,
+        /// Maximum amount that can be pulled within a period
+        cap: Vec<Coin>,
+        /// Length of a period in seconds
+        period: u64,
+        /// Time after which the authorization can't be used
+        end: Option<Timestamp>,
+    },
+
+    /// Owner only method to revoke a pull authorization
+    RevokePull {
+        /// Address of the merchant
+        merchant: String,
+    },
+
+    /// Owner only method to let a third party run pre-approved actions, e.g. a keeper bot
+    GrantExecutor {
+        /// Address of the executor
+        executor: String,
+        /// Templates the executor is allowed to run
+        permissions: Vec<ExecutorPermission>,
+        /// Time after which the grant can't be used
+        expiration: Option<Timestamp>,
+        /// Paid to the executor for every call
+        fee: Vec<Coin>,
+        /// Minimal number of seconds between the calls
+        interval: u64,
+    },
+
+    /// Owner only method to revoke the rights of an executor
+    RevokeExecutor {
+        /// Address of the executor
+        executor: String,
+    },
+
+    /// Owner only method to install a plugin contract implementing the plugin interface
+    InstallPlugin {
+        /// Address of the plugin contract
+        address: String,
+        /// Whether the plugin validates the actions or gets called after them
+        kind: PluginKind,
+    },
+
+    /// Owner only method to remove a plugin. Isn't subject to the validator plugins
+    UninstallPlugin {
+        /// Address of the plugin contract
+        address: String,
+    },
+
+    /// Owner only method to replace the rules every batch of actions is checked against. Empty list removes them
+    UpdatePolicy {
+        rules: Vec<PolicyRule>,
+    },
+
+    /// Owner only method to choose the actions that need two credentials. Empty list removes the requirement
+    UpdateTwoFactor {
+        actions: Vec<SensitiveAction>,
+        thresholds: Vec<Coin>,
+    },
+
+    /// Owner only method to remove stored credentials
+    RemoveCredentials {
+        ids: Vec<CredentialId>,
+    },
+
+    /// Owner only method to allow the registry to migrate the account to the given code id
+    ApproveMigration {
+        code_id: u64,
+    },
+
+    /// Owner only method to set the guardians that can recover the account. Empty list removes them
+    UpdateGuardians {
+        /// Addresses of the guardians
+        guardians: Vec<String>,
+        /// Number of approvals required to start a recovery
+        threshold: u32,
+        /// Delay in seconds before an approved recovery takes effect. At least a day
+        delay: u64,
+    },
+
+    /// Owner only method to cancel a pending recovery
+    VetoRecovery {},
+
+    /// Owner only method to replace the collections checked by `sync_known_tokens`
+    UpdateWatchedCollections {
+        collections: Vec<String>,
+    },
+
+    /// Owner only method to choose what happens to tokens received with an unrecognised payload
+    UpdateReceiveConfig {
+        reject_unknown: bool,
+    },
+
+    /// Owner only method to run actions on the account bound to a token held by this account
+    ExecuteOnChild {
+        /// Token the child account is bound to
+        token_info: TokenInfo,
+        /// Actions forwarded to the child through `ExecuteNative`
+        actions: Vec<ActiontMsg>,
     },
 
     /// Registry only method to call when a token is moved to escrow
@@ -108,7 +621,7 @@ pub enum ActiontMsg {
 }
 
 
-#[signed_execute(ActiontMsg, Credential)]
+#[signed_execute(ActiontMsg, SignedCredentials)]
 #[cw_serde]
 pub enum ExecuteMsg {
     /// Registry only method to update the owner to the current NFT holder
@@ -122,25 +635,80 @@ pub enum ExecuteMsg {
     /// Owner only method to update account data
     UpdateAccountData(UpdateAccountOp),
 
+    /// Social recovery of the account credentials by the guardians
+    Recovery(RecoveryMsg),
+
+    /// Actions signed by one credential and approved later by another
+    Approvals(ApprovalMsg),
+
+    /// Cranking of the scheduled payments and pulls by the merchants
+    Payments(PaymentMsg),
+
+    /// Executor only method to run one of the pre-approved actions
+    Executor(ExecutorMsg),
+
     /// Registering a token as known on receiving
     ReceiveNft(Cw721ReceiveMsg),
 
+    /// Registering a cw20 contract as known on receiving
+    Receive(Cw20ReceiveMsg),
+
+    /// ICS-721 bridge only method to report the outcome of a transfer sent by the account
+    /// or of the bound token being bridged out
+    Ics721AckCallback(Ics721AckCallbackMsg),
+
+    /// ICS-721 bridge only method to report tokens received by the account or the bound token
+    /// coming back from another chain
+    Ics721ReceiveCallback(Ics721ReceiveCallbackMsg),
+
     /// A method callable by anybody to freeze the account if the owner has changed
     Freeze {},
 
+    /// A method callable by anybody to add the held tokens of the watched collections to
+    /// the known ones and prune the tokens the account no longer holds. Resumes from the
+    /// stored cursor of every collection
+    SyncKnownTokens {
+        /// Tokens checked per collection. Capped at `MAX_SYNC_LIMIT`
+        limit: Option<u32>,
+    },
+
     /// Remove all the data from the contract and make it unsuable
     Purge {},
 }
 
-pub type KnownTokensResponse = Vec<TokenInfo>;
+pub type KnownTokensResponse = Vec<KnownToken>;
+
+#[cw_serde]
+pub struct KnownCollection {
+    /// Contract address of the collection
+    pub collection: String,
+    /// Number of the known tokens of the collection
+    pub tokens: u32,
+}
+
+pub type KnownCollectionsResponse = Vec<KnownCollection>;
+
+pub type FeeGrantsResponse = Vec<FeeGrantInfo>;
 
 
+#[cw_serde]
+pub struct StakingResponse {
+    /// Delegations of the account
+    pub delegations: Vec<Delegation>,
+    /// Pending staking rewards per validator
+    pub rewards: Vec<DelegatorReward>,
+    /// Total pending staking rewards
+    pub total_rewards: Vec<DecCoin>,
+}
+
 #[cw_serde]
 pub struct AssetsResponse {
     /// Native fungible tokens held by an account
     pub balances: Vec<Coin>,
+    /// CW20 tokens held by an account
+    pub cw20_balances: Vec<Cw20Coin>,
     /// NFT tokens the account is aware of
-    pub tokens: Vec<TokenInfo>,
+    pub tokens: Vec<KnownToken>,
 }
 
 // #[saa_derivable]
@@ -163,20 +731,142 @@ pub enum QueryMsg {
     #[returns(String)]
     Registry {},
 
+    /// Holder of the voucher of the bound token if it was bridged to another chain
+    #[returns(Option<RemoteHolder>)]
+    RemoteHolder {},
+
     /// List of the tokens the account is aware of
     #[returns(KnownTokensResponse)]
     KnownTokens {
-        skip: Option<u32>,
+        /// Only list the tokens of this collection
+        collection: Option<String>,
+        /// Token to start after. Only its id is used when the collection is given
+        start_after: Option<TokenInfo>,
+        limit: Option<u32>,
+        /// Attach the token uri, the extension and the collection name and symbol to every token
+        with_metadata: Option<bool>,
+    },
+
+    /// Approvals given by the account for its tokens that haven't been revoked
+    #[returns(TokenApprovalsResponse)]
+    TokenApprovals {},
+
+    /// Collections the account is aware of with the number of their known tokens
+    #[returns(KnownCollectionsResponse)]
+    KnownCollections {
+        start_after: Option<String>,
         limit: Option<u32>,
     },
 
     /// List of the assets (balances + tokens) the account is aware of
     #[returns(AssetsResponse)]
     Assets {
+        /// Applied to both the tokens and the cw20 balances
         skip: Option<u32>,
+        /// Applied to both the tokens and the cw20 balances. The latter capped at `MAX_CW20_BALANCES`
+        limit: Option<u32>,
+        /// Attach the token uri, the extension and the collection name and symbol to every token
+        with_metadata: Option<bool>,
+    },
+
+    /// Delegations and pending staking rewards of the account
+    #[returns(StakingResponse)]
+    Staking {},
+
+    /// Fee grants given by the account that haven't been revoked
+    #[returns(FeeGrantsResponse)]
+    FeeGrants {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Rules for delaying the actions of the account
+    #[returns(Option<crate::TimelockConfig>)]
+    Timelock {},
+
+    /// Actions waiting in the queue to be executed
+    #[returns(crate::QueuedActionsResponse)]
+    QueuedActions {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// Payments scheduled by the owner
+    #[returns(crate::ScheduledPaymentsResponse)]
+    ScheduledPayments {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// Merchants allowed to pull funds from the account
+    #[returns(crate::PullAuthorizationsResponse)]
+    PullAuthorizations {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Executors of the account and their permissions
+    #[returns(crate::ExecutorsResponse)]
+    Executors {
+        start_after: Option<String>,
         limit: Option<u32>,
     },
 
+    /// Plugins installed on the account
+    #[returns(crate::PluginsResponse)]
+    Plugins {},
+
+    /// Rules every batch of actions is checked against
+    #[returns(crate::Policy)]
+    Policy {},
+
+    /// Collections checked by `sync_known_tokens` with their cursors
+    #[returns(crate::WatchedCollectionsResponse)]
+    WatchedCollections {},
+
+    /// How the account treats the payloads of received tokens
+    #[returns(crate::ReceiveConfig)]
+    ReceiveConfig {},
+
+    /// Label and lock given to a token by the payload it was received with
+    #[returns(crate::TokenAttributes)]
+    TokenAttributes {
+        collection: String,
+        token_id: String,
+    },
+
+    /// Accounts bound to the tokens held by this account and by its children
+    #[returns(crate::AccountTreeNode)]
+    AccountTree {
+        /// Levels of children to include. Capped at `MAX_TREE_DEPTH`
+        depth: Option<u32>,
+    },
+
+    /// Check which rule of the policy would reject the given actions
+    #[returns(crate::SimulatePolicyResponse)]
+    SimulatePolicy {
+        actions: Vec<ActiontMsg>,
+    },
+
+    /// Actions that need two credentials
+    #[returns(Option<crate::TwoFactorConfig>)]
+    TwoFactor {},
+
+    /// Actions waiting for the approval by a second credential
+    #[returns(crate::PendingApprovalsResponse)]
+    PendingApprovals {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// Guardians of the account and the pending recovery proposal
+    #[returns(RecoveryResponse)]
+    Recovery {},
+
+    /// Channels allowed for IBC transfers and the default timeout
+    #[returns(IbcConfig)]
+    IbcConfig {},
+
     /// Incremental number telling wether a direct interaction with the account has occured
     #[returns(u64)]
     AccountNumber {},
@@ -184,36 +874,57 @@ pub enum QueryMsg {
     #[cfg(feature = "full_info")]
     #[returns(crate::FullInfoResponse)]
     FullInfo {
+        /// Applied to both the tokens and the cw20 balances
         skip: Option<u32>,
+        /// Applied to both the tokens and the cw20 balances. The latter capped at `MAX_CW20_BALANCES`
         limit: Option<u32>,
+        /// Attach the token uri, the extension and the collection name and symbol to every token
+        with_metadata: Option<bool>,
     },
 }
 
-pub fn encode_feegrant_msg(
-    granter: &str,
-    grantee: &str,
-    allowance: Option<BasicAllowance>,
-) -> StdResult<CosmosMsg> {
-    let (spend_limit, expiration) = match allowance {
-        Some(allowance) => {
-            let coins = allowance
-                .spend_limit
-                .iter()
-                .map(|coin| {
-                    Anybuf::new()
-                        .append_string(1, &coin.denom)
-                        .append_string(2, &coin.amount.to_string())
-                })
-                .collect();
-
-            let expiration = allowance.expiration.map(|ts| {
-                Anybuf::new()
-                    .append_int64(1, ts.seconds() as i64)
-                    .append_int32(2, 0i32)
-            });
-
-            (coins, expiration)
+/// Actions that a cosmos message would trigger when executed natively on the given account.
+/// An `ExecuteNative` call on the account itself is unwrapped, anything else is treated
+/// as a message passed to the `Execute` proxy action
+pub fn native_actions(account: &str, msg: CosmosMsg) -> Vec<ActiontMsg> {
+    if let CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr,
+        msg: inner,
+        ..
+    }) = &msg
+    {
+        if contract_addr == account {
+            if let Ok(ExecuteMsg::ExecuteNative { msgs }) = from_json(inner) {
+                return msgs;
+            }
         }
+    }
+    vec![ActiontMsg::Execute { msgs: vec![msg] }]
+}
+
+fn encode_coins(coins: &[Coin]) -> Vec<Anybuf> {
+    coins
+        .iter()
+        .map(|coin| {
+            Anybuf::new()
+                .append_string(1, &coin.denom)
+                .append_string(2, coin.amount.to_string())
+        })
+        .collect()
+}
+
+fn encode_timestamp(ts: &Timestamp) -> Anybuf {
+    Anybuf::new()
+        .append_int64(1, ts.seconds() as i64)
+        .append_int32(2, 0i32)
+}
+
+pub fn encode_feegrant_msg(granter: &str, grant: &FeeGrantInfo) -> StdResult<CosmosMsg> {
+    let (spend_limit, expiration) = match &grant.allowance {
+        Some(allowance) => (
+            encode_coins(&allowance.spend_limit),
+            allowance.expiration.as_ref().map(encode_timestamp),
+        ),
         None => (vec![], None),
     };
 
@@ -223,24 +934,42 @@ pub fn encode_feegrant_msg(
         basic_msg = basic_msg.append_message(2, &expiration.unwrap());
     }
 
-    let basic = Anybuf::new()
-        .append_string(1, "/cosmos.feegrant.v1beta1.BasicAllowance")
-        .append_message(2, &basic_msg);
+    let inner = match &grant.periodic {
+        Some(periodic) => {
+            // the first period starts right away with the full limit available
+            let limit = encode_coins(&periodic.period_spend_limit);
+            let reset = grant.granted_at.plus_seconds(periodic.period);
+            Anybuf::new()
+                .append_string(1, "/cosmos.feegrant.v1beta1.PeriodicAllowance")
+                .append_message(
+                    2,
+                    &Anybuf::new()
+                        .append_message(1, &basic_msg)
+                        .append_message(2, &Anybuf::new().append_int64(1, periodic.period as i64))
+                        .append_repeated_message(3, &limit)
+                        .append_repeated_message(4, &limit)
+                        .append_message(5, &encode_timestamp(&reset)),
+                )
+        }
+        None => Anybuf::new()
+            .append_string(1, "/cosmos.feegrant.v1beta1.BasicAllowance")
+            .append_message(2, &basic_msg),
+    };
 
     let allowed_msg = Anybuf::new()
         .append_string(1, "/cosmos.feegrant.v1beta1.AllowedMsgAllowance")
         .append_message(
             2,
             &Anybuf::new()
-                .append_message(1, &basic)
-                .append_repeated_string(2, &["/cosmwasm.wasm.v1.MsgExecuteContract"]),
+                .append_message(1, &inner)
+                .append_repeated_string(2, &grant.allowed_msgs),
         );
 
     let msg: CosmosMsg = CosmosMsg::Stargate {
         type_url: "/cosmos.feegrant.v1beta1.MsgGrantAllowance".to_string(),
         value: anybuf::Anybuf::new()
             .append_string(1, granter)
-            .append_string(2, grantee)
+            .append_string(2, &grant.grantee)
             .append_message(3, &allowed_msg)
             .into_vec()
             .into(),
@@ -248,3 +977,202 @@ pub fn encode_feegrant_msg(
 
     Ok(msg)
 }
+
+pub fn encode_revoke_feegrant_msg(granter: &str, grantee: &str) -> StdResult<CosmosMsg> {
+    let msg: CosmosMsg = CosmosMsg::Stargate {
+        type_url: "/cosmos.feegrant.v1beta1.MsgRevokeAllowance".to_string(),
+        value: Anybuf::new()
+            .append_string(1, granter)
+            .append_string(2, grantee)
+            .into_vec()
+            .into(),
+    };
+
+    Ok(msg)
+}
+
+
+/// Whether the feegrant module still has the grant. Expired and used up grants are
+/// pruned by the chain and can't be revoked anymore
+pub fn has_fee_grant(querier: &QuerierWrapper, granter: &str, grantee: &str) -> StdResult<bool> {
+    let request: QueryRequest<Empty> = QueryRequest::Stargate {
+        path: "/cosmos.feegrant.v1beta1.Query/Allowance".to_string(),
+        data: Anybuf::new()
+            .append_string(1, granter)
+            .append_string(2, grantee)
+            .into_vec()
+            .into(),
+    };
+    // the module answers with an error if there is no grant
+    Ok(matches!(
+        querier.raw_query(&to_json_vec(&request)?),
+        SystemResult::Ok(ContractResult::Ok(_))
+    ))
+}
+
+
+pub fn encode_ibc_transfer_msg(
+    sender: &str,
+    channel: &str,
+    receiver: &str,
+    amount: &Coin,
+    memo: Option<String>,
+    timeout: Timestamp,
+) -> StdResult<CosmosMsg> {
+    // IbcMsg::Transfer of cosmwasm v1 doesn't support memos
+    let mut value = Anybuf::new()
+        .append_string(1, "transfer")
+        .append_string(2, channel)
+        .append_message(
+            3,
+            &Anybuf::new()
+                .append_string(1, &amount.denom)
+                .append_string(2, amount.amount.to_string()),
+        )
+        .append_string(4, sender)
+        .append_string(5, receiver)
+        .append_uint64(7, timeout.nanos());
+
+    if let Some(memo) = memo {
+        value = value.append_string(8, memo);
+    }
+
+    let msg: CosmosMsg = CosmosMsg::Stargate {
+        type_url: "/ibc.applications.transfer.v1.MsgTransfer".to_string(),
+        value: value.into_vec().into(),
+    };
+
+    Ok(msg)
+}
+
+
+pub fn try_ibc_transfering(
+    storage: &dyn Storage,
+    env: &Env,
+    channel: String,
+    to: String,
+    amount: Coin,
+    memo: Option<String>,
+    timeout: Option<u64>,
+) -> AccountResult {
+    let config = IBC_CONFIG.may_load(storage)?.unwrap_or_default();
+    ensure!(
+        config.channels.contains(&channel),
+        AccountError::ChannelNotAllowed(channel)
+    );
+    let timeout = env
+        .block
+        .time
+        .plus_seconds(timeout.unwrap_or(config.default_timeout));
+
+    let sender = env.contract.address.as_str();
+    let msg = encode_ibc_transfer_msg(sender, &channel, &to, &amount, memo, timeout)?;
+
+    Ok(Response::new()
+        .add_message(msg)
+        .add_event(Event::new("ibc_transfer").add_attributes([
+            ("channel", channel),
+            ("sender", sender.to_string()),
+            ("receiver", to),
+            ("amount", amount.to_string()),
+            ("timeout", timeout.seconds().to_string()),
+        ]))
+        .add_attribute("action", "ibc_transfer"))
+}
+
+/// Raw IBC transfers have to go through the channels allowed for the typed action
+pub fn assert_ibc_channels(storage: &dyn Storage, actions: &[ActiontMsg]) -> AccountResult<()> {
+    let config = IBC_CONFIG.may_load(storage)?.unwrap_or_default();
+    let channels = actions
+        .iter()
+        .filter_map(|action| match action {
+            ActiontMsg::Execute { msgs } => Some(msgs),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|msg| match msg {
+            CosmosMsg::Ibc(IbcMsg::Transfer { channel_id, .. }) => Some(channel_id),
+            _ => None,
+        });
+    for channel in channels {
+        ensure!(
+            config.channels.contains(channel),
+            AccountError::ChannelNotAllowed(channel.clone())
+        );
+    }
+    Ok(())
+}
+
+pub fn try_updating_ibc_config(
+    storage: &mut dyn Storage,
+    channels: Vec<String>,
+    default_timeout: Option<u64>,
+) -> AccountResult {
+    let mut config = IBC_CONFIG.may_load(storage)?.unwrap_or_default();
+    config.channels = channels;
+    if let Some(timeout) = default_timeout {
+        ensure!(timeout > 0, AccountError::Generic("Timeout must be positive".into()));
+        config.default_timeout = timeout;
+    }
+    IBC_CONFIG.save(storage, &config)?;
+    Ok(Response::new().add_attribute("action", "update_ibc_config"))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn try_fee_granting(
+    querier: &QuerierWrapper,
+    storage: &mut dyn Storage,
+    env: &Env,
+    grantee: String,
+    allowance: Option<BasicAllowance>,
+    periodic: Option<PeriodicAllowance>,
+    allowed_msgs: Option<Vec<String>>,
+) -> AccountResult {
+    let contract = env.contract.address.as_str();
+    let mut res = Response::new();
+
+    // the chain doesn't allow to grant twice so the previous grant is revoked first
+    if FEE_GRANTS.has(storage, &grantee) && has_fee_grant(querier, contract, &grantee)? {
+        res = res.add_message(encode_revoke_feegrant_msg(contract, &grantee)?);
+    }
+
+    let grant = FeeGrantInfo {
+        grantee,
+        allowance,
+        periodic,
+        allowed_msgs: allowed_msgs
+            .filter(|msgs| !msgs.is_empty())
+            .unwrap_or(vec![DEFAULT_FEE_GRANT_MSG.to_string()]),
+        granted_at: env.block.time,
+    };
+    let msg = encode_feegrant_msg(contract, &grant)?;
+    FEE_GRANTS.save(storage, &grant.grantee, &grant)?;
+
+    Ok(res
+        .add_message(msg)
+        .add_attribute("action", "fee_grant")
+        .add_attribute("grantee", grant.grantee))
+}
+
+pub fn try_revoking_fee_grant(
+    querier: &QuerierWrapper,
+    storage: &mut dyn Storage,
+    env: &Env,
+    grantee: String,
+) -> AccountResult {
+    if !FEE_GRANTS.has(storage, &grantee) {
+        return Err(AccountError::Generic(format!("No fee grant for {grantee}")));
+    }
+    FEE_GRANTS.remove(storage, &grantee);
+
+    let mut res = Response::new()
+        .add_attribute("action", "revoke_fee_grant")
+        .add_attribute("grantee", grantee.as_str());
+
+    // expired or used up grants are already gone from the chain
+    let contract = env.contract.address.as_str();
+    if has_fee_grant(querier, contract, &grantee)? {
+        res = res.add_message(encode_revoke_feegrant_msg(contract, &grantee)?);
+    }
+    Ok(res)
+}
@@ -0,0 +1,377 @@
+use anybuf::Anybuf;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Binary, CosmosMsg, QuerierWrapper, QueryRequest, StdError, StdResult, WasmMsg,
+};
+#[cfg(feature = "omniflix")]
+use omniflix_std::types::omniflix::onft::v1beta1::{MsgBurnOnft, MsgTransferOnft, OnftQuerier};
+use serde::Deserialize;
+
+use crate::{ContractInfoResponse, Cw721Msg, NftInfoResponse, OwnerOfResponse, TokensResponse};
+
+
+/// Module or contract standard the tokens of a collection follow
+#[cw_serde]
+pub enum NftBackend {
+    /// Collection is the address of a cw721 contract
+    Cw721,
+    /// Collection is the denom id of the Omniflix ONFT module
+    Onft,
+    /// Collection is the class id of the Cosmos SDK x/nft module
+    XNft,
+}
+
+/// Backend of a collection the registry has no record of
+impl Default for NftBackend {
+    fn default() -> Self {
+        NftBackend::Cw721
+    }
+}
+
+#[cw_serde]
+pub struct CollectionBackend {
+    /// Contract address, denom id or class id of the collection
+    pub collection: String,
+    pub backend: NftBackend,
+}
+
+
+#[derive(Deserialize)]
+struct XNftOwnerResponse {
+    owner: String,
+}
+
+#[derive(Deserialize)]
+struct XNftToken {
+    id: String,
+    #[serde(default)]
+    uri: String,
+}
+
+#[derive(Deserialize)]
+struct XNftTokensResponse {
+    #[serde(default)]
+    nfts: Vec<XNftToken>,
+}
+
+#[derive(Deserialize)]
+struct XNftTokenResponse {
+    nft: Option<XNftToken>,
+}
+
+#[derive(Deserialize)]
+struct XNftClass {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    symbol: String,
+}
+
+#[derive(Deserialize)]
+struct XNftClassResponse {
+    class: Option<XNftClass>,
+}
+
+fn xnft_query<T: serde::de::DeserializeOwned>(
+    querier: &QuerierWrapper,
+    method: &str,
+    data: Anybuf,
+) -> StdResult<T> {
+    querier.query(&QueryRequest::Stargate {
+        path: format!("/cosmos.nft.v1beta1.Query/{method}"),
+        data: data.into_vec().into(),
+    })
+}
+
+
+impl NftBackend {
+    fn unsupported(&self, what: &str) -> StdError {
+        StdError::generic_err(format!("{what} isn't supported by the {self:?} backend"))
+    }
+
+    /// Whether the collections of the backend let other addresses transfer the tokens of an owner
+    pub fn supports_approvals(&self) -> bool {
+        matches!(self, NftBackend::Cw721)
+    }
+
+    pub fn query_owner(
+        &self,
+        querier: &QuerierWrapper,
+        collection: &str,
+        token_id: &str,
+    ) -> StdResult<String> {
+        match self {
+            NftBackend::Cw721 => {
+                let res: OwnerOfResponse = querier.query_wasm_smart(
+                    collection,
+                    &Cw721Msg::OwnerOf {
+                        token_id: token_id.to_string(),
+                        include_expired: None,
+                    },
+                )?;
+                Ok(res.owner)
+            }
+            #[cfg(feature = "omniflix")]
+            NftBackend::Onft => OnftQuerier::new(querier)
+                .onft(collection.to_string(), token_id.to_string())?
+                .onft
+                .map(|onft| onft.owner)
+                .ok_or_else(|| StdError::generic_err("Token not found")),
+            #[cfg(not(feature = "omniflix"))]
+            NftBackend::Onft => Err(self.unsupported("Querying owners")),
+            NftBackend::XNft => {
+                let res: XNftOwnerResponse = xnft_query(
+                    querier,
+                    "Owner",
+                    Anybuf::new()
+                        .append_string(1, collection)
+                        .append_string(2, token_id),
+                )?;
+                Ok(res.owner)
+            }
+        }
+    }
+
+    /// Ids of the tokens of the collection held by the owner, after `start_after` if the backend can paginate
+    pub fn query_tokens(
+        &self,
+        querier: &QuerierWrapper,
+        collection: &str,
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<TokensResponse> {
+        match self {
+            NftBackend::Cw721 => querier.query_wasm_smart(
+                collection,
+                &Cw721Msg::Tokens {
+                    owner,
+                    start_after,
+                    limit,
+                },
+            ),
+            #[cfg(feature = "omniflix")]
+            NftBackend::Onft => {
+                let res = OnftQuerier::new(querier).owner_onf_ts(
+                    collection.to_string(),
+                    owner,
+                    None,
+                )?;
+                let owner = res
+                    .owner
+                    .ok_or_else(|| StdError::generic_err("Owner not found"))?;
+                Ok(TokensResponse {
+                    tokens: owner
+                        .id_collections
+                        .into_iter()
+                        .find(|c| c.denom_id == collection)
+                        .map(|c| c.onft_ids)
+                        .unwrap_or_default(),
+                })
+            }
+            #[cfg(not(feature = "omniflix"))]
+            NftBackend::Onft => Err(self.unsupported("Querying tokens")),
+            NftBackend::XNft => {
+                // the module paginates the tokens of an owner by id, starting at the given key inclusively
+                let mut pagination = Anybuf::new();
+                if let Some(start_after) = &start_after {
+                    pagination = pagination.append_bytes(1, start_after.as_bytes());
+                }
+                if let Some(limit) = limit {
+                    pagination = pagination.append_uint64(3, limit as u64 + 1);
+                }
+                let res: XNftTokensResponse = xnft_query(
+                    querier,
+                    "NFTs",
+                    Anybuf::new()
+                        .append_string(1, collection)
+                        .append_string(2, owner)
+                        .append_message(3, &pagination),
+                )?;
+                Ok(TokensResponse {
+                    tokens: res
+                        .nfts
+                        .into_iter()
+                        .map(|nft| nft.id)
+                        .filter(|id| Some(id) != start_after.as_ref())
+                        .collect(),
+                })
+            }
+        }
+    }
+
+    pub fn transfer_msg(
+        &self,
+        collection: &str,
+        token_id: &str,
+        sender: &str,
+        recipient: &str,
+    ) -> StdResult<CosmosMsg> {
+        match self {
+            NftBackend::Cw721 => Ok(WasmMsg::Execute {
+                contract_addr: collection.to_string(),
+                msg: to_json_binary(&Cw721Msg::TransferNft {
+                    recipient: recipient.to_string(),
+                    token_id: token_id.to_string(),
+                })?,
+                funds: vec![],
+            }
+            .into()),
+            #[cfg(feature = "omniflix")]
+            NftBackend::Onft => Ok(MsgTransferOnft {
+                denom_id: collection.to_string(),
+                id: token_id.to_string(),
+                sender: sender.to_string(),
+                recipient: recipient.to_string(),
+            }
+            .into()),
+            #[cfg(not(feature = "omniflix"))]
+            NftBackend::Onft => Err(self.unsupported("Transferring tokens")),
+            NftBackend::XNft => Ok(CosmosMsg::Stargate {
+                type_url: "/cosmos.nft.v1beta1.MsgSend".to_string(),
+                value: Anybuf::new()
+                    .append_string(1, collection)
+                    .append_string(2, token_id)
+                    .append_string(3, sender)
+                    .append_string(4, recipient)
+                    .into_vec()
+                    .into(),
+            }),
+        }
+    }
+
+    /// Transfer of a token to a contract notifying it with the message. Only cw721 collections can notify
+    pub fn send_msg(
+        &self,
+        collection: &str,
+        token_id: &str,
+        contract: &str,
+        msg: Binary,
+        funds: Vec<cosmwasm_std::Coin>,
+    ) -> StdResult<CosmosMsg> {
+        match self {
+            NftBackend::Cw721 => Ok(WasmMsg::Execute {
+                contract_addr: collection.to_string(),
+                msg: to_json_binary(&Cw721Msg::SendNft {
+                    contract: contract.to_string(),
+                    token_id: token_id.to_string(),
+                    msg,
+                })?,
+                funds,
+            }
+            .into()),
+            _ => Err(self.unsupported("Sending tokens")),
+        }
+    }
+
+    #[cfg_attr(not(feature = "omniflix"), allow(unused_variables))]
+    pub fn burn_msg(&self, collection: &str, token_id: &str, sender: &str) -> StdResult<CosmosMsg> {
+        match self {
+            NftBackend::Cw721 => Ok(WasmMsg::Execute {
+                contract_addr: collection.to_string(),
+                msg: to_json_binary(&Cw721Msg::Burn {
+                    token_id: token_id.to_string(),
+                })?,
+                funds: vec![],
+            }
+            .into()),
+            #[cfg(feature = "omniflix")]
+            NftBackend::Onft => Ok(MsgBurnOnft {
+                denom_id: collection.to_string(),
+                id: token_id.to_string(),
+                sender: sender.to_string(),
+            }
+            .into()),
+            #[cfg(not(feature = "omniflix"))]
+            NftBackend::Onft => Err(self.unsupported("Burning tokens")),
+            NftBackend::XNft => Err(self.unsupported("Burning tokens")),
+        }
+    }
+
+    /// Uri and JSON encoded extension of a token
+    pub fn query_token_metadata(
+        &self,
+        querier: &QuerierWrapper,
+        collection: &str,
+        token_id: &str,
+    ) -> StdResult<(Option<String>, Option<Binary>)> {
+        match self {
+            NftBackend::Cw721 => {
+                let res: NftInfoResponse = querier.query_wasm_smart(
+                    collection,
+                    &Cw721Msg::NftInfo {
+                        token_id: token_id.to_string(),
+                    },
+                )?;
+                let extension = res
+                    .extension
+                    .map(|ext| to_json_binary(&ext))
+                    .transpose()?;
+                Ok((res.token_uri, extension))
+            }
+            #[cfg(feature = "omniflix")]
+            NftBackend::Onft => {
+                let onft = OnftQuerier::new(querier)
+                    .onft(collection.to_string(), token_id.to_string())?
+                    .onft
+                    .ok_or_else(|| StdError::generic_err("Token not found"))?;
+                let token_uri = onft
+                    .metadata
+                    .map(|m| m.media_uri)
+                    .filter(|uri| !uri.is_empty());
+                let extension = Some(onft.data)
+                    .filter(|data| !data.is_empty())
+                    .map(|data| Binary::from(data.into_bytes()));
+                Ok((token_uri, extension))
+            }
+            #[cfg(not(feature = "omniflix"))]
+            NftBackend::Onft => Err(self.unsupported("Querying token metadata")),
+            NftBackend::XNft => {
+                let res: XNftTokenResponse = xnft_query(
+                    querier,
+                    "NFT",
+                    Anybuf::new()
+                        .append_string(1, collection)
+                        .append_string(2, token_id),
+                )?;
+                let nft = res
+                    .nft
+                    .ok_or_else(|| StdError::generic_err("Token not found"))?;
+                Ok((Some(nft.uri).filter(|uri| !uri.is_empty()), None))
+            }
+        }
+    }
+
+    /// Name and symbol of a collection
+    pub fn query_collection_metadata(
+        &self,
+        querier: &QuerierWrapper,
+        collection: &str,
+    ) -> StdResult<(String, String)> {
+        match self {
+            NftBackend::Cw721 => {
+                let res: ContractInfoResponse =
+                    querier.query_wasm_smart(collection, &Cw721Msg::ContractInfo {})?;
+                Ok((res.name, res.symbol))
+            }
+            #[cfg(feature = "omniflix")]
+            NftBackend::Onft => {
+                let denom = OnftQuerier::new(querier)
+                    .denom(collection.to_string())?
+                    .denom
+                    .ok_or_else(|| StdError::generic_err("Denom not found"))?;
+                Ok((denom.name, denom.symbol))
+            }
+            #[cfg(not(feature = "omniflix"))]
+            NftBackend::Onft => Err(self.unsupported("Querying collection metadata")),
+            NftBackend::XNft => {
+                let res: XNftClassResponse =
+                    xnft_query(querier, "Class", Anybuf::new().append_string(1, collection))?;
+                let class = res
+                    .class
+                    .ok_or_else(|| StdError::generic_err("Class not found"))?;
+                Ok((class.name, class.symbol))
+            }
+        }
+    }
+}
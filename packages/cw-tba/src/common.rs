@@ -1,9 +1,10 @@
-#[cfg(feature = "omniflix")]
-use omniflix_std::types::omniflix::onft::v1beta1::{MsgTransferOnft,  OnftQuerier};
-use cosmwasm_std::{Addr, Coin, QuerierWrapper, StdError, StdResult};
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Coin, CosmosMsg, Order, QuerierWrapper, Response, StdError, StdResult, Storage,
+    Uint128, WasmMsg,
+};
 use saa_wasm::StoredCredentials;
 use cosmwasm_schema::cw_serde;
-use crate::{msgs::*, Status};
+use crate::{msgs::*, query_collection_backend, state::KNOWN_CW20S, NftBackend, QueryMsg, StakingResponse, Status};
 
 
 #[cw_serde]
@@ -20,6 +21,20 @@ impl TokenInfo {
     }
 }
 
+#[cw_serde]
+pub struct Cw20Coin {
+    /// Contract address of the cw20 token
+    pub address: String,
+    /// Amount of the tokens held
+    pub amount: Uint128,
+}
+
+/// Maximum number of cw20 contracts an account keeps track of
+pub const MAX_KNOWN_CW20S: usize = 50;
+
+/// Maximum number of cw20 balances queried for a single response
+pub const MAX_CW20_BALANCES: u32 = 20;
+
 #[cw_serde]
 pub struct FullInfoResponse {
     /// Current owner of the token account that is ideally a holder of an NFT
@@ -30,8 +45,10 @@ pub struct FullInfoResponse {
     pub registry: String,
     /// Native fungible tokens held by an account
     pub balances: Vec<Coin>,
+    /// CW20 tokens held by an account
+    pub cw20_balances: Vec<Cw20Coin>,
     /// NFT tokens the account is aware of
-    pub tokens: Vec<TokenInfo>,
+    pub tokens: Vec<crate::KnownToken>,
     /// Whether the account is frozen
     pub status: Status,
     /// Full info about installed credentials
@@ -41,138 +58,150 @@ pub struct FullInfoResponse {
 
 pub fn verify_nft_ownership(
     querier: &QuerierWrapper,
+    backend: &NftBackend,
     address: &str,
     token_info: TokenInfo,
 ) -> StdResult<()> {
-    if query_owner(querier, &token_info.collection, &token_info.id)?.owner != address {
+    if backend.query_owner(querier, &token_info.collection, &token_info.id)? != address {
         return Err(StdError::generic_err("Not NFT owner"));
     }
     Ok(())
 }
 
 
-#[cfg(not(feature = "omniflix"))]
-fn query_owner(
+/// Token bound to the address if it's an account created by the given registry
+pub fn query_account_token(
     querier: &QuerierWrapper,
-    collection: &str,
-    token_id: &str,
-) -> StdResult<OwnerOfResponse> {
-    querier.query_wasm_smart(
-        collection,
-        &Cw721Msg::OwnerOf {
-            token_id: token_id.to_string(),
-            include_expired: None,
-        },
-    )
+    registry: &str,
+    address: &str,
+) -> Option<TokenInfo> {
+    let account_registry: String = querier
+        .query_wasm_smart(address, &QueryMsg::Registry {})
+        .ok()?;
+    if account_registry != registry {
+        return None;
+    }
+    querier.query_wasm_smart(address, &QueryMsg::Token {}).ok()
 }
 
-#[cfg(feature = "omniflix")]
-fn query_owner(
+/// Whether giving the tokens to the account bound to `bound` would let any of them control itself.
+/// Walks up the holders of the bound tokens for at most `depth` accounts
+pub fn creates_cycle(
     querier: &QuerierWrapper,
-    denom: &str,
-    token_id: &str,
-) -> StdResult<OwnerOfResponse> {
-    let res = OnftQuerier::new(querier).onft(denom.to_string(), token_id.to_string())?;
-    if let Some(onft) = res.onft {
-        Ok(OwnerOfResponse {
-            owner: onft.owner,
-            approvals: vec![],
-        })
-    } else {
-        Err(StdError::generic_err("Token not found"))
+    registry: &str,
+    bound: TokenInfo,
+    tokens: &[TokenInfo],
+    depth: u32,
+) -> StdResult<bool> {
+    let mut bound = bound;
+    for _ in 0..depth.max(1) {
+        if tokens.contains(&bound) {
+            return Ok(true);
+        }
+        let backend = query_collection_backend(querier, registry, &bound.collection)?;
+        let holder = backend.query_owner(querier, &bound.collection, &bound.id)?;
+        bound = match query_account_token(querier, registry, &holder) {
+            Some(next) => next,
+            None => return Ok(false),
+        };
     }
+    Ok(false)
 }
 
-#[cfg(not(feature = "omniflix"))]
-pub fn query_tokens(
+pub fn query_cw20_balance(
     querier: &QuerierWrapper,
-    collection: &str,
-    owner: String,
-    start_after: Option<String>,
-    limit: Option<u32>,
-) -> StdResult<TokensResponse> {
-
-    
-    querier.query_wasm_smart(
-        collection,
-        &Cw721Msg::Tokens {
-            owner,
-            start_after,
-            limit,
-        },
-    )
+    contract: &str,
+    address: String,
+) -> StdResult<Uint128> {
+    let res: Cw20BalanceResponse =
+        querier.query_wasm_smart(contract, &Cw20Msg::Balance { address })?;
+    Ok(res.balance)
 }
 
-
-#[cfg(feature = "omniflix")]
-pub fn query_tokens(
+/// Balances of the account in the given cw20 contracts. Contracts that can't be queried
+/// or have no balance left are skipped
+pub fn query_cw20_balances(
     querier: &QuerierWrapper,
-    denom: &str,
-    owner: String,
-    _start_after: Option<String>,
-    _limit: Option<u32>,
-) -> StdResult<TokensResponse> {
-    let res = OnftQuerier::new(querier).owner_onf_ts(
-        denom.to_string(),
-        owner,
-        None
-    )?;
-    
-    match res.owner {
-        None => return Err(StdError::generic_err("Owner not found")),
-        Some(o) => {
-            return Ok(TokensResponse {
-                tokens: o.id_collections
-                        .into_iter()
-                        .find_map(|id_collection| {
-                            if id_collection.denom_id == denom {
-                                Some(id_collection.onft_ids)
-                            } else {
-                                None
-                            }
-                        })
-                        .unwrap_or_default()
-            });
-        }
-        
-    }
-
+    contracts: Vec<String>,
+    account: &str,
+) -> Vec<Cw20Coin> {
+    contracts
+        .into_iter()
+        .filter_map(|address| {
+            query_cw20_balance(querier, &address, account.to_string())
+                .ok()
+                .filter(|amount| !amount.is_zero())
+                .map(|amount| Cw20Coin { address, amount })
+        })
+        .collect()
 }
 
 
-#[cfg(feature = "omniflix")]
-pub fn transfer_nft_msg(
-    denom_id: &str,
-    token_id: &str,
-    sender: &str,
-    recipient: &str,
-) -> cosmwasm_std::CosmosMsg {
+pub fn query_staking(querier: &QuerierWrapper, address: &str) -> StdResult<StakingResponse> {
+    let delegations = querier.query_all_delegations(address)?;
+    // not every chain allows contracts to query the distribution module
+    let (rewards, total_rewards) = match querier.query_delegation_total_rewards(address) {
+        Ok(res) => (res.rewards, res.total),
+        Err(_) => (vec![], vec![]),
+    };
+    Ok(StakingResponse {
+        delegations,
+        rewards,
+        total_rewards,
+    })
+}
+
 
-    MsgTransferOnft {
-        denom_id: denom_id.to_string(),
-        id: token_id.to_string(),
-        sender: sender.to_string(),
-        recipient: recipient.to_string(),
+pub fn try_updating_known_cw20_on_receive(
+    storage: &mut dyn Storage,
+    contract: String,
+    amount: Uint128,
+) -> StdResult<Response> {
+    // anybody can send cw20s so only a limited number of contracts is tracked
+    let tracked = KNOWN_CW20S.has(storage, contract.as_str())
+        || KNOWN_CW20S
+            .keys(storage, None, None, Order::Ascending)
+            .take(MAX_KNOWN_CW20S)
+            .count()
+            < MAX_KNOWN_CW20S;
+    if tracked {
+        KNOWN_CW20S.save(storage, contract.as_str(), &true)?;
     }
-    .into()
+    Ok(Response::default().add_attributes(vec![
+        ("action", "update_known_cw20_on_receive"),
+        ("contract", contract.as_str()),
+        ("amount", amount.to_string().as_str()),
+        ("tracked", tracked.to_string().as_str()),
+    ]))
 }
 
-
-#[cfg(not(feature = "omniflix"))]
-pub fn transfer_nft_msg(
-    collection: &str,
-    token_id: &str,
-    _sender: &str,
-    recipient: &str,
-) -> cosmwasm_std::CosmosMsg {
-    cosmwasm_std::CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute {
-        contract_addr: collection.to_string(),
-        msg: cosmwasm_std::to_json_binary(&Cw721Msg::TransferNft {
-            recipient: recipient.to_string(),
-            token_id: token_id.to_string(),
-        }).unwrap_or_default(),
+pub fn try_transfering_cw20(contract: String, amount: Uint128, recipient: String) -> StdResult<Response> {
+    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: contract,
+        msg: to_json_binary(&Cw20Msg::Transfer { recipient, amount })?,
         funds: vec![],
-    })
+    });
+    Ok(Response::default()
+        .add_message(msg)
+        .add_attribute("action", "transfer_cw20"))
 }
 
-
+pub fn try_sending_cw20(
+    contract: String,
+    amount: Uint128,
+    recipient: String,
+    msg: Binary,
+) -> StdResult<Response> {
+    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: contract,
+        msg: to_json_binary(&Cw20Msg::Send {
+            contract: recipient,
+            amount,
+            msg,
+        })?,
+        funds: vec![],
+    });
+    Ok(Response::default()
+        .add_message(msg)
+        .add_attribute("action", "send_cw20"))
+}
@@ -0,0 +1,33 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+
+/// Errors of the logic shared by the account contracts
+#[derive(Error, Debug, PartialEq)]
+pub enum AccountError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("IBC transfers through channel {0} are not allowed")]
+    ChannelNotAllowed(String),
+
+    #[error("The token would end up controlling its own account")]
+    CycleDetected {},
+
+    #[error("Token {token_id} of {collection} is locked")]
+    TokenLocked { collection: String, token_id: String },
+
+    #[error("Rejected by the policy: {0}")]
+    PolicyViolation(String),
+
+    #[error("Rejected by plugin {plugin}: {reason}")]
+    PluginRejected { plugin: String, reason: String },
+
+    #[error("{0}")]
+    Generic(String),
+}
+
+pub type AccountResult<T = cosmwasm_std::Response> = Result<T, AccountError>;
@@ -0,0 +1,312 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, BankMsg, Coin, Env, QuerierWrapper, Response, Storage, Timestamp};
+
+use crate::{assert_payouts_policy, state::EXECUTORS, AccountError, AccountResult, ActiontMsg};
+
+
+#[cw_serde]
+pub enum ExecutorPermission {
+    /// Claim the staking rewards from all delegations
+    ClaimRewards,
+    /// Delegate the pending rewards to the validators the account is already delegating to
+    Restake,
+    /// Refresh the tokens the account is aware of
+    UpdateKnownTokens,
+}
+
+#[cw_serde]
+pub struct ExecutorGrant {
+    /// Address of the executor
+    pub executor: String,
+    /// Templates the executor is allowed to run
+    pub permissions: Vec<ExecutorPermission>,
+    /// Time after which the grant can't be used
+    pub expiration: Option<Timestamp>,
+    /// Paid to the executor for every call
+    pub fee: Vec<Coin>,
+    /// Minimal number of seconds between the calls
+    pub interval: u64,
+    /// Time of the last call
+    pub last_call: Option<Timestamp>,
+}
+
+impl ExecutorGrant {
+    pub fn is_expired(&self, now: &Timestamp) -> bool {
+        self.expiration.map(|exp| exp <= *now).unwrap_or_default()
+    }
+
+    pub fn is_cooling_down(&self, now: &Timestamp) -> bool {
+        self.last_call
+            .map(|last| last.plus_seconds(self.interval) > *now)
+            .unwrap_or_default()
+    }
+}
+
+pub type ExecutorsResponse = Vec<ExecutorGrant>;
+
+
+/// Pre-approved action templates that executors can run on behalf of the owner
+#[cw_serde]
+pub enum ExecutorMsg {
+    ClaimRewards {},
+
+    Restake {
+        /// Validator the account is already delegating to
+        validator: String,
+        /// Amount of the tokens to delegate. Can't exceed the pending rewards from the validator
+        amount: Coin,
+    },
+
+    UpdateKnownTokens {
+        collection: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+impl ExecutorMsg {
+    pub fn permission(&self) -> ExecutorPermission {
+        match self {
+            ExecutorMsg::ClaimRewards {} => ExecutorPermission::ClaimRewards,
+            ExecutorMsg::Restake { .. } => ExecutorPermission::Restake,
+            ExecutorMsg::UpdateKnownTokens { .. } => ExecutorPermission::UpdateKnownTokens,
+        }
+    }
+
+    pub fn into_action(self) -> ActiontMsg {
+        match self {
+            ExecutorMsg::ClaimRewards {} => ActiontMsg::ClaimRewards { validators: vec![] },
+            ExecutorMsg::Restake { validator, amount } => ActiontMsg::Delegate { validator, amount },
+            ExecutorMsg::UpdateKnownTokens {
+                collection,
+                start_after,
+                limit,
+            } => ActiontMsg::UpdateKnownTokens {
+                collection,
+                start_after,
+                limit,
+            },
+        }
+    }
+}
+
+
+pub fn try_granting_executor(
+    storage: &mut dyn Storage,
+    executor: String,
+    permissions: Vec<ExecutorPermission>,
+    expiration: Option<Timestamp>,
+    fee: Vec<Coin>,
+    interval: u64,
+) -> AccountResult {
+    ensure!(
+        !permissions.is_empty(),
+        AccountError::Generic("Executor must have at least one permission".into())
+    );
+    let grant = ExecutorGrant {
+        executor,
+        permissions,
+        expiration,
+        fee,
+        interval,
+        last_call: None,
+    };
+    EXECUTORS.save(storage, &grant.executor, &grant)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "grant_executor")
+        .add_attribute("executor", grant.executor))
+}
+
+pub fn try_revoking_executor(storage: &mut dyn Storage, executor: String) -> AccountResult {
+    ensure!(
+        EXECUTORS.has(storage, &executor),
+        AccountError::Generic(format!("{executor} is not an executor"))
+    );
+    EXECUTORS.remove(storage, &executor);
+    Ok(Response::new()
+        .add_attribute("action", "revoke_executor")
+        .add_attribute("executor", executor))
+}
+
+/// Check that the executor may run the template right now and record the call.
+/// Returns the fee owed to the executor
+pub fn use_executor_grant(
+    querier: &QuerierWrapper,
+    storage: &mut dyn Storage,
+    env: &Env,
+    executor: &str,
+    msg: &ExecutorMsg,
+) -> AccountResult<Option<BankMsg>> {
+    let now = env.block.time;
+    let mut grant = EXECUTORS
+        .may_load(storage, executor)?
+        .ok_or(AccountError::Unauthorized("Not an executor".into()))?;
+
+    ensure!(
+        !grant.is_expired(&now) && grant.permissions.contains(&msg.permission()),
+        AccountError::Unauthorized("Not an executor".into())
+    );
+    ensure!(
+        !grant.is_cooling_down(&now),
+        AccountError::Generic("Executor has been called too recently".into())
+    );
+    grant.last_call = Some(now);
+    EXECUTORS.save(storage, executor, &grant)?;
+
+    if let ExecutorMsg::Restake { validator, amount } = msg {
+        let delegation = querier
+            .query_delegation(env.contract.address.as_str(), validator)?
+            .ok_or(AccountError::Generic("Can only restake to the current validators".into()))?;
+        // delegating withdraws the pending rewards first so only they get compounded
+        let rewards = delegation
+            .accumulated_rewards
+            .iter()
+            .find(|c| c.denom == amount.denom)
+            .map(|c| c.amount)
+            .unwrap_or_default();
+        ensure!(
+            amount.amount <= rewards,
+            AccountError::Generic("Can only restake the pending rewards".into())
+        );
+    }
+
+    if grant.fee.is_empty() {
+        return Ok(None);
+    }
+    let fee = BankMsg::Send {
+        to_address: grant.executor,
+        amount: grant.fee,
+    };
+    assert_payouts_policy(storage, env, std::slice::from_ref(&fee))?;
+    Ok(Some(fee))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{state::POLICY, Policy, PolicyRule};
+    use cosmwasm_std::{
+        coin, coins,
+        testing::{mock_dependencies, mock_env},
+        Decimal, Empty, FullDelegation, Validator,
+    };
+
+    fn grant(storage: &mut dyn Storage, permissions: Vec<ExecutorPermission>, fee: Vec<Coin>, interval: u64) {
+        try_granting_executor(storage, "executor".into(), permissions, None, fee, interval).unwrap();
+    }
+
+    #[test]
+    fn executors_are_limited_to_their_permissions() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+        grant(&mut deps.storage, vec![ExecutorPermission::ClaimRewards], vec![], 0);
+
+        let claim = ExecutorMsg::ClaimRewards {};
+        assert_eq!(use_executor_grant(&querier, &mut deps.storage, &env, "executor", &claim).unwrap(), None);
+
+        let restake = ExecutorMsg::Restake {
+            validator: "validator".into(),
+            amount: coin(1, "ustars"),
+        };
+        let err = use_executor_grant(&querier, &mut deps.storage, &env, "executor", &restake);
+        assert!(matches!(err, Err(AccountError::Unauthorized(_))));
+        let err = use_executor_grant(&querier, &mut deps.storage, &env, "stranger", &claim);
+        assert!(matches!(err, Err(AccountError::Unauthorized(_))));
+
+        try_revoking_executor(&mut deps.storage, "executor".into()).unwrap();
+        assert!(use_executor_grant(&querier, &mut deps.storage, &env, "executor", &claim).is_err());
+    }
+
+    #[test]
+    fn executors_respect_the_interval_and_expiration() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+        let claim = ExecutorMsg::ClaimRewards {};
+        grant(&mut deps.storage, vec![ExecutorPermission::ClaimRewards], vec![], 60);
+
+        use_executor_grant(&querier, &mut deps.storage, &env, "executor", &claim).unwrap();
+        assert!(use_executor_grant(&querier, &mut deps.storage, &env, "executor", &claim).is_err());
+        env.block.time = env.block.time.plus_seconds(60);
+        use_executor_grant(&querier, &mut deps.storage, &env, "executor", &claim).unwrap();
+
+        try_granting_executor(
+            &mut deps.storage,
+            "executor".into(),
+            vec![ExecutorPermission::ClaimRewards],
+            Some(env.block.time),
+            vec![],
+            0,
+        )
+        .unwrap();
+        assert!(use_executor_grant(&querier, &mut deps.storage, &env, "executor", &claim).is_err());
+    }
+
+    #[test]
+    fn restaking_is_limited_to_the_rewards_of_current_validators() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let validator = |address: &str| Validator {
+            address: address.into(),
+            commission: Decimal::zero(),
+            max_commission: Decimal::one(),
+            max_change_rate: Decimal::one(),
+        };
+        deps.querier.update_staking(
+            "ustars",
+            &[validator("current"), validator("other")],
+            &[FullDelegation {
+                delegator: env.contract.address.clone(),
+                validator: "current".into(),
+                amount: coin(100, "ustars"),
+                can_redelegate: coin(100, "ustars"),
+                accumulated_rewards: coins(5, "ustars"),
+            }],
+        );
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+        grant(&mut deps.storage, vec![ExecutorPermission::Restake], vec![], 0);
+
+        let restake = |validator: &str, amount: Coin| ExecutorMsg::Restake {
+            validator: validator.into(),
+            amount,
+        };
+        let mut use_grant =
+            |msg: ExecutorMsg| use_executor_grant(&querier, &mut deps.storage, &env, "executor", &msg);
+        assert!(use_grant(restake("current", coin(5, "ustars"))).is_ok());
+        assert!(use_grant(restake("other", coin(1, "ustars"))).is_err());
+        // the rest of the balance stays out of reach
+        assert!(use_grant(restake("current", coin(6, "ustars"))).is_err());
+        assert!(use_grant(restake("current", coin(1, "uatom"))).is_err());
+    }
+
+    #[test]
+    fn fees_are_checked_against_the_policy() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+        let claim = ExecutorMsg::ClaimRewards {};
+        grant(&mut deps.storage, vec![ExecutorPermission::ClaimRewards], coins(10, "ustars"), 0);
+
+        let fee = use_executor_grant(&querier, &mut deps.storage, &env, "executor", &claim).unwrap();
+        assert_eq!(
+            fee,
+            Some(BankMsg::Send {
+                to_address: "executor".into(),
+                amount: coins(10, "ustars"),
+            })
+        );
+
+        let policy = Policy {
+            rules: vec![PolicyRule::MaxAmount {
+                denom: "ustars".into(),
+                amount: 5u128.into(),
+            }],
+        };
+        POLICY.save(&mut deps.storage, &policy).unwrap();
+        let err = use_executor_grant(&querier, &mut deps.storage, &env, "executor", &claim);
+        assert!(matches!(err, Err(AccountError::PolicyViolation(_))));
+    }
+}
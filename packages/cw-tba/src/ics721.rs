@@ -0,0 +1,427 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    ensure, from_json, Binary, DepsMut, Env, MessageInfo, QuerierWrapper, Response, StdResult, Storage,
+};
+use cw_ownable::get_ownership;
+
+use crate::{
+    collection_backend, query_ics721_bridges,
+    state::{forget_token, save_known_token, REGISTRY_ADDRESS, REMOTE_HOLDER, STATUS, TOKEN_INFO},
+    AccountError, AccountResult, ActiontMsg, Status, TokenInfo,
+};
+
+
+/// Data of an ICS-721 packet as relayed by the bridge to the callbacks
+#[cw_serde]
+pub struct NonFungibleTokenPacketData {
+    /// Class id on the sending chain. Prefixed with the port and channel for vouchers
+    pub class_id: String,
+    pub class_uri: Option<String>,
+    pub class_data: Option<Binary>,
+    pub token_ids: Vec<String>,
+    pub token_uris: Option<Vec<String>>,
+    pub token_data: Option<Vec<Binary>>,
+    /// Address of the sender on the sending chain
+    pub sender: String,
+    /// Address of the receiver on the receiving chain
+    pub receiver: String,
+    pub memo: Option<String>,
+}
+
+#[cw_serde]
+pub enum Ics721Status {
+    Success,
+    Failed(String),
+}
+
+/// Callback of the bridge on the sending chain once the packet is acknowledged
+#[cw_serde]
+pub struct Ics721AckCallbackMsg {
+    pub status: Ics721Status,
+    /// Local collection the tokens were sent from
+    pub nft_contract: String,
+    pub original_packet: NonFungibleTokenPacketData,
+    pub msg: Binary,
+}
+
+/// Callback of the bridge on the receiving chain once the tokens are released or minted
+#[cw_serde]
+pub struct Ics721ReceiveCallbackMsg {
+    /// Local collection the tokens were received in
+    pub nft_contract: String,
+    pub original_packet: NonFungibleTokenPacketData,
+    pub msg: Binary,
+}
+
+impl NonFungibleTokenPacketData {
+    /// Whether the packet moved the given token of the local collection
+    pub fn moves(&self, nft_contract: &str, token: &TokenInfo) -> bool {
+        nft_contract == token.collection && self.token_ids.contains(&token.id)
+    }
+}
+
+
+/// Holder of the voucher of the bound token on the chain it was bridged to
+#[cw_serde]
+pub struct RemoteHolder {
+    /// ICS-721 contract escrowing the token
+    pub bridge: String,
+    /// Class id of the token as sent in the packet
+    pub class_id: String,
+    /// Address of the voucher holder on the remote chain
+    pub holder: String,
+}
+
+/// Token a local voucher collection stands for
+#[cw_serde]
+pub struct VoucherOrigin {
+    /// ICS-721 contract that minted the vouchers
+    pub bridge: String,
+    /// Full class id of the vouchers
+    pub class_id: String,
+    /// Port the vouchers were received on
+    pub port: String,
+    /// Channel the vouchers were received on
+    pub channel: String,
+    /// Class id on the previous chain. The collection address if it's the chain of origin
+    pub origin_class_id: String,
+}
+
+/// Split the `{port}/{channel}/{class_id}` prefix of a voucher class id
+pub fn parse_voucher_class_id(class_id: &str) -> Option<(String, String, String)> {
+    let mut parts = class_id.splitn(3, '/');
+    let port = parts.next()?;
+    let channel = parts.next()?;
+    let origin = parts.next()?;
+    if port.is_empty() || !channel.starts_with("channel-") || origin.is_empty() {
+        return None;
+    }
+    Some((port.to_string(), channel.to_string(), origin.to_string()))
+}
+
+
+#[cw_serde]
+enum Ics721QueryMsg {
+    ClassId { contract: String },
+}
+
+/// Origin of the collection if the bridge minted it for the vouchers of a remote class
+pub fn query_voucher_origin(
+    querier: &QuerierWrapper,
+    bridge: &str,
+    collection: &str,
+) -> Option<VoucherOrigin> {
+    let class_id: String = querier
+        .query_wasm_smart::<Option<String>>(
+            bridge,
+            &Ics721QueryMsg::ClassId {
+                contract: collection.to_string(),
+            },
+        )
+        .ok()??;
+    let (port, channel, origin_class_id) = parse_voucher_class_id(&class_id)?;
+    Some(VoucherOrigin {
+        bridge: bridge.to_string(),
+        class_id,
+        port,
+        channel,
+        origin_class_id,
+    })
+}
+
+
+/// Refuse callbacks that don't come from an ICS-721 contract registered in the registry
+pub fn assert_ics721_bridge(querier: &QuerierWrapper, store: &dyn Storage, addr: &str) -> AccountResult<()> {
+    let registry = REGISTRY_ADDRESS.load(store)?;
+    ensure!(
+        query_ics721_bridges(querier, &registry)?.iter().any(|b| b == addr),
+        AccountError::Unauthorized(format!("{addr} is not a registered ICS-721 bridge"))
+    );
+    Ok(())
+}
+
+/// Whether the token is escrowed by an ICS-721 contract registered in the registry
+pub fn is_bridged(querier: &QuerierWrapper, store: &dyn Storage, token: &TokenInfo) -> StdResult<bool> {
+    let backend = collection_backend(querier, store, &token.collection)?;
+    let owner = backend.query_owner(querier, &token.collection, &token.id)?;
+    let registry = REGISTRY_ADDRESS.load(store)?;
+    Ok(query_ics721_bridges(querier, &registry)?.contains(&owner))
+}
+
+pub fn try_handling_ics721_ack(
+    deps: DepsMut,
+    env: &Env,
+    info: MessageInfo,
+    msg: Ics721AckCallbackMsg,
+) -> AccountResult {
+    assert_ics721_bridge(&deps.querier, deps.storage, info.sender.as_str())?;
+    let res = Response::default().add_attribute("action", "ics721_ack_callback");
+    // the bridge gives the tokens of a failed transfer back to the sender
+    if msg.status != Ics721Status::Success {
+        return Ok(res.add_attribute("status", "failed"));
+    }
+    let packet = msg.original_packet;
+    let token = TOKEN_INFO.load(deps.storage)?;
+
+    if packet.moves(&msg.nft_contract, &token) {
+        REMOTE_HOLDER.save(
+            deps.storage,
+            &RemoteHolder {
+                bridge: info.sender.to_string(),
+                class_id: packet.class_id,
+                holder: packet.receiver.clone(),
+            },
+        )?;
+        return Ok(res.add_attribute("remote_holder", packet.receiver));
+    }
+
+    if packet.sender == env.contract.address.as_str() {
+        for id in packet.token_ids.iter() {
+            forget_token(deps.storage, msg.nft_contract.as_str(), id.as_str())?;
+        }
+    }
+    Ok(res)
+}
+
+pub fn try_handling_ics721_receive(
+    deps: DepsMut,
+    env: &Env,
+    info: MessageInfo,
+    msg: Ics721ReceiveCallbackMsg,
+) -> AccountResult {
+    assert_ics721_bridge(&deps.querier, deps.storage, info.sender.as_str())?;
+    let res = Response::default().add_attribute("action", "ics721_receive_callback");
+    let packet = msg.original_packet;
+    let token = TOKEN_INFO.load(deps.storage)?;
+
+    if packet.moves(&msg.nft_contract, &token) {
+        REMOTE_HOLDER.remove(deps.storage);
+        // a new holder takes the account over through the registry
+        let owner = get_ownership(deps.storage)?
+            .owner
+            .ok_or(AccountError::Unauthorized("The account has no owner".into()))?;
+        if owner.as_str() != packet.receiver {
+            STATUS.save(deps.storage, &Status { frozen: true })?;
+        }
+        return Ok(res.add_attribute("receiver", packet.receiver));
+    }
+
+    if packet.receiver == env.contract.address.as_str() {
+        for id in packet.token_ids.iter() {
+            save_known_token(deps.storage, msg.nft_contract.as_str(), id.as_str())?;
+        }
+    }
+    Ok(res)
+}
+
+/// Actions the holder of the voucher sent along a packet to keep control of the account while the token is away.
+/// Only packets relayed by the bridge escrowing the token and sent by the recorded holder are considered
+pub fn remote_holder_actions(
+    querier: &QuerierWrapper,
+    store: &dyn Storage,
+    bridge: &str,
+    msg: &Ics721ReceiveCallbackMsg,
+) -> AccountResult<Option<Vec<ActiontMsg>>> {
+    let remote = match REMOTE_HOLDER.may_load(store)? {
+        Some(remote) => remote,
+        None => return Ok(None),
+    };
+    let packet = &msg.original_packet;
+    if remote.bridge != bridge || packet.sender != remote.holder || msg.msg.is_empty() {
+        return Ok(None);
+    }
+    let token = TOKEN_INFO.load(store)?;
+    // the voucher coming back hands the account over instead
+    if packet.moves(&msg.nft_contract, &token) {
+        return Ok(None);
+    }
+    // vouchers are burned rather than escrowed on the way back to their chain of origin
+    let away = match query_voucher_origin(querier, bridge, &token.collection) {
+        Some(_) => collection_backend(querier, store, &token.collection)?
+            .query_owner(querier, &token.collection, &token.id)
+            .is_err(),
+        None => is_bridged(querier, store, &token)?,
+    };
+    ensure!(
+        away,
+        AccountError::Unauthorized("The token is no longer held by the bridge".into())
+    );
+    Ok(Some(from_json(&msg.msg)?))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{mock_chain, token, RegistryQueryMsg, BRIDGE, COLLECTION, REGISTRY};
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_env, mock_info},
+        to_json_binary, ContractResult, Empty, SystemResult, WasmQuery,
+    };
+
+    fn packet(sender: &str, receiver: &str, token_id: &str) -> NonFungibleTokenPacketData {
+        NonFungibleTokenPacketData {
+            class_id: COLLECTION.into(),
+            class_uri: None,
+            class_data: None,
+            token_ids: vec![token_id.into()],
+            token_uris: None,
+            token_data: None,
+            sender: sender.into(),
+            receiver: receiver.into(),
+            memo: None,
+        }
+    }
+
+    fn remote_holder() -> RemoteHolder {
+        RemoteHolder {
+            bridge: BRIDGE.into(),
+            class_id: COLLECTION.into(),
+            holder: "remote".into(),
+        }
+    }
+
+    fn ack(packet: NonFungibleTokenPacketData) -> Ics721AckCallbackMsg {
+        Ics721AckCallbackMsg {
+            status: Ics721Status::Success,
+            nft_contract: COLLECTION.into(),
+            original_packet: packet,
+            msg: Binary::default(),
+        }
+    }
+
+    #[test]
+    fn escrowed_tokens_are_bridged() {
+        let mut deps = mock_dependencies();
+        mock_chain(&mut deps, "1", &[], &[("1", BRIDGE), ("2", "wallet")]);
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+
+        assert!(is_bridged(&querier, &deps.storage, &token("1")).unwrap());
+        assert!(!is_bridged(&querier, &deps.storage, &token("2")).unwrap());
+        // failing owner queries don't count as not bridged
+        assert!(is_bridged(&querier, &deps.storage, &token("3")).is_err());
+    }
+
+    #[test]
+    fn callbacks_only_come_from_bridges() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        mock_chain(&mut deps, "1", &[], &[("1", BRIDGE)]);
+
+        let msg = ack(packet("owner", "remote", "1"));
+        let err = try_handling_ics721_ack(deps.as_mut(), &env, mock_info("stranger", &[]), msg.clone());
+        assert!(matches!(err, Err(AccountError::Unauthorized(_))));
+        assert!(REMOTE_HOLDER.may_load(&deps.storage).unwrap().is_none());
+
+        try_handling_ics721_ack(deps.as_mut(), &env, mock_info(BRIDGE, &[]), msg).unwrap();
+        assert_eq!(REMOTE_HOLDER.load(&deps.storage).unwrap().holder, "remote");
+    }
+
+    #[test]
+    fn returning_token_freezes_for_a_new_holder() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        mock_chain(&mut deps, "1", &[], &[]);
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some("owner")).unwrap();
+        let receive = |receiver: &str| Ics721ReceiveCallbackMsg {
+            nft_contract: COLLECTION.into(),
+            original_packet: packet("remote", receiver, "1"),
+            msg: Binary::default(),
+        };
+
+        try_handling_ics721_receive(deps.as_mut(), &env, mock_info(BRIDGE, &[]), receive("owner")).unwrap();
+        assert!(STATUS.may_load(&deps.storage).unwrap().is_none());
+
+        try_handling_ics721_receive(deps.as_mut(), &env, mock_info(BRIDGE, &[]), receive("buyer")).unwrap();
+        assert!(STATUS.load(&deps.storage).unwrap().frozen);
+        assert!(REMOTE_HOLDER.may_load(&deps.storage).unwrap().is_none());
+    }
+
+    #[test]
+    fn bridged_tokens_of_the_account_are_forgotten() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        mock_chain(&mut deps, "1", &[], &[]);
+        save_known_token(&mut deps.storage, COLLECTION, "5").unwrap();
+
+        let msg = ack(packet(env.contract.address.as_str(), "remote", "5"));
+        try_handling_ics721_ack(deps.as_mut(), &env, mock_info(BRIDGE, &[]), msg).unwrap();
+        assert!(!crate::state::KNOWN_TOKENS.has(&deps.storage, (COLLECTION, "5")));
+    }
+
+    #[test]
+    fn only_the_voucher_holder_sends_actions() {
+        let mut deps = mock_dependencies();
+        mock_chain(&mut deps, "1", &[], &[("1", BRIDGE)]);
+        REMOTE_HOLDER.save(&mut deps.storage, &remote_holder()).unwrap();
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+
+        let actions = vec![ActiontMsg::Execute { msgs: vec![] }];
+        let receive = |sender: &str, token_id: &str| Ics721ReceiveCallbackMsg {
+            nft_contract: COLLECTION.into(),
+            original_packet: packet(sender, "anyone", token_id),
+            msg: to_json_binary(&actions).unwrap(),
+        };
+
+        let sent = remote_holder_actions(&querier, &deps.storage, BRIDGE, &receive("remote", "2")).unwrap();
+        assert_eq!(sent, Some(actions.clone()));
+        // other senders, relayers and the voucher coming back don't carry actions
+        assert_eq!(remote_holder_actions(&querier, &deps.storage, BRIDGE, &receive("stranger", "2")).unwrap(), None);
+        assert_eq!(remote_holder_actions(&querier, &deps.storage, "relayer", &receive("remote", "2")).unwrap(), None);
+        assert_eq!(remote_holder_actions(&querier, &deps.storage, BRIDGE, &receive("remote", "1")).unwrap(), None);
+    }
+
+    #[test]
+    fn stale_remote_holders_are_refused() {
+        let mut deps = mock_dependencies();
+        mock_chain(&mut deps, "1", &[], &[("1", "wallet")]);
+        REMOTE_HOLDER.save(&mut deps.storage, &remote_holder()).unwrap();
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+
+        let msg = Ics721ReceiveCallbackMsg {
+            nft_contract: COLLECTION.into(),
+            original_packet: packet("remote", "anyone", "2"),
+            msg: to_json_binary(&Vec::<ActiontMsg>::new()).unwrap(),
+        };
+        let err = remote_holder_actions(&querier, &deps.storage, BRIDGE, &msg);
+        assert!(matches!(err, Err(AccountError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn vouchers_sent_home_keep_the_holder_in_control() {
+        let mut deps = mock_dependencies();
+        mock_chain(&mut deps, "1", &[], &[]);
+        REMOTE_HOLDER.save(&mut deps.storage, &remote_holder()).unwrap();
+        // the bound token is a voucher the bridge burned when it went back
+        deps.querier.update_wasm(|query| {
+            let WasmQuery::Smart { contract_addr, msg } = query else {
+                unimplemented!()
+            };
+            let res = match contract_addr.as_str() {
+                REGISTRY => match from_json(msg).unwrap() {
+                    RegistryQueryMsg::CollectionBackend { .. } => {
+                        to_json_binary(&crate::NftBackend::Cw721)
+                    }
+                    _ => unimplemented!(),
+                },
+                BRIDGE => match from_json(msg).unwrap() {
+                    Ics721QueryMsg::ClassId { .. } => {
+                        to_json_binary(&Some("wasm.bridge/channel-0/origin"))
+                    }
+                },
+                _ => return SystemResult::Ok(ContractResult::Err("Token not found".into())),
+            };
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        });
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+
+        let actions = vec![ActiontMsg::Execute { msgs: vec![] }];
+        let msg = Ics721ReceiveCallbackMsg {
+            nft_contract: "other".into(),
+            original_packet: packet("remote", "anyone", "2"),
+            msg: to_json_binary(&actions).unwrap(),
+        };
+        let sent = remote_holder_actions(&querier, &deps.storage, BRIDGE, &msg).unwrap();
+        assert_eq!(sent, Some(actions));
+    }
+}
@@ -1,10 +1,48 @@
 mod account;
+mod backend;
 mod common;
+mod error;
+mod executors;
+mod ics721;
+mod metadata;
+mod nested;
+mod payments;
+mod plugins;
+mod policy;
+mod receive;
 mod registry;
+mod staking;
+mod sync;
+mod timelock;
+mod token_approvals;
+mod transfers;
+mod two_factor;
+
+#[cfg(test)]
+mod testing;
+
+/// Storage shared by the account contracts and the reads their queries are answered with
+pub mod state;
 
 pub use account::*;
+pub use backend::*;
 pub use common::*;
+pub use error::*;
+pub use executors::*;
+pub use ics721::*;
+pub use metadata::*;
+pub use nested::*;
+pub use payments::*;
+pub use plugins::*;
+pub use policy::*;
+pub use receive::*;
 pub use registry::*;
+pub use staking::*;
+pub use sync::*;
+pub use timelock::*;
+pub use token_approvals::*;
+pub use transfers::*;
+pub use two_factor::*;
 
 // re-exports for same version usage
 pub use cosmwasm_schema;
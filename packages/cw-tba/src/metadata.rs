@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Binary, QuerierWrapper, StdResult};
+
+use crate::{query_collection_backend, NftBackend, TokenInfo};
+
+
+#[cw_serde]
+pub struct TokenMetadata {
+    /// URI pointing to the off-chain metadata of the token
+    pub token_uri: Option<String>,
+    /// JSON encoded on-chain extension of the token
+    pub extension: Option<Binary>,
+    /// Name of the collection
+    pub collection_name: Option<String>,
+    /// Symbol of the collection
+    pub collection_symbol: Option<String>,
+}
+
+#[cw_serde]
+pub struct KnownToken {
+    /// Contract address of the collection
+    pub collection: String,
+    /// Token id
+    pub id: String,
+    /// Only filled when requested with `with_metadata`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<TokenMetadata>,
+}
+
+impl From<TokenInfo> for KnownToken {
+    fn from(token: TokenInfo) -> Self {
+        KnownToken {
+            collection: token.collection,
+            id: token.id,
+            metadata: None,
+        }
+    }
+}
+
+
+/// Turn the tokens into the response format and attach their metadata if requested.
+/// Collections are queried once per call and tokens that can't be queried are left
+/// without metadata
+pub fn with_token_metadata(
+    querier: &QuerierWrapper,
+    registry: &str,
+    tokens: Vec<TokenInfo>,
+    with_metadata: Option<bool>,
+) -> StdResult<Vec<KnownToken>> {
+    if !with_metadata.unwrap_or(false) {
+        return Ok(tokens.into_iter().map(KnownToken::from).collect());
+    }
+
+    let mut collections: BTreeMap<String, (NftBackend, Option<(String, String)>)> = BTreeMap::new();
+    let mut known = Vec::with_capacity(tokens.len());
+
+    for token in tokens {
+        if !collections.contains_key(&token.collection) {
+            let backend = query_collection_backend(querier, registry, &token.collection)?;
+            let info = backend.query_collection_metadata(querier, &token.collection).ok();
+            collections.insert(token.collection.clone(), (backend, info));
+        }
+        let (backend, info) = collections[&token.collection].clone();
+
+        let metadata = backend
+            .query_token_metadata(querier, &token.collection, &token.id)
+            .ok()
+            .map(|(token_uri, extension)| TokenMetadata {
+                token_uri,
+                extension,
+                collection_name: info.as_ref().map(|(name, _)| name.clone()),
+                collection_symbol: info.map(|(_, symbol)| symbol),
+            });
+
+        known.push(KnownToken {
+            collection: token.collection,
+            id: token.id,
+            metadata,
+        });
+    }
+    Ok(known)
+}
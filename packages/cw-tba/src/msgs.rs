@@ -1,6 +1,6 @@
 use crate::TokenAccountPayload;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Binary;
+use cosmwasm_std::{Binary, Uint128};
 use saa_wasm::UpdateOperation;
 
 use smart_account_auth::{Expiration, VerifiedData};
@@ -39,6 +39,36 @@ pub enum Cw721Msg {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+
+    Approve {
+        spender: String,
+        token_id: String,
+        expires: Option<Expiration>,
+    },
+
+    Revoke {
+        spender: String,
+        token_id: String,
+    },
+
+    ApproveAll {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+
+    RevokeAll {
+        operator: String,
+    },
+
+    Burn {
+        token_id: String,
+    },
+
+    NftInfo {
+        token_id: String,
+    },
+
+    ContractInfo {},
 }
 
 #[cw_serde]
@@ -49,6 +79,37 @@ pub struct Cw721ReceiveMsg {
 }
 
 
+#[cw_serde]
+pub enum Cw20Msg {
+    Transfer {
+        recipient: String,
+        amount: Uint128,
+    },
+
+    Send {
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+
+    Balance {
+        address: String,
+    },
+}
+
+#[cw_serde]
+pub struct Cw20ReceiveMsg {
+    pub sender: String,
+    pub amount: Uint128,
+    pub msg: Binary,
+}
+
+#[cw_serde]
+pub struct Cw20BalanceResponse {
+    pub balance: Uint128,
+}
+
+
 #[cw_serde]
 pub struct OwnerOfResponse {
     pub owner: String,
@@ -60,5 +121,19 @@ pub struct TokensResponse {
     pub tokens: Vec<String>,
 }
 
+/// Extension of the token is kept as an arbitrary value since every collection
+/// defines its own
+#[derive(serde::Deserialize)]
+pub struct NftInfoResponse {
+    pub token_uri: Option<String>,
+    pub extension: Option<serde_cw_value::Value>,
+}
+
+#[cw_serde]
+pub struct ContractInfoResponse {
+    pub name: String,
+    pub symbol: String,
+}
+
 
 pub type UpdateAccountOp = UpdateOperation<VerifiedData>;
\ No newline at end of file
@@ -0,0 +1,179 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, to_json_binary, CosmosMsg, Env, QuerierWrapper, Response, StdResult, Storage, WasmMsg};
+use cw83::AccountResponse;
+
+use crate::{
+    collection_backend, creates_cycle, query_account_token, query_collection_backend, query_cycle_depth,
+    state::{REGISTRY_ADDRESS, TOKEN_INFO},
+    verify_nft_ownership, AccountError, AccountResult, ActiontMsg, ExecuteMsg, QueryMsg, TokenInfo,
+};
+
+
+/// Default number of levels returned by the account tree query
+pub const DEFAULT_TREE_DEPTH: u32 = 1;
+/// Maximum number of levels returned by the account tree query
+pub const MAX_TREE_DEPTH: u32 = 3;
+
+
+#[cw_serde]
+pub struct AccountTreeNode {
+    /// Address of the account
+    pub address: String,
+    /// Token the account is bound to
+    pub token: TokenInfo,
+    /// Accounts bound to the tokens held by this account
+    pub children: Vec<AccountTreeNode>,
+}
+
+
+#[cw_serde]
+enum RegistryQueryMsg {
+    AccountInfo(TokenInfo),
+}
+
+/// Address of the account created by the registry for the given token
+pub fn query_child_account(
+    querier: &QuerierWrapper,
+    registry: &str,
+    token: &TokenInfo,
+) -> StdResult<String> {
+    let res: AccountResponse<TokenInfo> =
+        querier.query_wasm_smart(registry, &RegistryQueryMsg::AccountInfo(token.clone()))?;
+    Ok(res.address)
+}
+
+/// Message forwarding the actions to a child account
+pub fn child_execute_msg(child: &str, actions: Vec<ActiontMsg>) -> StdResult<CosmosMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: child.to_string(),
+        msg: to_json_binary(&ExecuteMsg::ExecuteNative { msgs: actions })?,
+        funds: vec![],
+    }
+    .into())
+}
+
+/// Children of an account among the given tokens. Tokens without an account or
+/// no longer held by the parent are skipped
+pub fn query_child_nodes(
+    querier: &QuerierWrapper,
+    registry: &str,
+    parent: &str,
+    tokens: Vec<TokenInfo>,
+    depth: u32,
+) -> StdResult<Vec<AccountTreeNode>> {
+    let mut children = vec![];
+
+    for token in tokens {
+        let address = match query_child_account(querier, registry, &token) {
+            Ok(address) => address,
+            Err(_) => continue,
+        };
+        let backend = query_collection_backend(querier, registry, &token.collection)?;
+        if verify_nft_ownership(querier, &backend, parent, token.clone()).is_err() {
+            continue;
+        }
+        let node = if depth > 1 {
+            querier.query_wasm_smart(
+                &address,
+                &QueryMsg::AccountTree {
+                    depth: Some(depth - 1),
+                },
+            )?
+        } else {
+            AccountTreeNode {
+                address,
+                token,
+                children: vec![],
+            }
+        };
+        children.push(node);
+    }
+    Ok(children)
+}
+
+
+/// Refuse to move tokens into an account that they would control, directly or through a chain of accounts
+pub fn assert_no_cycle(
+    querier: &QuerierWrapper,
+    store: &dyn Storage,
+    env: &Env,
+    holder: &str,
+    tokens: &[TokenInfo],
+) -> AccountResult<()> {
+    let registry = REGISTRY_ADDRESS.load(store)?;
+    let bound = if holder == env.contract.address.as_str() {
+        Some(TOKEN_INFO.load(store)?)
+    } else {
+        query_account_token(querier, &registry, holder)
+    };
+    if let Some(bound) = bound {
+        let depth = query_cycle_depth(querier, &registry)?;
+        ensure!(
+            !creates_cycle(querier, &registry, bound, tokens, depth)?,
+            AccountError::CycleDetected {}
+        );
+    }
+    Ok(())
+}
+
+
+pub fn try_executing_on_child(
+    querier: &QuerierWrapper,
+    storage: &dyn Storage,
+    env: &Env,
+    token_info: TokenInfo,
+    actions: Vec<ActiontMsg>,
+) -> AccountResult {
+    ensure!(
+        token_info != TOKEN_INFO.load(storage)?,
+        AccountError::Generic("Can't execute on the account itself".into())
+    );
+    let registry = REGISTRY_ADDRESS.load(storage)?;
+    let child = query_child_account(querier, &registry, &token_info)?;
+    let backend = collection_backend(querier, storage, &token_info.collection)?;
+    verify_nft_ownership(querier, &backend, env.contract.address.as_str(), token_info)?;
+
+    Ok(Response::new()
+        .add_message(child_execute_msg(&child, actions)?)
+        .add_attribute("action", "execute_on_child")
+        .add_attribute("child", child))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{mock_chain, token};
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_env},
+        Empty,
+    };
+
+    #[test]
+    fn tokens_cant_end_up_controlling_themselves() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        // the account is bound to token 1 and holds token 2 which is bound to the child account
+        mock_chain(&mut deps, "1", &[("child", "2")], &[("1", "wallet"), ("2", env.contract.address.as_str())]);
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+
+        let err = assert_no_cycle(&querier, &deps.storage, &env, "child", &[token("1")]);
+        assert!(matches!(err, Err(AccountError::CycleDetected {})));
+        let err = assert_no_cycle(&querier, &deps.storage, &env, env.contract.address.as_str(), &[token("1")]);
+        assert!(matches!(err, Err(AccountError::CycleDetected {})));
+
+        assert!(assert_no_cycle(&querier, &deps.storage, &env, "wallet", &[token("1")]).is_ok());
+        assert!(assert_no_cycle(&querier, &deps.storage, &env, "child", &[token("3")]).is_ok());
+    }
+
+    #[test]
+    fn failing_owner_queries_are_errors() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        // nobody is known to hold token 2
+        mock_chain(&mut deps, "1", &[("child", "2")], &[("1", "wallet")]);
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+
+        let err = assert_no_cycle(&querier, &deps.storage, &env, "child", &[token("3")]);
+        assert!(matches!(err, Err(AccountError::Std(_))));
+    }
+}
@@ -0,0 +1,339 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, BankMsg, Coin, Env, Order, Response, StdError, StdResult, Storage, Timestamp};
+
+use crate::{
+    assert_payouts_policy,
+    state::{PAYMENTS, PAYMENT_COUNT, PULLS},
+    AccountError, AccountResult,
+};
+
+
+#[cw_serde]
+pub struct ScheduledPayment {
+    /// Id of the payment
+    pub id: u64,
+    /// Address receiving the payments
+    pub recipient: String,
+    /// Amount paid every interval
+    pub amount: Vec<Coin>,
+    /// Seconds between the payments
+    pub interval: u64,
+    /// Time the next payment is due
+    pub next_due: Timestamp,
+    /// Time after which no more payments are made
+    pub end: Option<Timestamp>,
+}
+
+impl ScheduledPayment {
+    pub fn is_due(&self, now: &Timestamp) -> bool {
+        self.next_due <= *now && !self.is_over()
+    }
+
+    /// Whether the next payment would be past the end date
+    pub fn is_over(&self) -> bool {
+        self.end.map(|end| self.next_due > end).unwrap_or_default()
+    }
+
+    /// Move to the next interval. Payments missed in the past can be cranked one by one
+    pub fn advance(&mut self) {
+        self.next_due = self.next_due.plus_seconds(self.interval);
+    }
+}
+
+
+#[cw_serde]
+pub struct PullAuthorization {
+    /// Address allowed to pull the funds
+    pub merchant: String,
+    /// Maximum amount that can be pulled within a period
+    pub cap: Vec<Coin>,
+    /// Length of a period in seconds
+    pub period: u64,
+    /// Start of the current period
+    pub period_start: Timestamp,
+    /// Amount pulled within the current period
+    pub spent: Vec<Coin>,
+    /// Time after which the authorization can't be used
+    pub end: Option<Timestamp>,
+}
+
+impl PullAuthorization {
+    pub fn is_expired(&self, now: &Timestamp) -> bool {
+        self.end.map(|end| end < *now).unwrap_or_default()
+    }
+
+    /// Record a pull of the given amount, starting a new period if the current one is over
+    pub fn pull(&mut self, now: &Timestamp, amount: &[Coin]) -> StdResult<()> {
+        if self.period_start.plus_seconds(self.period) <= *now {
+            self.period_start = *now;
+            self.spent = vec![];
+        }
+        for coin in amount {
+            let spent = match self.spent.iter_mut().find(|c| c.denom == coin.denom) {
+                Some(spent) => {
+                    spent.amount = spent.amount.checked_add(coin.amount)?;
+                    spent.amount
+                }
+                None => {
+                    self.spent.push(coin.clone());
+                    coin.amount
+                }
+            };
+            let cap = self
+                .cap
+                .iter()
+                .find(|c| c.denom == coin.denom)
+                .map(|c| c.amount)
+                .unwrap_or_default();
+            if spent > cap {
+                return Err(StdError::generic_err(format!(
+                    "Pull cap for {} is exceeded",
+                    coin.denom
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+
+#[cw_serde]
+pub enum PaymentMsg {
+    /// Make the scheduled payments that are due. All of them if no ids are given.
+    /// Callable by anybody
+    Crank {
+        ids: Vec<u64>,
+    },
+
+    /// Merchant only method to pull funds within the authorized cap
+    Pull {
+        /// Amount to pull
+        amount: Vec<Coin>,
+        /// Address receiving the funds. Defaults to the merchant
+        recipient: Option<String>,
+    },
+}
+
+pub type ScheduledPaymentsResponse = Vec<ScheduledPayment>;
+
+pub type PullAuthorizationsResponse = Vec<PullAuthorization>;
+
+
+pub fn try_scheduling_payment(
+    storage: &mut dyn Storage,
+    env: &Env,
+    recipient: String,
+    amount: Vec<Coin>,
+    interval: u64,
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+) -> AccountResult {
+    ensure!(
+        interval > 0 && !amount.is_empty(),
+        AccountError::Generic("Payment must have a positive interval and amount".into())
+    );
+    let id = PAYMENT_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    let payment = ScheduledPayment {
+        id,
+        recipient,
+        amount,
+        interval,
+        next_due: start.unwrap_or(env.block.time),
+        end,
+    };
+    PAYMENTS.save(storage, id, &payment)?;
+    PAYMENT_COUNT.save(storage, &id)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "schedule_payment")
+        .add_attribute("id", id.to_string())
+        .add_attribute("recipient", payment.recipient))
+}
+
+pub fn try_cancelling_payment(storage: &mut dyn Storage, id: u64) -> AccountResult {
+    ensure!(
+        PAYMENTS.has(storage, id),
+        AccountError::Generic(format!("No scheduled payment with id {id}"))
+    );
+    PAYMENTS.remove(storage, id);
+    Ok(Response::new()
+        .add_attribute("action", "cancel_payment")
+        .add_attribute("id", id.to_string()))
+}
+
+pub fn try_authorizing_pull(
+    storage: &mut dyn Storage,
+    env: &Env,
+    merchant: String,
+    cap: Vec<Coin>,
+    period: u64,
+    end: Option<Timestamp>,
+) -> AccountResult {
+    ensure!(
+        period > 0,
+        AccountError::Generic("Period must be positive".into())
+    );
+    let auth = PullAuthorization {
+        merchant,
+        cap,
+        period,
+        period_start: env.block.time,
+        spent: vec![],
+        end,
+    };
+    PULLS.save(storage, &auth.merchant, &auth)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "authorize_pull")
+        .add_attribute("merchant", auth.merchant))
+}
+
+pub fn try_revoking_pull(storage: &mut dyn Storage, merchant: String) -> AccountResult {
+    ensure!(
+        PULLS.has(storage, &merchant),
+        AccountError::Generic(format!("No pull authorization for {merchant}"))
+    );
+    PULLS.remove(storage, &merchant);
+    Ok(Response::new()
+        .add_attribute("action", "revoke_pull")
+        .add_attribute("merchant", merchant))
+}
+
+pub fn try_cranking_payments(storage: &mut dyn Storage, env: &Env, ids: Vec<u64>) -> AccountResult {
+    let payments = if ids.is_empty() {
+        PAYMENTS
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, payment)| payment))
+            .collect::<StdResult<Vec<ScheduledPayment>>>()?
+    } else {
+        ids.into_iter()
+            .map(|id| PAYMENTS.load(storage, id))
+            .collect::<StdResult<Vec<ScheduledPayment>>>()?
+    };
+
+    let mut msgs = vec![];
+    for mut payment in payments {
+        if !payment.is_due(&env.block.time) {
+            continue;
+        }
+        msgs.push(BankMsg::Send {
+            to_address: payment.recipient.clone(),
+            amount: payment.amount.clone(),
+        });
+        payment.advance();
+        if payment.is_over() {
+            PAYMENTS.remove(storage, payment.id);
+        } else {
+            PAYMENTS.save(storage, payment.id, &payment)?;
+        }
+    }
+    ensure!(
+        !msgs.is_empty(),
+        AccountError::Generic("No payments are due".into())
+    );
+    assert_payouts_policy(storage, env, &msgs)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "crank_payments")
+        .add_attribute("payments", msgs.len().to_string())
+        .add_messages(msgs))
+}
+
+pub fn try_pulling(
+    storage: &mut dyn Storage,
+    env: &Env,
+    merchant: String,
+    amount: Vec<Coin>,
+    recipient: Option<String>,
+) -> AccountResult {
+    let mut auth = PULLS
+        .may_load(storage, &merchant)?
+        .ok_or(AccountError::Unauthorized("No pull authorization".into()))?;
+    ensure!(
+        !auth.is_expired(&env.block.time),
+        AccountError::Generic("Pull authorization has expired".into())
+    );
+    auth.pull(&env.block.time, &amount)?;
+    PULLS.save(storage, &merchant, &auth)?;
+
+    let payout = BankMsg::Send {
+        to_address: recipient.unwrap_or(merchant.clone()),
+        amount,
+    };
+    assert_payouts_policy(storage, env, std::slice::from_ref(&payout))?;
+
+    Ok(Response::new()
+        .add_message(payout)
+        .add_attribute("action", "pull")
+        .add_attribute("merchant", merchant))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{state::POLICY, Policy, PolicyRule};
+    use cosmwasm_std::{coins, testing::{mock_dependencies, mock_env}};
+
+    #[test]
+    fn payments_are_made_once_per_interval() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let end = Some(env.block.time.plus_seconds(60));
+        try_scheduling_payment(&mut deps.storage, &env, "alice".into(), coins(10, "ustars"), 60, None, end).unwrap();
+
+        let res = try_cranking_payments(&mut deps.storage, &env, vec![]).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert!(try_cranking_payments(&mut deps.storage, &env, vec![]).is_err());
+
+        // the last installment is due on the end date and removes the payment
+        env.block.time = env.block.time.plus_seconds(60);
+        try_cranking_payments(&mut deps.storage, &env, vec![1]).unwrap();
+        assert!(!PAYMENTS.has(&deps.storage, 1));
+    }
+
+    #[test]
+    fn payouts_must_pass_the_policy() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        try_scheduling_payment(&mut deps.storage, &env, "bob".into(), coins(10, "ustars"), 60, None, None).unwrap();
+        try_authorizing_pull(&mut deps.storage, &env, "bob".into(), coins(10, "ustars"), 60, None).unwrap();
+
+        let policy = Policy {
+            rules: vec![PolicyRule::AllowedRecipients {
+                addresses: vec!["alice".into()],
+            }],
+        };
+        POLICY.save(&mut deps.storage, &policy).unwrap();
+        let err = try_cranking_payments(&mut deps.storage, &env, vec![]);
+        assert!(matches!(err, Err(AccountError::PolicyViolation(_))));
+        let err = try_pulling(&mut deps.storage, &env, "bob".into(), coins(1, "ustars"), None);
+        assert!(matches!(err, Err(AccountError::PolicyViolation(_))));
+        assert!(try_pulling(&mut deps.storage, &env, "bob".into(), coins(1, "ustars"), Some("alice".into())).is_ok());
+    }
+
+    #[test]
+    fn pulls_are_capped_per_period() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let end = Some(env.block.time.plus_seconds(100));
+        try_authorizing_pull(&mut deps.storage, &env, "merchant".into(), coins(10, "ustars"), 60, end).unwrap();
+
+        let pull = |storage: &mut dyn Storage, env: &Env, amount: u128| {
+            try_pulling(storage, env, "merchant".into(), coins(amount, "ustars"), None)
+        };
+        pull(&mut deps.storage, &env, 6).unwrap();
+        assert!(pull(&mut deps.storage, &env, 5).is_err());
+        assert!(try_pulling(&mut deps.storage, &env, "merchant".into(), coins(1, "uatom"), None).is_err());
+        assert!(matches!(
+            try_pulling(&mut deps.storage, &env, "stranger".into(), coins(1, "ustars"), None),
+            Err(AccountError::Unauthorized(_))
+        ));
+
+        env.block.time = env.block.time.plus_seconds(60);
+        pull(&mut deps.storage, &env, 10).unwrap();
+
+        env.block.time = env.block.time.plus_seconds(60);
+        assert!(pull(&mut deps.storage, &env, 1).is_err());
+    }
+}
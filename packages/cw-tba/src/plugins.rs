@@ -0,0 +1,335 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    ensure, to_json_binary, CosmosMsg, Deps, Env, Order, QuerierWrapper, Response, StdResult, Storage, WasmMsg,
+};
+use saa_schema::QueryResponses;
+
+use crate::{
+    state::{PLUGINS, QUEUE},
+    AccountError, AccountResult, ActiontMsg,
+};
+
+
+/// Maximum number of plugins an account can have installed
+pub const MAX_PLUGINS: usize = 8;
+
+
+#[cw_serde]
+pub enum PluginKind {
+    /// Queried before the actions are executed and can reject them
+    Validator,
+    /// Called after the actions have been executed
+    Hook,
+}
+
+#[cw_serde]
+pub struct PluginInfo {
+    /// Address of the plugin contract
+    pub address: String,
+    /// Whether the plugin is called before or after the actions
+    pub kind: PluginKind,
+}
+
+pub type PluginsResponse = Vec<PluginInfo>;
+
+
+/// Query every validator plugin must support
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum ValidatorQueryMsg {
+    /// Check whether the given actions can be executed by the account
+    #[returns(ValidationResponse)]
+    ValidateActions {
+        /// Address of the account
+        account: String,
+        /// Address of the sender who requested the actions
+        sender: String,
+        /// Actions about to be executed
+        actions: Vec<ActiontMsg>,
+    },
+}
+
+#[cw_serde]
+pub struct ValidationResponse {
+    /// Whether the actions can be executed
+    pub allowed: bool,
+    /// Optional explanation of the rejection
+    pub reason: Option<String>,
+}
+
+
+/// Message every hook plugin must accept
+#[cw_serde]
+pub enum HookExecuteMsg {
+    /// Called after the actions have been executed by the account
+    AfterActions {
+        /// Address of the account
+        account: String,
+        /// Address of the sender who requested the actions
+        sender: String,
+        /// Actions that have been executed
+        actions: Vec<ActiontMsg>,
+    },
+}
+
+
+pub fn query_plugin_validation(
+    querier: &QuerierWrapper,
+    plugin: &str,
+    account: &str,
+    sender: &str,
+    actions: &[ActiontMsg],
+) -> StdResult<ValidationResponse> {
+    querier.query_wasm_smart(
+        plugin,
+        &ValidatorQueryMsg::ValidateActions {
+            account: account.to_string(),
+            sender: sender.to_string(),
+            actions: actions.to_vec(),
+        },
+    )
+}
+
+pub fn plugin_hook_msg(
+    plugin: &str,
+    account: &str,
+    sender: &str,
+    actions: &[ActiontMsg],
+) -> StdResult<CosmosMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: plugin.to_string(),
+        msg: to_json_binary(&HookExecuteMsg::AfterActions {
+            account: account.to_string(),
+            sender: sender.to_string(),
+            actions: actions.to_vec(),
+        })?,
+        funds: vec![],
+    }
+    .into())
+}
+
+
+/// Whether the actions only remove plugins, right away or by executing removals delayed by the timelock
+fn only_uninstalls(store: &dyn Storage, actions: &[ActiontMsg]) -> bool {
+    let is_uninstall = |action: &ActiontMsg| matches!(action, ActiontMsg::UninstallPlugin { .. });
+    actions.iter().all(|action| match action {
+        ActiontMsg::ExecuteQueued { id } => QUEUE
+            .may_load(store, *id)
+            .ok()
+            .flatten()
+            .is_some_and(|queued| is_uninstall(&queued.action)),
+        action => is_uninstall(action),
+    })
+}
+
+
+/// Ask every validator plugin whether the actions can be executed
+pub fn assert_plugins_validation(
+    deps: Deps,
+    env: &Env,
+    sender: &str,
+    actions: &[ActiontMsg],
+) -> AccountResult<()> {
+    // removing a plugin must stay possible even if it rejects everything
+    if only_uninstalls(deps.storage, actions) {
+        return Ok(());
+    }
+    let validators = PLUGINS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|item| item.ok())
+        .filter(|(_, kind)| *kind == PluginKind::Validator)
+        .map(|(address, _)| address)
+        .collect::<Vec<String>>();
+
+    for plugin in validators {
+        let res = query_plugin_validation(
+            &deps.querier,
+            &plugin,
+            env.contract.address.as_str(),
+            sender,
+            actions,
+        )?;
+        ensure!(
+            res.allowed,
+            AccountError::PluginRejected {
+                plugin,
+                reason: res.reason.unwrap_or_default()
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Messages calling every hook plugin after the actions
+pub fn plugin_hook_msgs(
+    store: &dyn Storage,
+    env: &Env,
+    sender: &str,
+    actions: &[ActiontMsg],
+) -> StdResult<Vec<CosmosMsg>> {
+    // a failing hook must not be able to keep itself installed
+    if only_uninstalls(store, actions) {
+        return Ok(vec![]);
+    }
+    PLUGINS
+        .range(store, None, None, Order::Ascending)
+        .filter_map(|item| item.ok())
+        .filter(|(_, kind)| *kind == PluginKind::Hook)
+        .map(|(address, _)| plugin_hook_msg(&address, env.contract.address.as_str(), sender, actions))
+        .collect()
+}
+
+pub fn try_installing_plugin(
+    querier: &QuerierWrapper,
+    storage: &mut dyn Storage,
+    env: &Env,
+    address: String,
+    kind: PluginKind,
+) -> AccountResult {
+    ensure!(
+        address != env.contract.address.as_str(),
+        AccountError::Generic("Account can't be its own plugin".into())
+    );
+    querier.query_wasm_contract_info(&address)?;
+
+    if !PLUGINS.has(storage, &address) {
+        let count = PLUGINS.keys(storage, None, None, Order::Ascending).count();
+        ensure!(
+            count < MAX_PLUGINS,
+            AccountError::Generic(format!("Can't have more than {MAX_PLUGINS} plugins"))
+        );
+    }
+    PLUGINS.save(storage, &address, &kind)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "install_plugin")
+        .add_attribute("plugin", address))
+}
+
+pub fn try_uninstalling_plugin(storage: &mut dyn Storage, address: String) -> AccountResult {
+    ensure!(
+        PLUGINS.has(storage, &address),
+        AccountError::Generic(format!("{address} is not a plugin"))
+    );
+    PLUGINS.remove(storage, &address);
+    Ok(Response::new()
+        .add_attribute("action", "uninstall_plugin")
+        .add_attribute("plugin", address))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{try_queueing_action, TimelockConfig};
+    use cosmwasm_std::{
+        from_json,
+        testing::{mock_dependencies, mock_env},
+        ContractResult, Empty, SystemResult, WasmQuery,
+    };
+
+    fn uninstall(address: &str) -> ActiontMsg {
+        ActiontMsg::UninstallPlugin {
+            address: address.into(),
+        }
+    }
+
+    #[test]
+    fn validators_can_reject_actions() {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, msg } => {
+                let ValidatorQueryMsg::ValidateActions { sender, .. } = from_json(msg).unwrap();
+                let res = ValidationResponse {
+                    allowed: contract_addr == "lenient" || sender == "trusted",
+                    reason: Some("untrusted sender".into()),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&res).unwrap()))
+            }
+            _ => unimplemented!(),
+        });
+        let env = mock_env();
+        let actions = [ActiontMsg::Freeze {}];
+        PLUGINS.save(&mut deps.storage, "lenient", &PluginKind::Validator).unwrap();
+        PLUGINS.save(&mut deps.storage, "hook", &PluginKind::Hook).unwrap();
+        assert!(assert_plugins_validation(deps.as_ref(), &env, "sender", &actions).is_ok());
+
+        PLUGINS.save(&mut deps.storage, "strict", &PluginKind::Validator).unwrap();
+        let err = assert_plugins_validation(deps.as_ref(), &env, "sender", &actions).unwrap_err();
+        assert!(matches!(err, AccountError::PluginRejected { plugin, .. } if plugin == "strict"));
+        assert!(assert_plugins_validation(deps.as_ref(), &env, "trusted", &actions).is_ok());
+
+        // a validator rejecting everything can still be removed
+        let actions = [uninstall("strict")];
+        assert!(assert_plugins_validation(deps.as_ref(), &env, "sender", &actions).is_ok());
+    }
+
+    #[test]
+    fn hooks_are_skipped_when_only_uninstalling() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        PLUGINS.save(&mut deps.storage, "hook", &PluginKind::Hook).unwrap();
+        PLUGINS.save(&mut deps.storage, "validator", &PluginKind::Validator).unwrap();
+
+        let msgs = plugin_hook_msgs(&deps.storage, &env, "sender", &[ActiontMsg::Freeze {}]).unwrap();
+        assert_eq!(msgs.len(), 1);
+        assert!(matches!(&msgs[0], CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) if contract_addr == "hook"));
+
+        let msgs = plugin_hook_msgs(&deps.storage, &env, "sender", &[uninstall("hook")]).unwrap();
+        assert!(msgs.is_empty());
+        let msgs = plugin_hook_msgs(&deps.storage, &env, "sender", &[uninstall("hook"), ActiontMsg::Freeze {}]).unwrap();
+        assert_eq!(msgs.len(), 1);
+    }
+
+    #[test]
+    fn account_cant_be_its_own_plugin() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let querier = QuerierWrapper::<Empty>::new(&deps.querier);
+        let res = try_installing_plugin(
+            &querier,
+            &mut deps.storage,
+            &env,
+            env.contract.address.to_string(),
+            PluginKind::Hook,
+        );
+        assert!(res.is_err());
+        assert!(try_uninstalling_plugin(&mut deps.storage, "missing".into()).is_err());
+    }
+
+    #[test]
+    fn delayed_removals_pass_a_rejecting_validator() {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|_| {
+            let res = ValidationResponse {
+                allowed: false,
+                reason: Some("rejects everything".into()),
+            };
+            SystemResult::Ok(ContractResult::Ok(to_json_binary(&res).unwrap()))
+        });
+        let mut env = mock_env();
+        PLUGINS.save(&mut deps.storage, "strict", &PluginKind::Validator).unwrap();
+        let timelock = TimelockConfig {
+            delay: 60,
+            thresholds: vec![],
+            tokens: vec![],
+        };
+
+        // the removal is allowed through and delayed by the timelock
+        let actions = [uninstall("strict")];
+        assert!(assert_plugins_validation(deps.as_ref(), &env, "sender", &actions).is_ok());
+        assert_eq!(timelock.delayed_actions(&actions), vec![true]);
+        try_queueing_action(&mut deps.storage, &env, &timelock, uninstall("strict")).unwrap();
+
+        // and so is executing it once it's ready, unlike any other queued action
+        env.block.time = env.block.time.plus_seconds(60);
+        let execute = [ActiontMsg::ExecuteQueued { id: 1 }];
+        assert_eq!(timelock.delayed_actions(&execute), vec![false]);
+        assert!(assert_plugins_validation(deps.as_ref(), &env, "sender", &execute).is_ok());
+        assert!(plugin_hook_msgs(&deps.storage, &env, "sender", &execute).unwrap().is_empty());
+
+        try_queueing_action(&mut deps.storage, &env, &timelock, ActiontMsg::Freeze {}).unwrap();
+        let execute = [ActiontMsg::ExecuteQueued { id: 2 }];
+        assert!(assert_plugins_validation(deps.as_ref(), &env, "sender", &execute).is_err());
+    }
+}
@@ -0,0 +1,705 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    BankMsg, Coin, CosmosMsg, Env, IbcMsg, Response, StdError, StdResult, Storage, Timestamp, Uint128, WasmMsg,
+};
+
+use crate::{assert_ibc_channels, state::POLICY, AccountError, AccountResult, ActiontMsg};
+
+
+const SECONDS_IN_DAY: u64 = 86400;
+
+
+#[cw_serde]
+pub enum MsgKind {
+    Bank,
+    Staking,
+    Distribution,
+    Gov,
+    Ibc,
+    Wasm,
+    Stargate,
+    Custom,
+}
+
+#[cw_serde]
+pub enum PolicyRule {
+    /// Maximum amount of a denom that can leave the account in one transaction. Cw20 tokens use the contract address as the denom
+    MaxAmount {
+        denom: String,
+        amount: Uint128,
+    },
+    /// The only addresses that can receive the funds or the tokens
+    AllowedRecipients {
+        addresses: Vec<String>,
+    },
+    /// Seconds since the start of the UTC day between which the account can move assets. The window wraps around midnight if `start` is after `end`
+    TimeWindow {
+        start: u64,
+        end: u64,
+    },
+    /// Kinds of messages the account can't produce
+    BlockedMsgKinds {
+        kinds: Vec<MsgKind>,
+    },
+    /// Stargate messages whose type url contains any of these strings
+    BlockedTypeUrls {
+        type_urls: Vec<String>,
+    },
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct Policy {
+    pub rules: Vec<PolicyRule>,
+}
+
+#[cw_serde]
+pub struct PolicyViolation {
+    /// Position of the rule in the policy
+    pub index: u32,
+    /// The rule rejecting the actions
+    pub rule: PolicyRule,
+    /// Explanation of the rejection
+    pub reason: String,
+}
+
+#[cw_serde]
+pub struct SimulatePolicyResponse {
+    /// Whether the actions would pass the policy
+    pub allowed: bool,
+    /// The first rule that would reject the actions
+    pub violation: Option<PolicyViolation>,
+}
+
+
+/// What a message produced by an action does from the point of view of the policy
+struct PolicyItem {
+    kind: MsgKind,
+    recipient: Option<String>,
+    type_url: Option<String>,
+    funds: Vec<Coin>,
+    /// Encoded messages whose funds and recipients aren't known
+    opaque: bool,
+}
+
+impl PolicyItem {
+    fn new(kind: MsgKind, recipient: Option<&str>, funds: Vec<Coin>) -> Self {
+        Self {
+            kind,
+            recipient: recipient.map(|r| r.to_string()),
+            type_url: None,
+            funds,
+            opaque: false,
+        }
+    }
+
+    fn from_msg(msg: &CosmosMsg) -> Self {
+        match msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                Self::new(MsgKind::Bank, Some(to_address), amount.clone())
+            }
+            CosmosMsg::Bank(BankMsg::Burn { amount }) => Self::new(MsgKind::Bank, None, amount.clone()),
+            CosmosMsg::Ibc(IbcMsg::Transfer {
+                to_address, amount, ..
+            }) => Self::new(MsgKind::Ibc, Some(to_address), vec![amount.clone()]),
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                funds,
+                ..
+            }) => Self::new(MsgKind::Wasm, Some(contract_addr), funds.clone()),
+            CosmosMsg::Wasm(WasmMsg::Instantiate { funds, .. }) => {
+                Self::new(MsgKind::Wasm, None, funds.clone())
+            }
+            CosmosMsg::Stargate { type_url, .. } => Self {
+                type_url: Some(type_url.clone()),
+                opaque: true,
+                ..Self::new(MsgKind::Stargate, None, vec![])
+            },
+            CosmosMsg::Staking(_) => Self::new(MsgKind::Staking, None, vec![]),
+            CosmosMsg::Distribution(_) => Self::new(MsgKind::Distribution, None, vec![]),
+            CosmosMsg::Gov(_) => Self::new(MsgKind::Gov, None, vec![]),
+            CosmosMsg::Ibc(_) => Self::new(MsgKind::Ibc, None, vec![]),
+            CosmosMsg::Wasm(_) => Self::new(MsgKind::Wasm, None, vec![]),
+            _ => Self {
+                opaque: true,
+                ..Self::new(MsgKind::Custom, None, vec![])
+            },
+        }
+    }
+
+    /// Messages an action will produce once executed. `attached` are the funds sent along with the call
+    fn from_action(action: &ActiontMsg, attached: &[Coin]) -> Vec<Self> {
+        use ActiontMsg::*;
+
+        let cw20 = |contract: &str, amount| {
+            vec![Coin {
+                denom: contract.to_string(),
+                amount,
+            }]
+        };
+
+        match action {
+            Execute { msgs } => msgs.iter().map(Self::from_msg).collect(),
+
+            IbcTransfer { to, amount, .. } => {
+                vec![Self::new(MsgKind::Ibc, Some(to), vec![amount.clone()])]
+            }
+
+            TransferCw20 {
+                contract,
+                amount,
+                recipient,
+            }
+            | SendCw20 {
+                contract,
+                amount,
+                recipient,
+                ..
+            } => vec![Self::new(MsgKind::Wasm, Some(recipient), cw20(contract, *amount))],
+
+            TransferToken { recipient, .. } => vec![Self::new(MsgKind::Wasm, Some(recipient), vec![])],
+
+            TransferTokens {
+                token_ids,
+                recipient,
+                ..
+            } => token_ids
+                .iter()
+                .map(|_| Self::new(MsgKind::Wasm, Some(recipient), vec![]))
+                .collect(),
+
+            // the balances are only known at execution
+            WithdrawAll {
+                recipient,
+                include_native,
+                ..
+            } => {
+                let mut items = vec![Self::new(MsgKind::Wasm, Some(recipient), vec![])];
+                if *include_native {
+                    items.push(Self::new(MsgKind::Bank, Some(recipient), vec![]));
+                }
+                items
+            }
+
+            SendToken { contract, .. } => vec![Self::new(MsgKind::Wasm, Some(contract), vec![])],
+
+            // the attached funds are forwarded to the minter by every mint action
+            MintToken { minter, .. } => vec![Self::new(MsgKind::Wasm, Some(minter), attached.to_vec())],
+
+            // an approved address can take the tokens out of the account
+            Approve { spender, .. } => vec![Self::new(MsgKind::Wasm, Some(spender), vec![])],
+            ApproveAll { operator, .. } => vec![Self::new(MsgKind::Wasm, Some(operator), vec![])],
+
+            Burn { .. } => vec![Self::new(MsgKind::Wasm, None, vec![])],
+
+            // the grantee can spend up to the limits on fees
+            FeeGrant {
+                grantee,
+                allowance,
+                periodic,
+                ..
+            } => {
+                let mut funds = vec![];
+                if let Some(allowance) = allowance {
+                    funds.extend(allowance.spend_limit.iter().cloned());
+                }
+                if let Some(periodic) = periodic {
+                    funds.extend(periodic.period_spend_limit.iter().cloned());
+                }
+                vec![Self {
+                    type_url: Some("/cosmos.feegrant.v1beta1.MsgGrantAllowance".to_string()),
+                    ..Self::new(MsgKind::Stargate, Some(grantee), funds)
+                }]
+            }
+
+            RevokeFeeGrant { .. } => vec![Self {
+                type_url: Some("/cosmos.feegrant.v1beta1.MsgRevokeAllowance".to_string()),
+                ..Self::new(MsgKind::Stargate, None, vec![])
+            }],
+
+            Delegate { .. } | Undelegate { .. } | Redelegate { .. } => {
+                vec![Self::new(MsgKind::Staking, None, vec![])]
+            }
+
+            ClaimRewards { .. } => vec![Self::new(MsgKind::Distribution, None, vec![])],
+
+            // the future rewards are sent to the withdraw address
+            SetWithdrawAddress { address } => {
+                vec![Self::new(MsgKind::Distribution, Some(address), vec![])]
+            }
+
+            Vote { .. } | VoteWeighted { .. } => vec![Self::new(MsgKind::Gov, None, vec![])],
+
+            // standing orders are checked for one installment or the whole cap when set up and again on every payout
+            SchedulePayment {
+                recipient, amount, ..
+            } => vec![Self::new(MsgKind::Bank, Some(recipient), amount.clone())],
+
+            AuthorizePull { merchant, cap, .. } => {
+                vec![Self::new(MsgKind::Bank, Some(merchant), cap.clone())]
+            }
+
+            GrantExecutor { executor, fee, .. } => {
+                if fee.is_empty() {
+                    vec![]
+                } else {
+                    vec![Self::new(MsgKind::Bank, Some(executor), fee.clone())]
+                }
+            }
+
+            ExecuteOnChild { .. } => vec![Self::new(MsgKind::Wasm, None, vec![])],
+
+            Revoke { .. }
+            | RevokeAll { .. }
+            | ForgetTokens { .. }
+            | UpdateKnownTokens { .. }
+            | UpdateIbcConfig { .. }
+            | UpdateTimelock { .. }
+            | ExecuteQueued { .. }
+            | CancelQueued { .. }
+            | CancelPayment { .. }
+            | RevokePull { .. }
+            | RevokeExecutor { .. }
+            | InstallPlugin { .. }
+            | UninstallPlugin { .. }
+            | UpdatePolicy { .. }
+            | UpdateTwoFactor { .. }
+            | RemoveCredentials { .. }
+            | ApproveMigration { .. }
+            | UpdateGuardians { .. }
+            | VetoRecovery {}
+            | UpdateWatchedCollections { .. }
+            | UpdateReceiveConfig { .. }
+            | Freeze {}
+            | Unfreeze {} => vec![],
+        }
+    }
+}
+
+
+/// Funds leaving the account once the action is executed, including standing orders and spending limits.
+/// Cw20 tokens use the contract address as the denom. Funds attached to the call aren't included
+pub fn outgoing_funds(action: &ActiontMsg) -> Vec<Coin> {
+    PolicyItem::from_action(action, &[])
+        .into_iter()
+        .flat_map(|item| item.funds)
+        .filter(|c| !c.amount.is_zero())
+        .collect()
+}
+
+
+impl Policy {
+    pub fn validate(&self) -> StdResult<()> {
+        for rule in self.rules.iter() {
+            if let PolicyRule::TimeWindow { start, end } = rule {
+                if *start >= SECONDS_IN_DAY || *end >= SECONDS_IN_DAY {
+                    return Err(StdError::generic_err(
+                        "Time window must be within a day in seconds",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the actions against every rule and return the first violated one
+    pub fn check(
+        &self,
+        actions: &[ActiontMsg],
+        attached: &[Coin],
+        now: &Timestamp,
+    ) -> Result<(), PolicyViolation> {
+        if self.rules.is_empty() {
+            return Ok(());
+        }
+        let items = actions
+            .iter()
+            .flat_map(|action| PolicyItem::from_action(action, attached))
+            .collect::<Vec<PolicyItem>>();
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            if let Some(reason) = rule.violation(&items, now) {
+                return Err(PolicyViolation {
+                    index: index as u32,
+                    rule: rule.clone(),
+                    reason,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn simulate(
+        &self,
+        actions: &[ActiontMsg],
+        attached: &[Coin],
+        now: &Timestamp,
+    ) -> SimulatePolicyResponse {
+        let violation = self.check(actions, attached, now).err();
+        SimulatePolicyResponse {
+            allowed: violation.is_none(),
+            violation,
+        }
+    }
+}
+
+
+impl PolicyRule {
+    fn violation(&self, items: &[PolicyItem], now: &Timestamp) -> Option<String> {
+        use PolicyRule::*;
+
+        match self {
+            MaxAmount { denom, amount } => {
+                if let Some(item) = items.iter().find(|item| item.opaque) {
+                    return Some(format!("{:?} messages can't be checked against the limit", item.kind));
+                }
+                let total = items
+                    .iter()
+                    .flat_map(|item| item.funds.iter())
+                    .filter(|c| c.denom == *denom)
+                    .fold(Uint128::zero(), |acc, c| acc.saturating_add(c.amount));
+                (total > *amount).then(|| format!("Sending {total}{denom} while the limit is {amount}"))
+            }
+
+            AllowedRecipients { addresses } => {
+                if let Some(item) = items.iter().find(|item| item.opaque) {
+                    return Some(format!("{:?} messages can't be checked against the recipients", item.kind));
+                }
+                items
+                    .iter()
+                    .filter_map(|item| item.recipient.as_ref())
+                    .find(|r| !addresses.contains(r))
+                    .map(|r| format!("{r} is not an allowed recipient"))
+            }
+
+            TimeWindow { start, end } => {
+                let time = now.seconds() % SECONDS_IN_DAY;
+                let inside = if start <= end {
+                    time >= *start && time <= *end
+                } else {
+                    time >= *start || time <= *end
+                };
+                (!inside && !items.is_empty())
+                    .then(|| format!("Only allowed between {start} and {end} seconds of the day"))
+            }
+
+            BlockedMsgKinds { kinds } => items
+                .iter()
+                .find(|item| kinds.contains(&item.kind))
+                .map(|item| format!("{:?} messages are blocked", item.kind)),
+
+            BlockedTypeUrls { type_urls } => items
+                .iter()
+                .filter_map(|item| item.type_url.as_ref())
+                .find(|url| {
+                    let url = url.to_lowercase();
+                    type_urls.iter().any(|t| url.contains(&t.to_lowercase()))
+                })
+                .map(|url| format!("{url} messages are blocked")),
+        }
+    }
+}
+
+
+/// Check the raw messages against the rules built into the account and everything against the rules of the owner
+pub fn simulate_policy(
+    store: &dyn Storage,
+    env: &Env,
+    builtin: &Policy,
+    actions: &[ActiontMsg],
+    attached: &[Coin],
+) -> StdResult<SimulatePolicyResponse> {
+    let raw = actions
+        .iter()
+        .filter(|a| matches!(a, ActiontMsg::Execute { .. }))
+        .cloned()
+        .collect::<Vec<ActiontMsg>>();
+
+    let res = builtin.simulate(&raw, attached, &env.block.time);
+    if !res.allowed {
+        return Ok(res);
+    }
+    Ok(POLICY
+        .may_load(store)?
+        .unwrap_or_default()
+        .simulate(actions, attached, &env.block.time))
+}
+
+pub fn assert_policy(
+    store: &dyn Storage,
+    env: &Env,
+    builtin: &Policy,
+    actions: &[ActiontMsg],
+    attached: &[Coin],
+) -> AccountResult<()> {
+    assert_ibc_channels(store, actions)?;
+    let res = simulate_policy(store, env, builtin, actions, attached)?;
+    match res.violation {
+        Some(violation) => Err(AccountError::PolicyViolation(violation.reason)),
+        None => Ok(()),
+    }
+}
+
+/// Payouts made without an action of the owner, like standing orders and executor fees, still have to pass the policy.
+/// The built-in rules never concern bank messages and are skipped
+pub fn assert_payouts_policy(store: &dyn Storage, env: &Env, payouts: &[BankMsg]) -> AccountResult<()> {
+    let msgs = payouts.iter().cloned().map(CosmosMsg::Bank).collect();
+    assert_policy(store, env, &Policy::default(), &[ActiontMsg::Execute { msgs }], &[])
+}
+
+pub fn try_updating_policy(storage: &mut dyn Storage, rules: Vec<PolicyRule>) -> AccountResult {
+    if rules.is_empty() {
+        POLICY.remove(storage);
+    } else {
+        let policy = Policy { rules };
+        policy.validate()?;
+        POLICY.save(storage, &policy)?;
+    }
+    Ok(Response::new().add_attribute("action", "update_policy"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BasicAllowance;
+    use cosmwasm_std::{
+        coin, coins,
+        testing::{mock_dependencies, mock_env},
+        Binary,
+    };
+
+    fn send(to: &str, amount: u128) -> ActiontMsg {
+        ActiontMsg::Execute {
+            msgs: vec![BankMsg::Send {
+                to_address: to.into(),
+                amount: coins(amount, "ustars"),
+            }
+            .into()],
+        }
+    }
+
+    fn policy(rules: Vec<PolicyRule>) -> Policy {
+        Policy { rules }
+    }
+
+    #[test]
+    fn max_amount_is_summed_across_the_batch() {
+        let policy = policy(vec![PolicyRule::MaxAmount {
+            denom: "ustars".into(),
+            amount: Uint128::new(100),
+        }]);
+        let now = mock_env().block.time;
+
+        assert!(policy.check(&[send("alice", 60)], &[], &now).is_ok());
+        let violation = policy.check(&[send("alice", 60), send("bob", 60)], &[], &now).unwrap_err();
+        assert_eq!(violation.index, 0);
+
+        // cw20 transfers use the contract address as the denom
+        let cw20 = ActiontMsg::TransferCw20 {
+            contract: "ustars".into(),
+            amount: Uint128::new(101),
+            recipient: "alice".into(),
+        };
+        assert!(policy.check(&[cw20], &[], &now).is_err());
+
+        // funds attached to a mint are forwarded to the minter
+        let mint = ActiontMsg::MintToken {
+            minter: "minter".into(),
+            msg: Binary::default(),
+        };
+        assert!(policy.check(std::slice::from_ref(&mint), &coins(100, "ustars"), &now).is_ok());
+        assert!(policy.check(&[mint], &coins(101, "ustars"), &now).is_err());
+    }
+
+    #[test]
+    fn standing_orders_and_fees_are_checked_against_recipients() {
+        let policy = policy(vec![PolicyRule::AllowedRecipients {
+            addresses: vec!["alice".into()],
+        }]);
+        let now = mock_env().block.time;
+
+        let payment = |recipient: &str| ActiontMsg::SchedulePayment {
+            recipient: recipient.into(),
+            amount: coins(1, "ustars"),
+            interval: 60,
+            start: None,
+            end: None,
+        };
+        assert!(policy.check(&[payment("alice")], &[], &now).is_ok());
+        assert!(policy.check(&[payment("bob")], &[], &now).is_err());
+
+        let pull = ActiontMsg::AuthorizePull {
+            merchant: "bob".into(),
+            cap: coins(1, "ustars"),
+            period: 60,
+            end: None,
+        };
+        assert!(policy.check(&[pull], &[], &now).is_err());
+
+        let executor = |fee| ActiontMsg::GrantExecutor {
+            executor: "bob".into(),
+            permissions: vec![],
+            expiration: None,
+            fee,
+            interval: 0,
+        };
+        assert!(policy.check(&[executor(vec![])], &[], &now).is_ok());
+        assert!(policy.check(&[executor(coins(1, "ustars"))], &[], &now).is_err());
+
+        let withdraw = ActiontMsg::SetWithdrawAddress {
+            address: "bob".into(),
+        };
+        assert!(policy.check(&[withdraw], &[], &now).is_err());
+    }
+
+    #[test]
+    fn fee_grant_limits_count_as_outgoing_funds() {
+        let grant = ActiontMsg::FeeGrant {
+            grantee: "alice".into(),
+            allowance: Some(BasicAllowance {
+                spend_limit: coins(500, "ustars"),
+                expiration: None,
+            }),
+            periodic: None,
+            allowed_msgs: None,
+        };
+        assert_eq!(outgoing_funds(&grant), coins(500, "ustars"));
+
+        let policy = policy(vec![PolicyRule::BlockedTypeUrls {
+            type_urls: vec!["FEEGRANT".into()],
+        }]);
+        assert!(policy.check(&[grant], &[], &mock_env().block.time).is_err());
+    }
+
+    #[test]
+    fn time_window_wraps_around_midnight() {
+        // the mocked block time is 8619 seconds into the day
+        let now = mock_env().block.time;
+        let actions = [send("alice", 1)];
+
+        let morning = policy(vec![PolicyRule::TimeWindow { start: 0, end: 3600 }]);
+        assert!(morning.check(&actions, &[], &now).is_err());
+
+        let night = policy(vec![PolicyRule::TimeWindow { start: 80000, end: 10000 }]);
+        assert!(night.check(&actions, &[], &now).is_ok());
+
+        // actions producing no messages are never outside of the window
+        assert!(morning.check(&[ActiontMsg::Freeze {}], &[], &now).is_ok());
+
+        assert!(policy(vec![PolicyRule::TimeWindow { start: 0, end: SECONDS_IN_DAY }])
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn builtin_rules_only_apply_to_raw_messages() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let builtin = policy(vec![PolicyRule::BlockedMsgKinds {
+            kinds: vec![MsgKind::Wasm],
+        }]);
+
+        let raw = ActiontMsg::Execute {
+            msgs: vec![WasmMsg::Execute {
+                contract_addr: "contract".into(),
+                msg: Binary::default(),
+                funds: vec![],
+            }
+            .into()],
+        };
+        let err = assert_policy(&deps.storage, &env, &builtin, &[raw], &[]).unwrap_err();
+        assert!(matches!(err, AccountError::PolicyViolation(_)));
+
+        let typed = ActiontMsg::TransferToken {
+            collection: "collection".into(),
+            token_id: "1".into(),
+            recipient: "alice".into(),
+        };
+        assert!(assert_policy(&deps.storage, &env, &builtin, &[typed], &[]).is_ok());
+    }
+
+    #[test]
+    fn payouts_are_checked_against_the_stored_policy() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let payout = BankMsg::Send {
+            to_address: "bob".into(),
+            amount: vec![coin(10, "ustars")],
+        };
+        assert!(assert_payouts_policy(&deps.storage, &env, std::slice::from_ref(&payout)).is_ok());
+
+        try_updating_policy(
+            &mut deps.storage,
+            vec![PolicyRule::AllowedRecipients {
+                addresses: vec!["alice".into()],
+            }],
+        )
+        .unwrap();
+        assert!(assert_payouts_policy(&deps.storage, &env, std::slice::from_ref(&payout)).is_err());
+
+        // an empty set of rules removes the policy
+        try_updating_policy(&mut deps.storage, vec![]).unwrap();
+        assert!(assert_payouts_policy(&deps.storage, &env, &[payout]).is_ok());
+    }
+
+    #[test]
+    fn raw_ibc_transfers_need_an_allowed_channel() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let transfer = |channel: &str| ActiontMsg::Execute {
+            msgs: vec![IbcMsg::Transfer {
+                channel_id: channel.into(),
+                to_address: "remote".into(),
+                amount: coin(10, "ustars"),
+                timeout: env.block.time.into(),
+            }
+            .into()],
+        };
+        let builtin = Policy::default();
+
+        let err = assert_policy(&deps.storage, &env, &builtin, &[transfer("channel-0")], &[]);
+        assert!(matches!(err, Err(AccountError::ChannelNotAllowed(_))));
+
+        crate::state::IBC_CONFIG
+            .save(&mut deps.storage, &crate::IbcConfig {
+                channels: vec!["channel-0".into()],
+                default_timeout: 600,
+            })
+            .unwrap();
+        assert!(assert_policy(&deps.storage, &env, &builtin, &[transfer("channel-0")], &[]).is_ok());
+        assert!(assert_policy(&deps.storage, &env, &builtin, &[transfer("channel-1")], &[]).is_err());
+    }
+
+    #[test]
+    fn opaque_messages_break_amount_and_recipient_rules() {
+        let now = mock_env().block.time;
+        let stargate = ActiontMsg::Execute {
+            msgs: vec![CosmosMsg::Stargate {
+                type_url: "/cosmos.bank.v1beta1.MsgSend".into(),
+                value: Binary::default(),
+            }],
+        };
+        assert!(policy(vec![]).check(std::slice::from_ref(&stargate), &[], &now).is_ok());
+
+        let max = policy(vec![PolicyRule::MaxAmount {
+            denom: "ustars".into(),
+            amount: Uint128::new(100),
+        }]);
+        assert!(max.check(std::slice::from_ref(&stargate), &[], &now).is_err());
+
+        let recipients = policy(vec![PolicyRule::AllowedRecipients {
+            addresses: vec!["alice".into()],
+        }]);
+        assert!(recipients.check(&[stargate], &[], &now).is_err());
+
+        // typed fee grants are known and pass
+        let grant = ActiontMsg::FeeGrant {
+            grantee: "alice".into(),
+            allowance: None,
+            periodic: None,
+            allowed_msgs: None,
+        };
+        assert!(recipients.check(&[grant], &[], &now).is_ok());
+    }
+}
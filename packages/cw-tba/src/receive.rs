@@ -0,0 +1,118 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, from_json, Binary, Env, Response, StdError, StdResult, Storage, Timestamp};
+
+use crate::{
+    state::{RECEIVE_CONFIG, TOKEN_ATTRIBUTES},
+    AccountError, AccountResult, TokenInfo,
+};
+
+
+/// Maximum length of a label attached to a received token
+pub const MAX_LABEL_LENGTH: usize = 64;
+
+
+/// Follow-up instruction passed as the `msg` of a cw721 `send_nft` to the account
+#[cw_serde]
+pub enum ReceivePayload {
+    /// Transfer the token further to the given address
+    Forward {
+        recipient: String,
+    },
+    /// Send the token to a contract, e.g. for staking
+    Stake {
+        contract: String,
+        msg: Binary,
+    },
+    /// Keep the token in the account until the given time
+    Lock {
+        until: Timestamp,
+    },
+    /// Attach a label to the token
+    Tag {
+        label: String,
+    },
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct ReceiveConfig {
+    /// Whether tokens sent with an unrecognised payload are bounced back instead of being kept
+    pub reject_unknown: bool,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct TokenAttributes {
+    /// Label given to the token when it was received
+    pub label: Option<String>,
+    /// Time before which the token can't leave the account
+    pub locked_until: Option<Timestamp>,
+}
+
+
+impl ReceiveConfig {
+    /// Payload attached to a received token. Empty messages carry no payload
+    pub fn parse(&self, msg: &[u8]) -> StdResult<Option<ReceivePayload>> {
+        if msg.is_empty() {
+            return Ok(None);
+        }
+        match from_json::<ReceivePayload>(msg) {
+            Ok(payload) => Ok(Some(payload)),
+            Err(_) if self.reject_unknown => Err(StdError::generic_err("Unknown receive payload")),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+
+impl ReceivePayload {
+    pub fn validate(&self, now: &Timestamp) -> StdResult<()> {
+        match self {
+            ReceivePayload::Lock { until } if until <= now => {
+                Err(StdError::generic_err("Lock must end in the future"))
+            }
+            ReceivePayload::Tag { label } if label.is_empty() || label.len() > MAX_LABEL_LENGTH => {
+                Err(StdError::generic_err(format!(
+                    "Label must be between 1 and {MAX_LABEL_LENGTH} characters"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+
+impl TokenAttributes {
+    pub fn is_locked(&self, now: &Timestamp) -> bool {
+        self.locked_until.map(|until| until > *now).unwrap_or_default()
+    }
+}
+
+
+pub fn is_token_locked(store: &dyn Storage, env: &Env, collection: &str, token_id: &str) -> bool {
+    TOKEN_ATTRIBUTES
+        .may_load(store, (collection, token_id))
+        .ok()
+        .flatten()
+        .map(|attrs| attrs.is_locked(&env.block.time))
+        .unwrap_or_default()
+}
+
+/// Refuse to move tokens locked by the payload they were received with
+pub fn assert_not_locked(store: &dyn Storage, env: &Env, tokens: &[TokenInfo]) -> AccountResult<()> {
+    for token in tokens {
+        ensure!(
+            !is_token_locked(store, env, &token.collection, &token.id),
+            AccountError::TokenLocked {
+                collection: token.collection.clone(),
+                token_id: token.id.clone(),
+            }
+        );
+    }
+    Ok(())
+}
+
+pub fn try_updating_receive_config(storage: &mut dyn Storage, reject_unknown: bool) -> AccountResult {
+    RECEIVE_CONFIG.save(storage, &ReceiveConfig { reject_unknown })?;
+    Ok(Response::new().add_attribute("action", "update_receive_config"))
+}
@@ -1,7 +1,11 @@
-use crate::{common::TokenInfo, ActiontMsg};
+use crate::{common::TokenInfo, ActiontMsg, NftBackend};
 use smart_account_auth::CredentialData;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Coin;
+use cosmwasm_std::{Coin, QuerierWrapper, StdResult, Storage};
+
+
+/// Number of accounts checked for cycles if the registry doesn't say otherwise
+pub const DEFAULT_CYCLE_DEPTH: u32 = 3;
 
 
 #[cw_serde]
@@ -9,6 +13,8 @@ pub struct RegistryParams {
     pub allowed_code_ids: Vec<u64>,
     pub creation_fees: Vec<Coin>,
     pub managers: Vec<String>,
+    /// How many accounts up the chain of holders to check for tokens controlling themselves
+    pub max_cycle_depth: Option<u32>,
 }
 
 /// An extenstion for [cw83::CreateAccountMsg]
@@ -26,3 +32,48 @@ pub struct TokenAccountPayload {
     /// Optional parameter to create an account on behalf of another user that holds the token
     pub create_for: Option<String>,
 }
+
+
+#[cw_serde]
+enum RegistryQueryMsg {
+    RegistryParams {},
+    CollectionBackend { collection: String },
+    Ics721Bridges {},
+}
+
+/// Depth of the cycle checks configured in the registry
+pub fn query_cycle_depth(querier: &QuerierWrapper, registry: &str) -> StdResult<u32> {
+    let params: RegistryParams =
+        querier.query_wasm_smart(registry, &RegistryQueryMsg::RegistryParams {})?;
+    Ok(params.max_cycle_depth.unwrap_or(DEFAULT_CYCLE_DEPTH))
+}
+
+/// Backend of the collection recorded in the registry
+pub fn query_collection_backend(
+    querier: &QuerierWrapper,
+    registry: &str,
+    collection: &str,
+) -> StdResult<NftBackend> {
+    querier.query_wasm_smart(
+        registry,
+        &RegistryQueryMsg::CollectionBackend {
+            collection: collection.to_string(),
+        },
+    )
+}
+
+/// ICS-721 contracts registered in the registry
+pub fn query_ics721_bridges(querier: &QuerierWrapper, registry: &str) -> StdResult<Vec<String>> {
+    querier.query_wasm_smart(registry, &RegistryQueryMsg::Ics721Bridges {})
+}
+
+
+/// Backend the registry of the account recorded for the collection
+pub fn collection_backend(
+    querier: &QuerierWrapper,
+    store: &dyn Storage,
+    collection: &str,
+) -> StdResult<NftBackend> {
+    let registry = crate::state::REGISTRY_ADDRESS.load(store)?;
+    query_collection_backend(querier, &registry, collection)
+}
@@ -0,0 +1,73 @@
+use cosmwasm_std::{
+    Coin, DistributionMsg, Env, GovMsg, QuerierWrapper, Response, StakingMsg, StdResult, VoteOption,
+    WeightedVoteOption,
+};
+
+
+pub fn try_delegating(validator: String, amount: Coin) -> StdResult<Response> {
+    Ok(Response::new()
+        .add_message(StakingMsg::Delegate { validator, amount })
+        .add_attribute("action", "delegate"))
+}
+
+pub fn try_undelegating(validator: String, amount: Coin) -> StdResult<Response> {
+    Ok(Response::new()
+        .add_message(StakingMsg::Undelegate { validator, amount })
+        .add_attribute("action", "undelegate"))
+}
+
+pub fn try_redelegating(
+    src_validator: String,
+    dst_validator: String,
+    amount: Coin,
+) -> StdResult<Response> {
+    Ok(Response::new()
+        .add_message(StakingMsg::Redelegate {
+            src_validator,
+            dst_validator,
+            amount,
+        })
+        .add_attribute("action", "redelegate"))
+}
+
+/// Withdraw the rewards from the given validators or from every validator delegated to if none are given
+pub fn try_claiming_rewards(
+    querier: &QuerierWrapper,
+    env: &Env,
+    validators: Vec<String>,
+) -> StdResult<Response> {
+    let validators = if validators.is_empty() {
+        querier
+            .query_all_delegations(env.contract.address.as_str())?
+            .into_iter()
+            .map(|d| d.validator)
+            .collect()
+    } else {
+        validators
+    };
+    let msgs = validators
+        .into_iter()
+        .map(|validator| DistributionMsg::WithdrawDelegatorReward { validator });
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("action", "claim_rewards"))
+}
+
+pub fn try_setting_withdraw_address(address: String) -> StdResult<Response> {
+    Ok(Response::new()
+        .add_message(DistributionMsg::SetWithdrawAddress { address })
+        .add_attribute("action", "set_withdraw_address"))
+}
+
+pub fn try_voting(proposal_id: u64, vote: VoteOption) -> StdResult<Response> {
+    Ok(Response::new()
+        .add_message(GovMsg::Vote { proposal_id, vote })
+        .add_attribute("action", "vote"))
+}
+
+pub fn try_voting_weighted(proposal_id: u64, options: Vec<WeightedVoteOption>) -> StdResult<Response> {
+    Ok(Response::new()
+        .add_message(GovMsg::VoteWeighted { proposal_id, options })
+        .add_attribute("action", "vote_weighted"))
+}
@@ -0,0 +1,344 @@
+use cosmwasm_std::{Deps, Env, Order, StdResult, Storage};
+use cw_storage_plus::{Bound, Item, Map};
+
+use crate::{
+    query_child_nodes, query_cw20_balances, with_token_metadata, AccountTreeNode, Cw20Coin, ExecutorGrant, ExecutorsResponse, FeeGrantInfo,
+    FeeGrantsResponse, IbcConfig, KnownCollection, KnownCollectionsResponse, KnownTokensResponse, PluginInfo,
+    PluginKind, PluginsResponse, Policy, PullAuthorization, PullAuthorizationsResponse, QueuedAction,
+    QueuedActionsResponse, ReceiveConfig, RemoteHolder, ScheduledPayment, ScheduledPaymentsResponse, Status,
+    TimelockConfig, TokenApproval, TokenApprovalsResponse, TokenAttributes, TokenInfo, WatchedCollection,
+    WatchedCollectionsResponse, DEFAULT_TREE_DEPTH, MAX_CW20_BALANCES, MAX_TREE_DEPTH,
+};
+
+pub static REGISTRY_ADDRESS: Item<String> = Item::new("r");
+pub static TOKEN_INFO: Item<TokenInfo> = Item::new("t");
+pub static STATUS: Item<Status> = Item::new("s");
+pub static MINT_CACHE: Item<String> = Item::new("m");
+pub static KNOWN_TOKENS: Map<(&str, &str), bool> = Map::new("k");
+pub static KNOWN_COLLECTIONS: Map<&str, u32> = Map::new("kc");
+pub static KNOWN_CW20S: Map<&str, bool> = Map::new("c");
+pub static IBC_CONFIG: Item<IbcConfig> = Item::new("i");
+pub static FEE_GRANTS: Map<&str, FeeGrantInfo> = Map::new("f");
+pub static TIMELOCK: Item<TimelockConfig> = Item::new("l");
+pub static QUEUE: Map<u64, QueuedAction> = Map::new("q");
+pub static QUEUE_COUNT: Item<u64> = Item::new("n");
+pub static PAYMENTS: Map<u64, ScheduledPayment> = Map::new("y");
+pub static PAYMENT_COUNT: Item<u64> = Item::new("z");
+pub static PULLS: Map<&str, PullAuthorization> = Map::new("w");
+pub static EXECUTORS: Map<&str, ExecutorGrant> = Map::new("e");
+pub static PLUGINS: Map<&str, PluginKind> = Map::new("x");
+pub static POLICY: Item<Policy> = Item::new("o");
+pub static RECEIVE_CONFIG: Item<ReceiveConfig> = Item::new("rc");
+pub static TOKEN_ATTRIBUTES: Map<(&str, &str), TokenAttributes> = Map::new("ta");
+pub static WATCHED_COLLECTIONS: Map<&str, WatchedCollection> = Map::new("wc");
+/// Keyed by collection, token id and spender. Operators of a whole collection use an empty token id
+pub static TOKEN_APPROVALS: Map<(&str, &str, &str), TokenApproval> = Map::new("ap");
+/// Set while the bound token is escrowed by an ICS-721 bridge
+pub static REMOTE_HOLDER: Item<RemoteHolder> = Item::new("rh");
+
+
+/// Remember a token and keep the count of its collection up to date
+pub fn save_known_token(store: &mut dyn Storage, collection: &str, id: &str) -> StdResult<()> {
+    if KNOWN_TOKENS.has(store, (collection, id)) {
+        return Ok(());
+    }
+    KNOWN_TOKENS.save(store, (collection, id), &true)?;
+    KNOWN_COLLECTIONS.update(store, collection, |count| -> StdResult<u32> {
+        Ok(count.unwrap_or_default() + 1)
+    })?;
+    Ok(())
+}
+
+/// Forget a token and keep the count of its collection up to date
+pub fn remove_known_token(store: &mut dyn Storage, collection: &str, id: &str) -> StdResult<()> {
+    if !KNOWN_TOKENS.has(store, (collection, id)) {
+        return Ok(());
+    }
+    KNOWN_TOKENS.remove(store, (collection, id));
+    let count = KNOWN_COLLECTIONS
+        .may_load(store, collection)?
+        .unwrap_or_default()
+        .saturating_sub(1);
+    if count == 0 {
+        KNOWN_COLLECTIONS.remove(store, collection);
+    } else {
+        KNOWN_COLLECTIONS.save(store, collection, &count)?;
+    }
+    Ok(())
+}
+
+/// Forget a token leaving the account together with its attributes and approvals
+pub fn forget_token(store: &mut dyn Storage, collection: &str, id: &str) -> StdResult<()> {
+    remove_known_token(store, collection, id)?;
+    TOKEN_ATTRIBUTES.remove(store, (collection, id));
+    remove_token_approvals(store, collection, id)
+}
+
+/// Count the known tokens of every collection from scratch
+pub fn rebuild_known_collections(store: &mut dyn Storage) -> StdResult<()> {
+    let tokens = KNOWN_TOKENS
+        .keys(store, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, String)>>>()?;
+    KNOWN_COLLECTIONS.clear(store);
+    for (collection, _) in tokens {
+        KNOWN_COLLECTIONS.update(store, collection.as_str(), |count| -> StdResult<u32> {
+            Ok(count.unwrap_or_default() + 1)
+        })?;
+    }
+    Ok(())
+}
+
+/// Forget the approvals of a token leaving the account. Collections drop them on transfer anyway
+pub fn remove_token_approvals(store: &mut dyn Storage, collection: &str, id: &str) -> StdResult<()> {
+    let spenders = TOKEN_APPROVALS
+        .prefix((collection, id))
+        .keys(store, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+    for spender in spenders {
+        TOKEN_APPROVALS.remove(store, (collection, id, spender.as_str()));
+    }
+    Ok(())
+}
+
+
+const DEFAULT_BATCH_SIZE: u32 = 100;
+
+pub fn known_tokens(
+    deps: Deps,
+    collection: Option<String>,
+    start_after: Option<TokenInfo>,
+    limit: Option<u32>,
+    with_metadata: Option<bool>,
+) -> StdResult<KnownTokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
+
+    let tokens = match collection {
+        Some(collection) => {
+            let start_id = start_after.map(|t| t.id);
+            KNOWN_TOKENS
+                .prefix(collection.as_str())
+                .keys(
+                    deps.storage,
+                    start_id.as_deref().map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(limit)
+                .map(|id| {
+                    id.map(|id| TokenInfo {
+                        collection: collection.clone(),
+                        id,
+                    })
+                })
+                .collect::<StdResult<Vec<TokenInfo>>>()?
+        }
+        None => {
+            let start = start_after
+                .as_ref()
+                .map(|t| Bound::exclusive((t.collection.as_str(), t.id.as_str())));
+            KNOWN_TOKENS
+                .keys(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .map(|key| key.map(|(collection, id)| TokenInfo { collection, id }))
+                .collect::<StdResult<Vec<TokenInfo>>>()?
+        }
+    };
+
+    let registry = REGISTRY_ADDRESS.load(deps.storage)?;
+    with_token_metadata(&deps.querier, &registry, tokens, with_metadata)
+}
+
+pub fn skipped_known_tokens(
+    deps: Deps,
+    skip: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<Vec<TokenInfo>> {
+    let skip = skip.unwrap_or(0) as usize;
+    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
+
+    let tokens: StdResult<Vec<TokenInfo>> = KNOWN_TOKENS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .enumerate()
+        .filter(|(i, _)| *i >= skip)
+        .take(limit)
+        .map(|(_, kt)| {
+            let kp = kt?;
+            Ok(TokenInfo {
+                collection: kp.0,
+                id: kp.1,
+            })
+        })
+        .collect();
+
+    tokens
+}
+
+pub fn known_collections(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<KnownCollectionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
+
+    KNOWN_COLLECTIONS
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(collection, tokens)| KnownCollection { collection, tokens }))
+        .collect()
+}
+
+pub fn cw20_balances(
+    deps: Deps,
+    env: &Env,
+    skip: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Cw20Coin>> {
+    let skip = skip.unwrap_or(0) as usize;
+    let limit = limit.unwrap_or(MAX_CW20_BALANCES).min(MAX_CW20_BALANCES) as usize;
+
+    let contracts = KNOWN_CW20S
+        .keys(deps.storage, None, None, Order::Ascending)
+        .skip(skip)
+        .take(limit)
+        .collect::<StdResult<Vec<String>>>()?;
+
+    Ok(query_cw20_balances(&deps.querier, contracts, env.contract.address.as_str()))
+}
+
+pub fn fee_grants(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<FeeGrantsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    FEE_GRANTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, grant)| grant))
+        .collect()
+}
+
+pub fn queued_actions(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<QueuedActionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    QUEUE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, queued)| queued))
+        .collect()
+}
+
+pub fn scheduled_payments(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ScheduledPaymentsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    PAYMENTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, payment)| payment))
+        .collect()
+}
+
+pub fn pull_authorizations(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PullAuthorizationsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    PULLS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, auth)| auth))
+        .collect()
+}
+
+pub fn executors(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ExecutorsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_BATCH_SIZE) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    EXECUTORS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, grant)| grant))
+        .collect()
+}
+
+pub fn plugins(deps: Deps) -> StdResult<PluginsResponse> {
+    PLUGINS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(address, kind)| PluginInfo { address, kind }))
+        .collect()
+}
+
+pub fn policy(deps: Deps) -> StdResult<Policy> {
+    Ok(POLICY.may_load(deps.storage)?.unwrap_or_default())
+}
+
+pub fn token_attributes(
+    deps: Deps,
+    collection: String,
+    token_id: String,
+) -> StdResult<TokenAttributes> {
+    Ok(TOKEN_ATTRIBUTES
+        .may_load(deps.storage, (collection.as_str(), token_id.as_str()))?
+        .unwrap_or_default())
+}
+
+pub fn token_approvals(deps: Deps) -> StdResult<TokenApprovalsResponse> {
+    TOKEN_APPROVALS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, approval)| approval))
+        .collect()
+}
+
+pub fn watched_collections(deps: Deps) -> StdResult<WatchedCollectionsResponse> {
+    WATCHED_COLLECTIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, w)| w))
+        .collect()
+}
+
+pub fn account_tree(deps: Deps, env: Env, depth: Option<u32>) -> StdResult<AccountTreeNode> {
+    let depth = depth.unwrap_or(DEFAULT_TREE_DEPTH).min(MAX_TREE_DEPTH);
+    let address = env.contract.address.to_string();
+    let registry = REGISTRY_ADDRESS.load(deps.storage)?;
+
+    let tokens = KNOWN_TOKENS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|key| key.map(|(collection, id)| TokenInfo { collection, id }))
+        .collect::<StdResult<Vec<TokenInfo>>>()?;
+
+    let children = if depth > 0 {
+        query_child_nodes(&deps.querier, &registry, &address, tokens, depth)?
+    } else {
+        vec![]
+    };
+
+    Ok(AccountTreeNode {
+        address,
+        token: TOKEN_INFO.load(deps.storage)?,
+        children,
+    })
+}
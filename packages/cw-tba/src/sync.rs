@@ -0,0 +1,144 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, DepsMut, Env, Order, QuerierWrapper, Response, StdResult, Storage};
+use cw_storage_plus::Bound;
+
+use crate::{
+    collection_backend,
+    state::{forget_token, save_known_token, KNOWN_TOKENS, WATCHED_COLLECTIONS},
+    AccountError, AccountResult, NftBackend,
+};
+
+
+/// Maximum number of collections an account can watch
+pub const MAX_WATCHED_COLLECTIONS: usize = 10;
+/// Tokens checked per collection by a sync call if not specified
+pub const DEFAULT_SYNC_LIMIT: u32 = 30;
+/// Maximum number of tokens checked per collection by a sync call
+pub const MAX_SYNC_LIMIT: u32 = 100;
+
+
+#[cw_serde]
+pub struct WatchedCollection {
+    /// Contract address of the collection
+    pub collection: String,
+    /// Last token id checked by the sync. Empty once a full pass is complete
+    pub cursor: Option<String>,
+}
+
+pub type WatchedCollectionsResponse = Vec<WatchedCollection>;
+
+
+/// Ids of the tokens held by the owner after the cursor in ascending order and
+/// whether they are the last page of the collection
+pub fn query_owned_page(
+    querier: &QuerierWrapper,
+    backend: &NftBackend,
+    collection: &str,
+    owner: &str,
+    cursor: Option<String>,
+    limit: u32,
+) -> StdResult<(Vec<String>, bool)> {
+    let mut ids = backend.query_tokens(
+        querier,
+        collection,
+        owner.to_string(),
+        cursor.clone(),
+        Some(limit),
+    )?
+    .tokens;
+
+    // not every backend supports pagination
+    ids.sort();
+    if let Some(cursor) = cursor {
+        ids.retain(|id| *id > cursor);
+    }
+    let complete = ids.len() < limit as usize;
+    ids.truncate(limit as usize);
+    Ok((ids, complete))
+}
+
+
+pub fn try_updating_watched_collections(storage: &mut dyn Storage, collections: Vec<String>) -> AccountResult {
+    ensure!(
+        collections.len() <= MAX_WATCHED_COLLECTIONS,
+        AccountError::Generic(format!("Can't watch more than {MAX_WATCHED_COLLECTIONS} collections"))
+    );
+    let current = WATCHED_COLLECTIONS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+    for collection in current.iter().filter(|c| !collections.contains(c)) {
+        WATCHED_COLLECTIONS.remove(storage, collection);
+    }
+    // collections that are still watched keep their cursors
+    for collection in collections {
+        if !WATCHED_COLLECTIONS.has(storage, &collection) {
+            WATCHED_COLLECTIONS.save(
+                storage,
+                &collection,
+                &WatchedCollection {
+                    collection: collection.clone(),
+                    cursor: None,
+                },
+            )?;
+        }
+    }
+    Ok(Response::new().add_attribute("action", "update_watched_collections"))
+}
+
+pub fn try_syncing_known_tokens(deps: DepsMut, env: Env, limit: Option<u32>) -> AccountResult {
+    let limit = limit.unwrap_or(DEFAULT_SYNC_LIMIT).min(MAX_SYNC_LIMIT);
+    let account = env.contract.address.to_string();
+
+    let watched = WATCHED_COLLECTIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, w)| w))
+        .collect::<StdResult<Vec<WatchedCollection>>>()?;
+
+    let mut added = 0u32;
+    let mut pruned = 0u32;
+
+    for WatchedCollection { collection, cursor } in watched {
+        let backend = collection_backend(&deps.querier, deps.storage, &collection)?;
+        let (ids, complete) =
+            match query_owned_page(&deps.querier, &backend, &collection, &account, cursor.clone(), limit) {
+                Ok(page) => page,
+                // a broken collection must not block the others
+                Err(_) => continue,
+            };
+        let next = if complete { None } else { ids.last().cloned() };
+
+        // known tokens in the checked range that weren't returned are no longer held
+        let known = KNOWN_TOKENS
+            .prefix(collection.as_str())
+            .keys(
+                deps.storage,
+                cursor.as_deref().map(Bound::exclusive),
+                next.as_deref().map(Bound::inclusive),
+                Order::Ascending,
+            )
+            .collect::<StdResult<Vec<String>>>()?;
+
+        for id in known.iter().filter(|id| !ids.contains(id)) {
+            forget_token(deps.storage, collection.as_str(), id.as_str())?;
+            pruned += 1;
+        }
+        for id in ids.iter().filter(|id| !known.contains(id)) {
+            save_known_token(deps.storage, collection.as_str(), id.as_str())?;
+            added += 1;
+        }
+
+        WATCHED_COLLECTIONS.save(
+            deps.storage,
+            &collection,
+            &WatchedCollection {
+                collection: collection.clone(),
+                cursor: next,
+            },
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "sync_known_tokens")
+        .add_attribute("added", added.to_string())
+        .add_attribute("pruned", pruned.to_string()))
+}
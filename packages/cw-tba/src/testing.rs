@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_json,
+    testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR},
+    to_json_binary, Binary, ContractResult, Empty, OwnedDeps, StdResult, SystemError, SystemResult, WasmQuery,
+};
+
+use crate::{
+    state::{REGISTRY_ADDRESS, TOKEN_INFO},
+    Cw721Msg, NftBackend, OwnerOfResponse, QueryMsg, RegistryParams, TokenInfo,
+};
+
+pub const REGISTRY: &str = "registry";
+pub const BRIDGE: &str = "bridge";
+pub const COLLECTION: &str = "collection";
+
+
+#[cw_serde]
+pub enum RegistryQueryMsg {
+    RegistryParams {},
+    CollectionBackend { collection: String },
+    Ics721Bridges {},
+}
+
+pub fn token(id: &str) -> TokenInfo {
+    TokenInfo {
+        collection: COLLECTION.into(),
+        id: id.into(),
+    }
+}
+
+/// Registry, cw721 collection and accounts answering the queries of the mocked account bound to `bound`.
+/// `accounts` are the addresses of the token bound accounts and the ids of their tokens,
+/// `owners` the holders of the tokens by id
+pub fn mock_chain(
+    deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier, Empty>,
+    bound: &str,
+    accounts: &[(&str, &str)],
+    owners: &[(&str, &str)],
+) {
+    REGISTRY_ADDRESS.save(&mut deps.storage, &REGISTRY.to_string()).unwrap();
+    TOKEN_INFO.save(&mut deps.storage, &token(bound)).unwrap();
+
+    let mut accounts = accounts
+        .iter()
+        .map(|(address, id)| (address.to_string(), token(id)))
+        .collect::<BTreeMap<String, TokenInfo>>();
+    accounts.insert(MOCK_CONTRACT_ADDR.to_string(), token(bound));
+    let owners = owners
+        .iter()
+        .map(|(id, owner)| (id.to_string(), owner.to_string()))
+        .collect::<BTreeMap<String, String>>();
+
+    deps.querier.update_wasm(move |query| {
+        let WasmQuery::Smart { contract_addr, msg } = query else {
+            return SystemResult::Err(SystemError::Unknown {});
+        };
+        let res: StdResult<Binary> = match contract_addr.as_str() {
+            REGISTRY => match from_json(msg).unwrap() {
+                RegistryQueryMsg::RegistryParams {} => to_json_binary(&RegistryParams {
+                    allowed_code_ids: vec![],
+                    creation_fees: vec![],
+                    managers: vec![],
+                    max_cycle_depth: None,
+                }),
+                RegistryQueryMsg::CollectionBackend { .. } => to_json_binary(&NftBackend::Cw721),
+                RegistryQueryMsg::Ics721Bridges {} => to_json_binary(&vec![BRIDGE]),
+            },
+            COLLECTION => match from_json(msg).unwrap() {
+                Cw721Msg::OwnerOf { token_id, .. } => match owners.get(&token_id) {
+                    Some(owner) => to_json_binary(&OwnerOfResponse {
+                        owner: owner.clone(),
+                        approvals: vec![],
+                    }),
+                    None => return SystemResult::Ok(ContractResult::Err("Token not found".into())),
+                },
+                _ => unimplemented!(),
+            },
+            address => match (accounts.get(address), from_json(msg)) {
+                (Some(_), Ok(QueryMsg::Registry {})) => to_json_binary(REGISTRY),
+                (Some(token), Ok(QueryMsg::Token {})) => to_json_binary(token),
+                _ => {
+                    return SystemResult::Err(SystemError::NoSuchContract {
+                        addr: address.to_string(),
+                    })
+                }
+            },
+        };
+        SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+    });
+}
@@ -0,0 +1,339 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, Coin, CosmosMsg, Env, Response, Storage, Timestamp, Uint128};
+
+use crate::{
+    outgoing_funds,
+    state::{QUEUE, QUEUE_COUNT, TIMELOCK},
+    AccountError, AccountResult, ActiontMsg, TokenInfo,
+};
+
+
+#[cw_serde]
+pub struct TimelockConfig {
+    /// Seconds a delayed action has to wait in the queue before it can be executed
+    pub delay: u64,
+    /// Transfers above these amounts are delayed. Cw20 tokens use the contract address as the denom
+    pub thresholds: Vec<Coin>,
+    /// Tokens whose transfers are delayed
+    pub tokens: Vec<TokenInfo>,
+}
+
+#[cw_serde]
+pub struct QueuedAction {
+    /// Id of the action in the queue
+    pub id: u64,
+    /// The delayed action
+    pub action: ActiontMsg,
+    /// Time after which the action can be executed
+    pub ready_at: Timestamp,
+}
+
+pub type QueuedActionsResponse = Vec<QueuedAction>;
+
+
+fn sum_by_denom(funds: impl IntoIterator<Item = Coin>) -> Vec<Coin> {
+    let mut totals: Vec<Coin> = vec![];
+    for coin in funds {
+        match totals.iter_mut().find(|c| c.denom == coin.denom) {
+            Some(total) => total.amount = total.amount.saturating_add(coin.amount),
+            None => totals.push(coin),
+        }
+    }
+    totals
+}
+
+impl TimelockConfig {
+    fn exceeds(&self, denom: &str, amount: Uint128) -> bool {
+        self.thresholds
+            .iter()
+            .any(|t| t.denom == denom && amount > t.amount)
+    }
+
+    fn exceeds_total(&self, funds: Vec<Coin>) -> bool {
+        sum_by_denom(funds)
+            .iter()
+            .any(|c| self.exceeds(&c.denom, c.amount))
+    }
+
+    fn is_locked_token(&self, collection: &str, token_id: &str) -> bool {
+        self.tokens
+            .iter()
+            .any(|t| t.collection == collection && t.id == token_id)
+    }
+
+    /// Which of the actions have to wait in the queue before they can be executed.
+    /// Outgoing amounts are summed across the whole batch so that a transfer can't be split to stay under a threshold
+    pub fn delayed_actions(&self, actions: &[ActiontMsg]) -> Vec<bool> {
+        let totals = sum_by_denom(actions.iter().flat_map(outgoing_funds));
+        let exceeded: Vec<&str> = totals
+            .iter()
+            .filter(|c| self.exceeds(&c.denom, c.amount))
+            .map(|c| c.denom.as_str())
+            .collect();
+
+        actions
+            .iter()
+            .map(|action| {
+                self.requires_delay(action)
+                    || outgoing_funds(action)
+                        .iter()
+                        .any(|c| exceeded.contains(&c.denom.as_str()))
+            })
+            .collect()
+    }
+
+    /// Whether an action has to wait in the queue on its own
+    pub fn requires_delay(&self, action: &ActiontMsg) -> bool {
+        use ActiontMsg::*;
+
+        match action {
+            Execute { msgs } => {
+                // the content of raw messages can't be inspected
+                msgs.iter().any(|m| matches!(m, CosmosMsg::Stargate { .. }))
+                    || self.exceeds_total(outgoing_funds(action))
+            }
+
+            IbcTransfer { amount, .. } => self.exceeds(&amount.denom, amount.amount),
+
+            TransferCw20 {
+                contract, amount, ..
+            }
+            | SendCw20 {
+                contract, amount, ..
+            } => self.exceeds(contract, *amount),
+
+            TransferToken {
+                collection,
+                token_id,
+                ..
+            } => self.is_locked_token(collection, token_id),
+
+            TransferTokens {
+                collection,
+                token_ids,
+                ..
+            } => token_ids
+                .iter()
+                .any(|id| self.is_locked_token(collection, id)),
+
+            // the withdrawn tokens and balances are only known at execution
+            WithdrawAll {
+                include_native,
+                collections,
+                ..
+            } => {
+                (*include_native && !self.thresholds.is_empty())
+                    || self.tokens.iter().any(|t| {
+                        collections
+                            .as_ref()
+                            .map(|c| c.contains(&t.collection))
+                            .unwrap_or(true)
+                    })
+            }
+
+            SendToken {
+                collection,
+                token_id,
+                ..
+            } => self.is_locked_token(collection, token_id),
+
+            Approve {
+                collection,
+                token_id,
+                ..
+            } => self.is_locked_token(collection, token_id),
+
+            ApproveAll { collection, .. } => self.tokens.iter().any(|t| &t.collection == collection),
+
+            Burn {
+                collection,
+                token_id,
+            } => self.is_locked_token(collection, token_id),
+
+            // loosening the rules or setting up standing orders must not be faster than the rules themselves
+            UpdateTimelock { .. }
+            | UpdatePolicy { .. }
+            | UpdateTwoFactor { .. }
+            | RemoveCredentials { .. }
+            | UpdateGuardians { .. }
+            | UninstallPlugin { .. }
+            | RevokeExecutor { .. }
+            | SchedulePayment { .. }
+            | AuthorizePull { .. } => true,
+
+            GrantExecutor { fee, .. } => !fee.is_empty(),
+
+            _ => false,
+        }
+    }
+}
+
+
+pub fn try_queueing_action(
+    storage: &mut dyn Storage,
+    env: &Env,
+    timelock: &TimelockConfig,
+    action: ActiontMsg,
+) -> AccountResult {
+    let id = QUEUE_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    let ready_at = env.block.time.plus_seconds(timelock.delay);
+    QUEUE.save(storage, id, &QueuedAction { id, action, ready_at })?;
+    QUEUE_COUNT.save(storage, &id)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "queue_action")
+        .add_attribute("id", id.to_string())
+        .add_attribute("ready_at", ready_at.seconds().to_string()))
+}
+
+/// Take a queued action out of the queue once its delay is over
+pub fn take_ready_action(storage: &mut dyn Storage, env: &Env, id: u64) -> AccountResult<ActiontMsg> {
+    let queued = QUEUE
+        .may_load(storage, id)?
+        .ok_or(AccountError::Generic(format!("No queued action with id {id}")))?;
+    ensure!(
+        env.block.time >= queued.ready_at,
+        AccountError::Generic(format!("Action {id} can't be executed before {}", queued.ready_at))
+    );
+    QUEUE.remove(storage, id);
+    Ok(queued.action)
+}
+
+pub fn try_cancelling_queued(storage: &mut dyn Storage, id: u64) -> AccountResult {
+    ensure!(
+        QUEUE.has(storage, id),
+        AccountError::Generic(format!("No queued action with id {id}"))
+    );
+    QUEUE.remove(storage, id);
+    Ok(Response::new()
+        .add_attribute("action", "cancel_queued")
+        .add_attribute("id", id.to_string()))
+}
+
+pub fn try_updating_timelock(
+    storage: &mut dyn Storage,
+    delay: u64,
+    thresholds: Vec<Coin>,
+    tokens: Vec<TokenInfo>,
+) -> AccountResult {
+    if delay == 0 {
+        TIMELOCK.remove(storage);
+    } else {
+        TIMELOCK.save(
+            storage,
+            &TimelockConfig {
+                delay,
+                thresholds,
+                tokens,
+            },
+        )?;
+    }
+    Ok(Response::new().add_attribute("action", "update_timelock"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{coin, coins, testing::{mock_dependencies, mock_env}, BankMsg};
+
+    fn config() -> TimelockConfig {
+        TimelockConfig {
+            delay: 3600,
+            thresholds: coins(100, "ustars"),
+            tokens: vec![TokenInfo {
+                collection: "collection".into(),
+                id: "1".into(),
+            }],
+        }
+    }
+
+    fn send(amount: u128) -> ActiontMsg {
+        ActiontMsg::Execute {
+            msgs: vec![BankMsg::Send {
+                to_address: "recipient".into(),
+                amount: coins(amount, "ustars"),
+            }
+            .into()],
+        }
+    }
+
+    fn ibc(amount: u128) -> ActiontMsg {
+        ActiontMsg::IbcTransfer {
+            channel: "channel-0".into(),
+            to: "recipient".into(),
+            amount: coin(amount, "ustars"),
+            memo: None,
+            timeout: None,
+        }
+    }
+
+    #[test]
+    fn split_transfers_are_delayed_together() {
+        let config = config();
+
+        assert_eq!(config.delayed_actions(&[send(60), ibc(30)]), vec![false, false]);
+        assert_eq!(config.delayed_actions(&[send(60), ibc(60)]), vec![true, true]);
+        // actions moving other assets aren't delayed with them
+        assert_eq!(
+            config.delayed_actions(&[send(60), ibc(60), ActiontMsg::Freeze {}]),
+            vec![true, true, false]
+        );
+    }
+
+    #[test]
+    fn locked_tokens_are_delayed() {
+        let config = config();
+        let transfer = |token_id: &str| ActiontMsg::TransferToken {
+            collection: "collection".into(),
+            token_id: token_id.into(),
+            recipient: "recipient".into(),
+        };
+
+        assert!(config.requires_delay(&transfer("1")));
+        assert!(!config.requires_delay(&transfer("2")));
+        assert!(config.requires_delay(&ActiontMsg::ApproveAll {
+            collection: "collection".into(),
+            operator: "operator".into(),
+            expires: None,
+        }));
+    }
+
+    #[test]
+    fn loosening_the_rules_is_delayed() {
+        let config = config();
+
+        assert!(config.requires_delay(&ActiontMsg::UpdateTimelock {
+            delay: 0,
+            thresholds: vec![],
+            tokens: vec![],
+        }));
+        assert!(config.requires_delay(&ActiontMsg::UpdatePolicy { rules: vec![] }));
+        assert!(config.requires_delay(&ActiontMsg::UninstallPlugin {
+            address: "plugin".into(),
+        }));
+        assert!(config.requires_delay(&ActiontMsg::RevokeExecutor {
+            executor: "executor".into(),
+        }));
+        assert!(config.requires_delay(&ActiontMsg::RemoveCredentials { ids: vec![] }));
+        assert!(!config.requires_delay(&ActiontMsg::CancelQueued { id: 1 }));
+    }
+
+    #[test]
+    fn queued_actions_wait_for_the_delay() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+
+        try_queueing_action(&mut deps.storage, &env, &config(), send(1000)).unwrap();
+        assert!(take_ready_action(&mut deps.storage, &env, 1).is_err());
+
+        env.block.time = env.block.time.plus_seconds(3600);
+        assert_eq!(take_ready_action(&mut deps.storage, &env, 1).unwrap(), send(1000));
+        // an action can only be taken once
+        assert!(take_ready_action(&mut deps.storage, &env, 1).is_err());
+
+        try_queueing_action(&mut deps.storage, &env, &config(), send(1000)).unwrap();
+        try_cancelling_queued(&mut deps.storage, 2).unwrap();
+        assert!(try_cancelling_queued(&mut deps.storage, 2).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    ensure, BlockInfo, CosmosMsg, Env, Order, QuerierWrapper, Response, StdResult, Storage, WasmMsg,
+};
+use smart_account_auth::Expiration;
+
+use crate::{
+    assert_not_locked, collection_backend,
+    state::{TOKEN_APPROVALS, TOKEN_ATTRIBUTES},
+    verify_nft_ownership, AccountError, AccountResult, Cw721Msg, NftBackend, TokenInfo,
+};
+
+
+/// Maximum number of approvals tracked by an account so all of them can be revoked in one transaction
+pub const MAX_TOKEN_APPROVALS: usize = 30;
+
+
+#[cw_serde]
+pub struct TokenApproval {
+    /// Contract address of the collection
+    pub collection: String,
+    /// Approved token. The spender is an operator of the whole collection if omitted
+    pub token_id: Option<String>,
+    /// Address allowed to transfer the tokens
+    pub spender: String,
+    /// Time or height after which the approval is no longer valid. Never expires if omitted
+    pub expires: Option<Expiration>,
+}
+
+pub type TokenApprovalsResponse = Vec<TokenApproval>;
+
+
+impl TokenApproval {
+    pub fn is_expired(&self, block: &BlockInfo) -> bool {
+        self.expires
+            .as_ref()
+            .map(|e| e.is_expired(block))
+            .unwrap_or(false)
+    }
+
+    /// Message granting the approval on the collection
+    pub fn approve_msg(&self) -> StdResult<CosmosMsg> {
+        let msg = match &self.token_id {
+            Some(token_id) => Cw721Msg::Approve {
+                spender: self.spender.clone(),
+                token_id: token_id.clone(),
+                expires: self.expires.clone(),
+            },
+            None => Cw721Msg::ApproveAll {
+                operator: self.spender.clone(),
+                expires: self.expires.clone(),
+            },
+        };
+        self.wasm_msg(&msg)
+    }
+
+    /// Message revoking the approval on the collection
+    pub fn revoke_msg(&self) -> StdResult<CosmosMsg> {
+        let msg = match &self.token_id {
+            Some(token_id) => Cw721Msg::Revoke {
+                spender: self.spender.clone(),
+                token_id: token_id.clone(),
+            },
+            None => Cw721Msg::RevokeAll {
+                operator: self.spender.clone(),
+            },
+        };
+        self.wasm_msg(&msg)
+    }
+
+    fn wasm_msg(&self, msg: &Cw721Msg) -> StdResult<CosmosMsg> {
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: self.collection.clone(),
+            msg: cosmwasm_std::to_json_binary(msg)?,
+            funds: vec![],
+        }))
+    }
+}
+
+
+/// Forget all the tracked approvals and return the messages revoking the ones still in effect.
+/// Tokens that already left the account can't be revoked by it and are skipped
+pub fn revoke_token_approvals(
+    querier: &QuerierWrapper,
+    store: &mut dyn Storage,
+    env: &Env,
+) -> StdResult<Vec<CosmosMsg>> {
+    let approvals = TOKEN_APPROVALS
+        .range(store, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, approval)| approval))
+        .collect::<StdResult<Vec<TokenApproval>>>()?;
+    TOKEN_APPROVALS.clear(store);
+
+    approvals
+        .into_iter()
+        .filter(|approval| !approval.is_expired(&env.block))
+        .filter(|approval| match &approval.token_id {
+            // approvals are only granted on cw721 collections
+            Some(id) => verify_nft_ownership(
+                querier,
+                &NftBackend::Cw721,
+                env.contract.address.as_str(),
+                TokenInfo {
+                    collection: approval.collection.clone(),
+                    id: id.clone(),
+                },
+            )
+            .is_ok(),
+            None => true,
+        })
+        .map(|approval| approval.revoke_msg())
+        .collect()
+}
+
+
+/// Refuse approvals on collections whose backend has no notion of them
+fn assert_supports_approvals(querier: &QuerierWrapper, storage: &dyn Storage, collection: &str) -> AccountResult<()> {
+    let backend = collection_backend(querier, storage, collection)?;
+    ensure!(
+        backend.supports_approvals(),
+        AccountError::Generic(format!("Approvals aren't supported by the {backend:?} backend"))
+    );
+    Ok(())
+}
+
+/// Approve a spender for a token or an operator for the whole collection if no token id is given
+pub fn try_approving(
+    querier: &QuerierWrapper,
+    env: &Env,
+    storage: &mut dyn Storage,
+    collection: String,
+    token_id: Option<String>,
+    spender: String,
+    expires: Option<Expiration>,
+) -> AccountResult {
+    assert_supports_approvals(querier, storage, &collection)?;
+    // an approved address could move the locked tokens out of the account
+    let tokens = match &token_id {
+        Some(id) => vec![TokenInfo {
+            collection: collection.clone(),
+            id: id.clone(),
+        }],
+        None => TOKEN_ATTRIBUTES
+            .prefix(collection.as_str())
+            .keys(storage, None, None, Order::Ascending)
+            .map(|id| id.map(|id| TokenInfo { collection: collection.clone(), id }))
+            .collect::<StdResult<Vec<TokenInfo>>>()?,
+    };
+    assert_not_locked(storage, env, &tokens)?;
+
+    let key = (collection.as_str(), token_id.as_deref().unwrap_or_default(), spender.as_str());
+    if !TOKEN_APPROVALS.has(storage, key) {
+        let count = TOKEN_APPROVALS.keys(storage, None, None, Order::Ascending).count();
+        ensure!(
+            count < MAX_TOKEN_APPROVALS,
+            AccountError::Generic(format!("Can't track more than {MAX_TOKEN_APPROVALS} approvals"))
+        );
+    }
+    let approval = TokenApproval {
+        collection: collection.clone(),
+        token_id: token_id.clone(),
+        spender: spender.clone(),
+        expires,
+    };
+    TOKEN_APPROVALS.save(storage, key, &approval)?;
+
+    Ok(Response::default()
+        .add_message(approval.approve_msg()?)
+        .add_attribute("action", if token_id.is_some() { "approve" } else { "approve_all" })
+        .add_attribute("collection", collection)
+        .add_attribute("spender", spender))
+}
+
+/// Revoke a spender of a token or an operator of the whole collection if no token id is given
+pub fn try_revoking(
+    querier: &QuerierWrapper,
+    storage: &mut dyn Storage,
+    collection: String,
+    token_id: Option<String>,
+    spender: String,
+) -> AccountResult {
+    assert_supports_approvals(querier, storage, &collection)?;
+    // approvals given with a raw message aren't tracked but can still be revoked
+    TOKEN_APPROVALS.remove(
+        storage,
+        (collection.as_str(), token_id.as_deref().unwrap_or_default(), spender.as_str()),
+    );
+    let approval = TokenApproval {
+        collection: collection.clone(),
+        token_id: token_id.clone(),
+        spender: spender.clone(),
+        expires: None,
+    };
+
+    Ok(Response::default()
+        .add_message(approval.revoke_msg()?)
+        .add_attribute("action", if token_id.is_some() { "revoke" } else { "revoke_all" })
+        .add_attribute("collection", collection)
+        .add_attribute("spender", spender))
+}
@@ -0,0 +1,217 @@
+use cosmwasm_std::{
+    ensure, BankMsg, Binary, Coin, CosmosMsg, Env, Order, QuerierWrapper, Response, StdResult, Storage,
+};
+use std::collections::BTreeMap;
+
+use crate::{
+    assert_no_cycle, assert_not_locked, collection_backend, is_token_locked,
+    state::{forget_token, KNOWN_TOKENS},
+    AccountError, AccountResult, NftBackend, TokenInfo,
+};
+
+
+/// Maximum number of tokens moved by a single bulk action
+pub const MAX_BULK_TRANSFER: u32 = 50;
+
+
+pub fn try_transfering_token(
+    querier: &QuerierWrapper,
+    env: &Env,
+    storage: &mut dyn Storage,
+    collection: String,
+    token_id: String,
+    recipient: String,
+) -> AccountResult {
+    let tokens = [TokenInfo {
+        collection: collection.clone(),
+        id: token_id.clone(),
+    }];
+    assert_not_locked(storage, env, &tokens)?;
+    assert_no_cycle(querier, storage, env, &recipient, &tokens)?;
+    forget_token(storage, collection.as_str(), token_id.as_str())?;
+    let msg = collection_backend(querier, storage, &collection)?.transfer_msg(
+        &collection,
+        &token_id,
+        env.contract.address.as_str(),
+        &recipient,
+    )?;
+    Ok(Response::default()
+        .add_message(msg)
+        .add_attribute("action", "transfer_token"))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn try_sending_token(
+    querier: &QuerierWrapper,
+    env: &Env,
+    storage: &mut dyn Storage,
+    collection: String,
+    token_id: String,
+    contract: String,
+    msg: Binary,
+    funds: Vec<Coin>,
+) -> AccountResult {
+    let tokens = [TokenInfo {
+        collection: collection.clone(),
+        id: token_id.clone(),
+    }];
+    assert_not_locked(storage, env, &tokens)?;
+    assert_no_cycle(querier, storage, env, &contract, &tokens)?;
+    forget_token(storage, collection.as_str(), token_id.as_str())?;
+    let msg = collection_backend(querier, storage, &collection)?
+        .send_msg(&collection, &token_id, &contract, msg, funds)?;
+    Ok(Response::default()
+        .add_message(msg)
+        .add_attribute("action", "send_token"))
+}
+
+pub fn try_burning_token(
+    querier: &QuerierWrapper,
+    env: &Env,
+    storage: &mut dyn Storage,
+    collection: String,
+    token_id: String,
+) -> AccountResult {
+    let tokens = [TokenInfo {
+        collection: collection.clone(),
+        id: token_id.clone(),
+    }];
+    assert_not_locked(storage, env, &tokens)?;
+    forget_token(storage, collection.as_str(), token_id.as_str())?;
+    let msg = collection_backend(querier, storage, &collection)?.burn_msg(
+        &collection,
+        &token_id,
+        env.contract.address.as_str(),
+    )?;
+    Ok(Response::default()
+        .add_message(msg)
+        .add_attribute("action", "burn_token")
+        .add_attribute("collection", collection)
+        .add_attribute("token_id", token_id))
+}
+
+/// Transfer messages for the tokens, which are forgotten by the account
+fn bulk_transfer_msgs(
+    querier: &QuerierWrapper,
+    env: &Env,
+    storage: &mut dyn Storage,
+    tokens: &[TokenInfo],
+    recipient: &str,
+) -> AccountResult<Vec<CosmosMsg>> {
+    if tokens.is_empty() {
+        return Ok(vec![]);
+    }
+    assert_not_locked(storage, env, tokens)?;
+    assert_no_cycle(querier, storage, env, recipient, tokens)?;
+
+    let mut msgs = Vec::with_capacity(tokens.len());
+    let mut backends: BTreeMap<&str, NftBackend> = BTreeMap::new();
+    for token in tokens {
+        forget_token(storage, token.collection.as_str(), token.id.as_str())?;
+        let backend = match backends.get(token.collection.as_str()) {
+            Some(backend) => backend.clone(),
+            None => {
+                let backend = collection_backend(querier, storage, &token.collection)?;
+                backends.insert(token.collection.as_str(), backend.clone());
+                backend
+            }
+        };
+        msgs.push(backend.transfer_msg(
+            &token.collection,
+            &token.id,
+            env.contract.address.as_str(),
+            recipient,
+        )?);
+    }
+    Ok(msgs)
+}
+
+pub fn try_transfering_tokens(
+    querier: &QuerierWrapper,
+    env: &Env,
+    storage: &mut dyn Storage,
+    collection: String,
+    token_ids: Vec<String>,
+    recipient: String,
+) -> AccountResult {
+    ensure!(
+        !token_ids.is_empty() && token_ids.len() <= MAX_BULK_TRANSFER as usize,
+        AccountError::Generic(format!("Must transfer between 1 and {MAX_BULK_TRANSFER} tokens"))
+    );
+    let tokens = token_ids
+        .into_iter()
+        .map(|id| TokenInfo {
+            collection: collection.clone(),
+            id,
+        })
+        .collect::<Vec<TokenInfo>>();
+
+    let msgs = bulk_transfer_msgs(querier, env, storage, &tokens, &recipient)?;
+    Ok(Response::default()
+        .add_messages(msgs)
+        .add_attribute("action", "transfer_tokens")
+        .add_attribute("count", tokens.len().to_string()))
+}
+
+pub fn try_withdrawing_all(
+    querier: &QuerierWrapper,
+    env: &Env,
+    storage: &mut dyn Storage,
+    recipient: String,
+    include_native: bool,
+    collections: Option<Vec<String>>,
+    limit: Option<u32>,
+) -> AccountResult {
+    let limit = limit.unwrap_or(MAX_BULK_TRANSFER).min(MAX_BULK_TRANSFER) as usize;
+
+    // locked tokens are skipped and stay in the account
+    let mut tokens: Vec<TokenInfo> = vec![];
+    match collections {
+        Some(collections) => {
+            for collection in collections {
+                let ids = KNOWN_TOKENS
+                    .prefix(collection.as_str())
+                    .keys(storage, None, None, Order::Ascending)
+                    .filter(|id| {
+                        id.as_ref()
+                            .map(|id| !is_token_locked(storage, env, &collection, id))
+                            .unwrap_or(true)
+                    })
+                    .take(limit - tokens.len())
+                    .collect::<StdResult<Vec<String>>>()?;
+                tokens.extend(ids.into_iter().map(|id| TokenInfo {
+                    collection: collection.clone(),
+                    id,
+                }));
+            }
+        }
+        None => {
+            tokens = KNOWN_TOKENS
+                .keys(storage, None, None, Order::Ascending)
+                .filter(|key| {
+                    key.as_ref()
+                        .map(|(collection, id)| !is_token_locked(storage, env, collection, id))
+                        .unwrap_or(true)
+                })
+                .take(limit)
+                .map(|key| key.map(|(collection, id)| TokenInfo { collection, id }))
+                .collect::<StdResult<Vec<TokenInfo>>>()?;
+        }
+    }
+
+    let mut msgs = bulk_transfer_msgs(querier, env, storage, &tokens, &recipient)?;
+    if include_native {
+        let amount = querier.query_all_balances(env.contract.address.as_str())?;
+        if !amount.is_empty() {
+            msgs.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient.clone(),
+                amount,
+            }));
+        }
+    }
+
+    Ok(Response::default()
+        .add_messages(msgs)
+        .add_attribute("action", "withdraw_all")
+        .add_attribute("tokens", tokens.len().to_string()))
+}
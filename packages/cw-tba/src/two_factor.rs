@@ -0,0 +1,202 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Coin, CosmosMsg, Timestamp};
+use saa_wasm::saa_types::{Credential, CredentialId};
+
+use crate::{outgoing_funds, ActiontMsg};
+
+
+/// Seconds a proposed batch waits for the second credential before expiring
+pub const APPROVAL_TTL: u64 = 86400;
+
+
+#[cw_serde]
+pub enum SensitiveAction {
+    /// Removing stored credentials
+    RemoveCredentials,
+    /// Transfers above the thresholds. Denoms without a threshold always count as large
+    LargeTransfers,
+    /// Approving the migration of the account to another code id
+    MigrationApproval,
+    /// Giving out or changing fee grants
+    FeeGrants,
+}
+
+#[cw_serde]
+pub struct TwoFactorConfig {
+    /// Kinds of actions that need signatures from two distinct credentials
+    pub actions: Vec<SensitiveAction>,
+    /// Amounts above which a transfer counts as large. Cw20 tokens use the contract address as the denom
+    pub thresholds: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct PendingApproval {
+    /// Id of the proposal
+    pub id: u64,
+    /// Actions waiting for the second credential
+    pub actions: Vec<ActiontMsg>,
+    /// Credential that proposed the actions
+    pub proposer: CredentialId,
+    /// Time after which the proposal can't be approved
+    pub expires_at: Timestamp,
+}
+
+pub type PendingApprovalsResponse = Vec<PendingApproval>;
+
+
+/// One or several credentials authorising a signed execution
+#[cw_serde]
+#[serde(untagged)]
+pub enum SignedCredentials {
+    Single(Credential),
+    Multiple(Vec<Credential>),
+}
+
+impl SignedCredentials {
+    pub fn into_vec(self) -> Vec<Credential> {
+        match self {
+            SignedCredentials::Single(cred) => vec![cred],
+            SignedCredentials::Multiple(creds) => creds,
+        }
+    }
+}
+
+
+/// Propose / approve flow for the actions that need two credentials
+#[cw_serde]
+pub enum ApprovalMsg {
+    /// Store the actions signed by the first credential
+    Propose {
+        msgs: Vec<ActiontMsg>,
+        signed: Credential,
+    },
+    /// Sign the stored actions with another credential and execute them
+    Approve {
+        id: u64,
+        signed: Credential,
+    },
+    /// Remove a pending proposal
+    Cancel {
+        id: u64,
+    },
+}
+
+
+impl TwoFactorConfig {
+    fn covers(&self, action: &SensitiveAction) -> bool {
+        self.actions.contains(action)
+    }
+
+    fn is_large(&self, totals: &[Coin]) -> bool {
+        totals.iter().any(|c| {
+            self.thresholds
+                .iter()
+                .find(|t| t.denom == c.denom)
+                .map(|t| c.amount > t.amount)
+                .unwrap_or(true)
+        })
+    }
+
+    /// Whether a batch of actions has to be signed by two distinct credentials
+    pub fn requires_two(&self, actions: &[ActiontMsg]) -> bool {
+        use ActiontMsg::*;
+
+        let mut totals: Vec<Coin> = vec![];
+
+        for action in actions {
+            let sensitive = match action {
+                // a single credential must not be able to turn the protection off
+                // or hand the recovery of the account over to guardians of its choosing
+                UpdateTwoFactor { .. } | UpdateGuardians { .. } => true,
+                RemoveCredentials { .. } => self.covers(&SensitiveAction::RemoveCredentials),
+                ApproveMigration { .. } => self.covers(&SensitiveAction::MigrationApproval),
+                FeeGrant { .. } => self.covers(&SensitiveAction::FeeGrants),
+                // the withdrawn balance is only known at execution
+                WithdrawAll { include_native, .. } => {
+                    *include_native && self.covers(&SensitiveAction::LargeTransfers)
+                }
+                // the content of raw messages can't be inspected
+                Execute { msgs } => {
+                    self.covers(&SensitiveAction::LargeTransfers)
+                        && msgs.iter().any(|m| matches!(m, CosmosMsg::Stargate { .. }))
+                }
+                _ => false,
+            };
+            if sensitive {
+                return true;
+            }
+            for coin in outgoing_funds(action) {
+                match totals.iter_mut().find(|c| c.denom == coin.denom) {
+                    Some(total) => total.amount = total.amount.saturating_add(coin.amount),
+                    None => totals.push(coin),
+                }
+            }
+        }
+        self.covers(&SensitiveAction::LargeTransfers) && self.is_large(&totals)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{coin, coins, BankMsg};
+
+    fn config(actions: Vec<SensitiveAction>) -> TwoFactorConfig {
+        TwoFactorConfig {
+            actions,
+            thresholds: coins(100, "ustars"),
+        }
+    }
+
+    fn send(amount: u128) -> ActiontMsg {
+        ActiontMsg::Execute {
+            msgs: vec![BankMsg::Send {
+                to_address: "recipient".into(),
+                amount: coins(amount, "ustars"),
+            }
+            .into()],
+        }
+    }
+
+    #[test]
+    fn guardians_and_two_factor_updates_always_need_two() {
+        let config = config(vec![SensitiveAction::FeeGrants]);
+
+        assert!(config.requires_two(&[ActiontMsg::UpdateGuardians {
+            guardians: vec!["guardian".into()],
+            threshold: 1,
+            delay: 0,
+        }]));
+        assert!(config.requires_two(&[ActiontMsg::UpdateTwoFactor {
+            actions: vec![],
+            thresholds: vec![],
+        }]));
+        assert!(!config.requires_two(&[ActiontMsg::VetoRecovery {}]));
+    }
+
+    #[test]
+    fn large_transfers_are_summed_across_the_batch() {
+        let config = config(vec![SensitiveAction::LargeTransfers]);
+
+        assert!(!config.requires_two(&[send(60)]));
+        assert!(config.requires_two(&[send(60), send(60)]));
+        // denoms without a threshold always count as large
+        assert!(config.requires_two(&[ActiontMsg::IbcTransfer {
+            channel: "channel-0".into(),
+            to: "recipient".into(),
+            amount: coin(1, "uatom"),
+            memo: None,
+            timeout: None,
+        }]));
+    }
+
+    #[test]
+    fn uncovered_actions_need_one() {
+        let config = config(vec![SensitiveAction::MigrationApproval]);
+
+        assert!(!config.requires_two(&[send(1000)]));
+        assert!(!config.requires_two(&[ActiontMsg::RemoveCredentials { ids: vec![] }]));
+        assert!(config.requires_two(&[ActiontMsg::ApproveMigration { code_id: 1 }]));
+    }
+}